@@ -1,7 +1,23 @@
-use rsapar::{DecimalFormat, Parser, ParserConfig, ProcessedLineError, Convert, ConvertConfig};
+use rsapar::{DecimalFormat, GenerateOptions, Parser, ParserConfig, ProcessedLineError, Schema, Convert, ConvertConfig};
 use rayon::iter::{ParallelBridge, ParallelIterator};
 
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Generate the sample data of `example/fixedwidth_schema.xml` if it is missing, see `Schema::generate`
+fn sample_data() -> &'static str {
+    let file_path = "./example/fixedwidth_data.txt";
+    if !std::path::Path::new(file_path).exists() {
+        let schema = Schema::new("./example/fixedwidth_schema.xml").unwrap();
+        let occurs = [("Header", 1), ("Body", 10000), ("Footer", 1)];
+        let options = GenerateOptions {
+            seed: 1,
+            occurs: occurs.iter().map(|&(name, occurs)| (name.to_string(), occurs)).collect(),
+            ..Default::default()
+        };
+        schema.generate(std::io::BufWriter::new(std::fs::File::create(file_path).unwrap()), &options).unwrap();
+    }
+    file_path
+}
 
 fn bench_decimal_format_new(c: &mut Criterion) {
     c.bench_function("decimal_format_new", |b| {
@@ -19,10 +35,7 @@ fn bench_decimal_format_new(c: &mut Criterion) {
 fn bench_parse(c: &mut Criterion) {
     c.bench_function("parse", |b| {
         b.iter(|| {
-            let config = ParserConfig {
-                file_path: "./example/fixedwidth_data.txt".to_string(),
-                file_schema: "./example/fixedwidth_schema.xml".to_string(),
-            };
+            let config = ParserConfig::new(sample_data(), "./example/fixedwidth_schema.xml");
 
             let mut parser = Parser::new(config).unwrap();
 
@@ -35,13 +48,37 @@ fn bench_parse(c: &mut Criterion) {
     });
 }
 
+fn bench_lines(c: &mut Criterion) {
+    let file_path = sample_data();
+    let file_size = std::fs::metadata(file_path).unwrap().len();
+
+    let mut group = c.benchmark_group("lines");
+    group.throughput(Throughput::Bytes(file_size));
+
+    // Split by the "lineseparator" of the schema and by lineseparator="auto"
+    for file_schema in ["./example/fixedwidth_schema.xml", "./example/fixedwidth_schema_auto.xml"] {
+        group.bench_with_input(BenchmarkId::from_parameter(file_schema), file_schema, |b, file_schema| {
+            b.iter(|| {
+                let config = ParserConfig::new(file_path, file_schema.to_string());
+
+                let mut parser = Parser::new(config).unwrap();
+
+                for read_line in parser.lines() {
+                    if read_line.is_err() {
+                        panic!("Error reading line");
+                    }
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
 fn bench_parse_iter_par(c: &mut Criterion) {
     c.bench_function("parse_iter_par", |b| {
         b.iter(|| {
-            let config = ParserConfig {
-                file_path: "./example/fixedwidth_data.txt".to_string(),
-                file_schema: "./example/fixedwidth_schema.xml".to_string(),
-            };
+            let config = ParserConfig::new(sample_data(), "./example/fixedwidth_schema.xml");
 
             let mut parser = Parser::new(config).unwrap();
             let schema = parser.schema.clone();
@@ -60,7 +97,7 @@ fn bench_parse_iter_par(c: &mut Criterion) {
                                 Err(processed_line) => Err(processed_line),
                             }
                         }
-                        Err(e) => Err(ProcessedLineError { line_number: 0, message: format!("{}", e) }),
+                        Err(e) => Err(ProcessedLineError::new(0, format!("{}", e))),
                     }
                 })
                 .for_each(|result_processed_line| match result_processed_line {
@@ -73,6 +110,22 @@ fn bench_parse_iter_par(c: &mut Criterion) {
     });
 }
 
+fn bench_parse_par_iter(c: &mut Criterion) {
+    c.bench_function("parse_par_iter", |b| {
+        b.iter(|| {
+            let config = ParserConfig::new(sample_data(), "./example/fixedwidth_schema.xml");
+
+            let mut parser = Parser::new(config).unwrap();
+
+            for line_result in parser.par_iter(4) {
+                if line_result.is_err() {
+                    panic!("Error processing line");
+                }
+            }
+        })
+    });
+}
+
 fn bench_convert(c: &mut Criterion) {
     c.bench_function("convert", |b| {
         b.iter(|| {
@@ -86,10 +139,7 @@ fn bench_convert(c: &mut Criterion) {
             let template = Convert::new(tpl_config).unwrap();
             assert!(!template.blocks.is_empty());
 
-            let config = ParserConfig {
-                file_path: "./example/fixedwidth_data.txt".to_string(),
-                file_schema: "./example/fixedwidth_schema.xml".to_string(),
-            };
+            let config = ParserConfig::new(sample_data(), "./example/fixedwidth_schema.xml");
 
             let mut parser = Parser::new(config).unwrap();
 
@@ -99,5 +149,13 @@ fn bench_convert(c: &mut Criterion) {
 }
 
 
-criterion_group!(benches, bench_decimal_format_new, bench_parse, bench_parse_iter_par, bench_convert);
+criterion_group!(
+    benches,
+    bench_decimal_format_new,
+    bench_lines,
+    bench_parse,
+    bench_parse_iter_par,
+    bench_parse_par_iter,
+    bench_convert
+);
 criterion_main!(benches);
@@ -0,0 +1,248 @@
+//! Async parsing for tokio (feature "async").
+//! The lines are read from any `AsyncBufRead` (a file, a network socket, ...) as they arrive,
+//! and only when the stream is polled, so a slow consumer applies backpressure to the reader.
+
+use anyhow::{Context, Error, Result};
+use futures_util::stream::{self, Stream};
+use tokio::fs::File;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+
+use crate::parser::{take_line, LineScanner, LineSplit, MixedLineSeparators};
+use crate::{schema, ControlTotalError, ControlTotals, ParserConfig, ProcessedLineError, ProcessedLineOk, ReadLine};
+
+#[derive(Debug)]
+pub struct AsyncParser<R> {
+    pub config: ParserConfig,
+    pub schema: schema::Schema,
+    file_buffer: AsyncFileBuffer<R>,
+}
+
+/// The async version of the `Parser` file buffer
+#[derive(Debug)]
+struct AsyncFileBuffer<R> {
+    reader: R,
+    scanner: LineScanner, // Splits the chunks read into lines, as the `Parser` file buffer
+    buf: Vec<u8>,         // Bytes of the current line, reused between lines
+    current_line: usize,
+    eof: bool, // The end of the input was reached
+}
+
+impl AsyncParser<BufReader<File>> {
+    /// Creates a new `AsyncParser` instance reading the file of the given configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration for the parser, including the file path and schema.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `AsyncParser` instance if successful, or an `Error` if an error occurred.
+    pub async fn new(config: ParserConfig) -> Result<Self, Error> {
+        let file = File::open(&config.file_path).await.context("Failed to open file")?;
+        Self::with_reader(config, BufReader::new(file))
+    }
+}
+
+/// The `AsyncParser` struct is the async version of `Parser`.
+/// It provides streams over the lines of any `AsyncBufRead` processed according to the schema.
+impl<R: AsyncBufRead + Unpin> AsyncParser<R> {
+    /// Creates a new `AsyncParser` instance reading the lines from `reader`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration for the parser. The `file_path` is not used, the lines are read from `reader`.
+    /// * `reader` - The input, e.g. a `tokio::io::BufReader` over a `TcpStream`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `AsyncParser` instance if successful, or an `Error` if an error occurred.
+    pub fn with_reader(config: ParserConfig, reader: R) -> Result<Self, Error> {
+        // Create a new schema instance based on the file schema specified in the configuration
+        let schema = schema::Schema::from_config(&config)?;
+
+        // Split by the newline characters defined in the schema, or by record length if there are none
+        let scanner = LineScanner::new(LineSplit::from_schema(&schema)?);
+
+        let file_buffer = AsyncFileBuffer { reader, scanner, buf: Vec::new(), current_line: 0, eof: false };
+
+        Ok(Self { config, schema, file_buffer })
+    }
+
+    /// Reads the next line from the reader.
+    ///
+    /// # Returns
+    ///
+    /// The next line as a `Result` containing either a `ReadLine` or an `std::io::Error`, or `None` at the end of the input.
+    pub async fn next_line(&mut self) -> Option<std::io::Result<ReadLine>> {
+        self.file_buffer.next_line().await
+    }
+
+    /// Returns a stream over the lines of the input.
+    ///
+    /// This method does not process the lines according to the schema.
+    /// Use the `stream` method to process each line according to the schema.
+    ///
+    /// # Returns
+    ///
+    /// A stream that yields each line as a `Result` containing either a `ReadLine` or an `std::io::Error`.
+    pub fn lines(&mut self) -> impl Stream<Item = Result<ReadLine, std::io::Error>> + '_ {
+        stream::unfold(&mut self.file_buffer, |file_buffer| async move {
+            let read_line = file_buffer.next_line().await?;
+            Some((read_line, file_buffer))
+        })
+    }
+
+    /// Returns a stream that processes each line of the input according to the schema.
+    ///
+    /// The stream must be pinned before polling it, e.g. with `tokio::pin!`.
+    ///
+    /// # Returns
+    ///
+    /// A stream that yields each processed line as a `Result` containing either a `ProcessedLineOk` or a `ProcessedLineError`.
+    pub fn stream(&mut self) -> impl Stream<Item = Result<ProcessedLineOk, ProcessedLineError>> + '_ {
+        let warn_mixed_line_separators = self.config.warn_mixed_line_separators;
+        let schema = &self.schema;
+        let state = StreamState {
+            file_buffer: &mut self.file_buffer,
+            mixed_line_separators: MixedLineSeparators::default(),
+            control_totals: Some(ControlTotals::new(schema, schema.get_control_totals())),
+            control_total_errors: Vec::new().into_iter(),
+            last_line_number: 0,
+        };
+
+        stream::unfold(state, move |mut state| async move {
+            let Some(control_totals) = &mut state.control_totals else {
+                let error = state.control_total_errors.next()?;
+                return Some((Err(error.into()), state));
+            };
+
+            let mut next_line = state.file_buffer.next_line().await;
+            while matches!(&next_line, Some(Ok(read_line)) if schema.is_ignored_line(&read_line.line_content)) {
+                next_line = state.file_buffer.next_line().await;
+            }
+
+            let result = match next_line {
+                Some(Ok(read_line)) => match schema.validate_line(read_line.line_number, read_line.line_content) {
+                    Ok(mut processed_line) => {
+                        if warn_mixed_line_separators {
+                            if let Some(warning) = state.mixed_line_separators.check(read_line.line_separator) {
+                                processed_line.warnings.push(warning);
+                            }
+                        }
+                        state.last_line_number = processed_line.line_number;
+                        control_totals.add_line(&processed_line);
+                        Ok(processed_line)
+                    }
+                    Err(processed_line) => Err(processed_line),
+                },
+                Some(Err(err)) => Err(ProcessedLineError::new(0, format!("{:?}", err))),
+                None => {
+                    // The control totals of the file are checked after the last line
+                    let control_totals = state.control_totals.take()?;
+                    state.control_total_errors = control_totals.finish(state.last_line_number).into_iter();
+                    let error = state.control_total_errors.next()?;
+                    Err(error.into())
+                }
+            };
+            Some((result, state))
+        })
+    }
+
+    /// Returns the line separator found in the input when the schema uses lineseparator="auto".
+    /// See `Parser::detected_line_separator`.
+    pub fn detected_line_separator(&self) -> Option<&'static str> {
+        self.file_buffer.scanner.detected_line_separator
+    }
+}
+
+/// State of `AsyncParser::stream`
+struct StreamState<'a, R> {
+    file_buffer: &'a mut AsyncFileBuffer<R>,
+    mixed_line_separators: MixedLineSeparators,
+    control_totals: Option<ControlTotals<'a>>, // None after the last line
+    control_total_errors: std::vec::IntoIter<ControlTotalError>,
+    last_line_number: usize,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncFileBuffer<R> {
+    async fn next_line(&mut self) -> Option<std::io::Result<ReadLine>> {
+        let line_length = match self.read_line().await? {
+            Ok(line_length) => line_length,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let line = match take_line(&mut self.buf, line_length) {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e)),
+        };
+
+        self.current_line += 1;
+        Some(Ok(ReadLine {
+            line_number: self.current_line,
+            line_content: line,
+            line_separator: self.scanner.line_separator,
+        }))
+    }
+
+    /// Read chunks from the reader until the scanner finds the end of the line.
+    /// Returns the length of the line in `buf`.
+    async fn read_line(&mut self) -> Option<std::io::Result<usize>> {
+        loop {
+            if self.eof || self.scanner.is_finished() {
+                return self.scanner.end_of_file(&mut self.buf);
+            }
+
+            let chunk = match self.reader.fill_buf().await {
+                Ok(chunk) => chunk,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e)),
+            };
+            if chunk.is_empty() {
+                self.eof = true;
+                continue;
+            }
+
+            let (consumed, line_length) = self.scanner.scan(&mut self.buf, chunk);
+            self.reader.consume(consumed);
+            if line_length.is_some() {
+                return line_length;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::Parser;
+
+    /// The async parser yields the same lines as the blocking parser.
+    #[tokio::test]
+    async fn test_async_parser() {
+        let config = || ParserConfig {
+            file_path: "./example/fixedwidth_data_mixed_separators.txt".to_string(),
+            file_schema: "./example/fixedwidth_schema_auto.xml".to_string(),
+            warn_mixed_line_separators: true,
+            ..Default::default()
+        };
+
+        let mut parser = Parser::new(config()).unwrap();
+        let expected: Vec<String> = parser.iter_mut().map(|line_result| format!("{:?}", line_result)).collect();
+
+        let mut async_parser = AsyncParser::new(config()).await.unwrap();
+        let processed_lines: Vec<String> =
+            async_parser.stream().map(|line_result| format!("{:?}", line_result)).collect().await;
+        assert_eq!(processed_lines, expected);
+        assert_eq!(async_parser.detected_line_separator(), Some("\\r\\n"));
+
+        // Read from a stream in small chunks
+        let data = std::fs::read("./example/fixedwidth_data_mixed_separators.txt").unwrap();
+        let reader = BufReader::with_capacity(3, &data[..]);
+        let mut async_parser = AsyncParser::with_reader(config(), reader).unwrap();
+        let processed_lines: Vec<String> =
+            async_parser.stream().map(|line_result| format!("{:?}", line_result)).collect().await;
+        assert_eq!(processed_lines, expected);
+    }
+}
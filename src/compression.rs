@@ -0,0 +1,157 @@
+//! Transparent decompression of the input file.
+//! The compression is detected by the magic bytes at the start of the file, or by the file extension,
+//! and the file is decompressed on the fly, so line numbers and errors refer to the uncompressed content.
+//! Each format needs its cargo feature: "gzip", "zstd" or "bzip2".
+
+use anyhow::{anyhow, Context, Error, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// The compression formats that can be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// Detect the compression by the magic bytes at the start of the file, or else by the file extension.
+    /// Returns `None` for uncompressed files.
+    pub fn detect(header: &[u8], file_path: &str) -> Option<Self> {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            return Some(Self::Gzip);
+        }
+        if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            return Some(Self::Zstd);
+        }
+        if header.starts_with(b"BZh") {
+            return Some(Self::Bzip2);
+        }
+
+        match Path::new(file_path).extension().and_then(|extension| extension.to_str()) {
+            Some("gz") => Some(Self::Gzip),
+            Some("zst") => Some(Self::Zstd),
+            Some("bz2") => Some(Self::Bzip2),
+            _ => None,
+        }
+    }
+
+    /// Name of the cargo feature required to read this format.
+    pub fn feature(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Bzip2 => "bzip2",
+        }
+    }
+}
+
+/// Open the input file, decompressing it if it is compressed.
+pub(crate) fn open_input(file_path: &str) -> Result<Box<dyn BufRead + Send>, Error> {
+    let file = File::open(file_path).context("Failed to open file")?;
+    read_input(BufReader::new(file), file_path)
+}
+
+/// Read an input, e.g. the standard input, decompressing it if it is compressed.
+/// The `file_path` is only used to detect the compression by its extension, it may be empty.
+pub(crate) fn read_input<R: BufRead + Send + 'static>(
+    mut reader: R, file_path: &str,
+) -> Result<Box<dyn BufRead + Send>, Error> {
+    // Peek at the start of the input without consuming it
+    let header = reader.fill_buf().context("Failed to read file")?;
+    match Compression::detect(header, file_path) {
+        None => Ok(Box::new(reader)),
+        Some(compression) => decompress(compression, reader),
+    }
+}
+
+#[allow(unused_variables)]
+fn decompress<R: BufRead + Send + 'static>(
+    compression: Compression, reader: R,
+) -> Result<Box<dyn BufRead + Send>, Error> {
+    match compression {
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Ok(Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader)))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            let decoder = zstd::stream::read::Decoder::with_buffer(reader).context("Failed to read zstd file")?;
+            Ok(Box::new(BufReader::new(decoder)))
+        }
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Ok(Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(reader)))),
+        #[allow(unreachable_patterns)]
+        _ => Err(anyhow!(
+            "The file is compressed with {:?}, enable the \"{}\" feature to read it",
+            compression,
+            compression.feature()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_compression() {
+        assert_eq!(Compression::detect(&[0x1f, 0x8b, 0x08], "data.txt"), Some(Compression::Gzip));
+        assert_eq!(Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd], "data"), Some(Compression::Zstd));
+        assert_eq!(Compression::detect(b"BZh91AY", "data.txt"), Some(Compression::Bzip2));
+        assert_eq!(Compression::detect(b"", "data.txt.gz"), Some(Compression::Gzip));
+        assert_eq!(Compression::detect(b"", "data.zst"), Some(Compression::Zstd));
+        assert_eq!(Compression::detect(b"", "data.bz2"), Some(Compression::Bzip2));
+        assert_eq!(Compression::detect(b"H20240524", "data.txt"), None);
+    }
+
+    /// A compressed file yields the same lines as the uncompressed file.
+    #[test]
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2"))]
+    #[allow(clippy::vec_init_then_push)] // The formats pushed depend on the features
+    fn test_parser_compressed_file() {
+        use crate::{Parser, ParserConfig};
+        use std::io::Write;
+
+        let data = std::fs::read(crate::schema_generate::sample_data()).unwrap();
+        let mut compressed_files: Vec<(&str, Vec<u8>)> = Vec::new();
+        #[cfg(feature = "gzip")]
+        {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&data).unwrap();
+            compressed_files.push(("gz", encoder.finish().unwrap()));
+        }
+        #[cfg(feature = "zstd")]
+        compressed_files.push(("zst", zstd::stream::encode_all(&data[..], 0).unwrap()));
+        #[cfg(feature = "bzip2")]
+        {
+            let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+            encoder.write_all(&data).unwrap();
+            compressed_files.push(("bz2", encoder.finish().unwrap()));
+        }
+
+        let config = |file_path: &str| ParserConfig {
+            file_path: file_path.to_string(),
+            file_schema: "./example/fixedwidth_schema.xml".to_string(),
+            ..Default::default()
+        };
+        let expected: Vec<String> = Parser::new(config(crate::schema_generate::sample_data()))
+            .unwrap()
+            .iter_mut()
+            .map(|line_result| format!("{:?}", line_result))
+            .collect();
+
+        for (extension, compressed_data) in compressed_files {
+            // Without extension, the compression is detected by the magic bytes
+            let file_path =
+                std::env::temp_dir().join(format!("rsapar_compressed_{}_{}", std::process::id(), extension));
+            std::fs::File::create(&file_path).unwrap().write_all(&compressed_data).unwrap();
+
+            let mut parser = Parser::new(config(file_path.to_str().unwrap())).unwrap();
+            let processed_lines: Vec<String> =
+                parser.iter_mut().map(|line_result| format!("{:?}", line_result)).collect();
+            std::fs::remove_file(&file_path).unwrap();
+            assert_eq!(processed_lines, expected, "{}", extension);
+        }
+    }
+}
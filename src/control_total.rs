@@ -0,0 +1,398 @@
+//! Control totals: cross-line rules checking a cell of a trailer line against the other lines.
+//!
+//! ```xml
+//! <controltotal rule="Footer.TotalAmount == sum(Body.Amount)"/>
+//! <controltotal rule="Footer.Count == count(Body)"/>
+//! ```
+//!
+//! The rules of the `<fixedwidthschema>` are checked at the end of the file,
+//! the rules of a `<group>` when the group ends (see `Parser::groups`).
+
+use std::cmp::Ordering;
+
+use crate::decimal_format::Decimal;
+use crate::{Aggregate, Cell, ControlTotal, ProcessedLine, ProcessedLineError, ProcessedLineOk, Schema};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlTotalErrorKind {
+    /// The total of the trailer line does not match the lines
+    Mismatch { expected: String, actual: String },
+    /// The trailer line was not found
+    MissingLine { linetype: String },
+    /// A value to sum, or the total, is not a number
+    NotANumber { value: String },
+}
+
+/// A control total that does not hold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlTotalError {
+    pub line_number: usize,
+    pub rule: String,
+    pub kind: ControlTotalErrorKind,
+}
+
+/// The error message format is:
+/// - [err:011]|controltotal|rule|the total is {actual} but was expected {expected}
+/// - [err:012]|controltotal|rule|the line type {} was not found
+/// - [err:013]|controltotal|rule|the value {} is not a number
+impl From<ControlTotalError> for ProcessedLineError {
+    fn from(error: ControlTotalError) -> Self {
+        let message = match error.kind {
+            ControlTotalErrorKind::Mismatch { expected, actual } => {
+                format!("[err:011]|controltotal|{}|the total is {} but was expected {}", error.rule, actual, expected)
+            }
+            ControlTotalErrorKind::MissingLine { linetype } => {
+                format!("[err:012]|controltotal|{}|the line type {} was not found", error.rule, linetype)
+            }
+            ControlTotalErrorKind::NotANumber { value } => {
+                format!("[err:013]|controltotal|{}|the value {} is not a number", error.rule, value)
+            }
+        };
+        ProcessedLineError::new(error.line_number, message)
+    }
+}
+
+/// A processed line with the cell values of the control totals
+pub trait ControlTotalLine {
+    fn line_number(&self) -> usize;
+    fn linetype(&self) -> &str;
+    fn cell_value(&self, name: &str) -> Option<&str>;
+}
+
+impl ControlTotalLine for ProcessedLineOk {
+    fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    fn linetype(&self) -> &str {
+        &self.linetype
+    }
+
+    fn cell_value(&self, name: &str) -> Option<&str> {
+        self.cell_values.get(name).map(String::as_str)
+    }
+}
+
+impl ControlTotalLine for ProcessedLine<'_> {
+    fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    fn linetype(&self) -> &str {
+        self.linetype
+    }
+
+    fn cell_value(&self, name: &str) -> Option<&str> {
+        self.cell_values.get(name).copied()
+    }
+}
+
+/// State of a rule while the lines are read
+#[derive(Debug)]
+struct ControlTotalState<'s> {
+    control_total: &'s ControlTotal,
+    total_cell: Option<&'s Cell>,
+    summed_cell: Option<&'s Cell>,
+    sum: Option<Decimal>, // None when the sum overflows
+    count: usize,
+    total: Option<(usize, String)>, // Line number and value of the total, read with the format of its cell
+    error: Option<ControlTotalError>,
+}
+
+/// Checks the control totals of a file or group from its processed lines
+#[derive(Debug)]
+pub struct ControlTotals<'s> {
+    states: Vec<ControlTotalState<'s>>,
+}
+
+impl<'s> ControlTotals<'s> {
+    /// The cells of the rules are read with their formats in the schema, e.g. grouped or implied decimals
+    pub fn new(schema: &'s Schema, control_totals: &'s [ControlTotal]) -> Self {
+        let states = control_totals
+            .iter()
+            .map(|control_total| ControlTotalState {
+                control_total,
+                total_cell: schema.find_cell(&control_total.linetype, &control_total.cell),
+                summed_cell: match &control_total.aggregate {
+                    Aggregate::Sum { linetype, cell } => schema.find_cell(linetype, cell),
+                    Aggregate::Count { .. } => None,
+                },
+                sum: Some(Decimal::default()),
+                count: 0,
+                total: None,
+                error: None,
+            })
+            .collect();
+        Self { states }
+    }
+
+    /// Add a valid line of the file or group
+    pub fn add_line(&mut self, line: &impl ControlTotalLine) {
+        for state in &mut self.states {
+            let control_total = state.control_total;
+            if line.linetype() == control_total.linetype && state.total.is_none() {
+                let total = line.cell_value(&control_total.cell).unwrap_or_default();
+                state.total = Some((line.line_number(), total.to_string()));
+            }
+
+            match &control_total.aggregate {
+                Aggregate::Count { linetype } if line.linetype() == linetype => state.count += 1,
+                Aggregate::Sum { linetype, cell } if line.linetype() == linetype => {
+                    let value = line.cell_value(cell).unwrap_or_default();
+                    // Empty optional cells are left out of the sum
+                    if is_empty(state.summed_cell, value) {
+                        continue;
+                    }
+                    match parse_number(state.summed_cell, value) {
+                        Some(value) => state.sum = state.sum.and_then(|sum| sum.checked_add(value)),
+                        None if state.error.is_none() => {
+                            state.error = Some(ControlTotalError {
+                                line_number: line.line_number(),
+                                rule: control_total.rule.to_owned(),
+                                kind: ControlTotalErrorKind::NotANumber { value: value.to_string() },
+                            })
+                        }
+                        None => (),
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Check the rules after the last line of the file or group
+    pub fn finish(self, line_number: usize) -> Vec<ControlTotalError> {
+        self.states.into_iter().filter_map(|state| state.check(line_number)).collect()
+    }
+}
+
+impl ControlTotalState<'_> {
+    fn check(self, line_number: usize) -> Option<ControlTotalError> {
+        let rule = self.control_total.rule.to_owned();
+        if self.error.is_some() {
+            return self.error;
+        }
+
+        let Some((total_line_number, total)) = self.total else {
+            let linetype = self.control_total.linetype.to_owned();
+            return Some(ControlTotalError {
+                line_number,
+                rule,
+                kind: ControlTotalErrorKind::MissingLine { linetype },
+            });
+        };
+        let Some(actual) = parse_number(self.total_cell, &total) else {
+            return Some(ControlTotalError {
+                line_number: total_line_number,
+                rule,
+                kind: ControlTotalErrorKind::NotANumber { value: total },
+            });
+        };
+
+        let expected = match self.control_total.aggregate {
+            Aggregate::Sum { .. } => self.sum,
+            Aggregate::Count { .. } => i128::try_from(self.count).ok().map(Decimal::from_integer),
+        };
+        match expected {
+            Some(expected) if expected.compare(actual) == Some(Ordering::Equal) => None,
+            expected => Some(ControlTotalError {
+                line_number: total_line_number,
+                rule,
+                kind: ControlTotalErrorKind::Mismatch {
+                    expected: expected.map_or("overflow".to_string(), |expected| expected.to_string()),
+                    actual: total,
+                },
+            }),
+        }
+    }
+}
+
+/// Check if a value of a cell is empty, see `Cell::is_empty_value`
+fn is_empty(cell: Option<&Cell>, value: &str) -> bool {
+    let value = value.trim();
+    value.is_empty() || cell.is_some_and(|cell| cell.is_empty_value(value))
+}
+
+/// Read a value with the format of its cell, see `Format::parse_decimal`
+fn parse_number(cell: Option<&Cell>, value: &str) -> Option<Decimal> {
+    match cell.and_then(|cell| cell.format.as_ref()) {
+        Some(format) => format.parse_decimal(value.trim()),
+        None => Decimal::parse(value),
+    }
+}
+
+/// Iterator adding the errors of the control totals of the file after the last line
+pub(crate) struct WithControlTotals<'s, I> {
+    lines: I,
+    control_totals: Option<ControlTotals<'s>>, // None after the last line
+    errors: std::vec::IntoIter<ControlTotalError>,
+    last_line_number: usize,
+}
+
+impl<'s, I> WithControlTotals<'s, I> {
+    pub(crate) fn new(lines: I, schema: &'s Schema) -> Self {
+        Self {
+            lines,
+            control_totals: Some(ControlTotals::new(schema, schema.get_control_totals())),
+            errors: Vec::new().into_iter(),
+            last_line_number: 0,
+        }
+    }
+}
+
+impl<L: ControlTotalLine, I: Iterator<Item = Result<L, ProcessedLineError>>> Iterator for WithControlTotals<'_, I> {
+    type Item = Result<L, ProcessedLineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(control_totals) = &mut self.control_totals else {
+            return self.errors.next().map(|error| Err(error.into()));
+        };
+
+        match self.lines.next() {
+            Some(line_result) => {
+                if let Ok(line) = &line_result {
+                    self.last_line_number = line.line_number();
+                    control_totals.add_line(line);
+                }
+                Some(line_result)
+            }
+            None => {
+                if let Some(control_totals) = self.control_totals.take() {
+                    self.errors = control_totals.finish(self.last_line_number).into_iter();
+                }
+                self.errors.next().map(|error| Err(error.into()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::TestSchemaFile;
+    use crate::{Parser, ParserConfig, ProcessedRecord, Schema};
+
+    #[test]
+    fn test_control_totals() {
+        let schema = Schema::new("./example/fixedwidth_schema_groups.xml").unwrap();
+        let line = |line_number: usize, linetype: &str, cells: &[(&str, &str)]| ProcessedLineOk {
+            line_number,
+            cell_values: cells.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            linetype: linetype.to_string(),
+            warnings: Vec::new(),
+        };
+
+        // Batch rules: count and sum of the details
+        let mut control_totals = ControlTotals::new(&schema, &schema.get_groups()[0].controltotals);
+        control_totals.add_line(&line(1, "BatchHeader", &[("BatchID", "001")]));
+        control_totals.add_line(&line(2, "Detail", &[("Amount", "000001.00")]));
+        control_totals.add_line(&line(3, "Detail", &[("Amount", "000002.50")]));
+        control_totals.add_line(&line(4, "BatchTrailer", &[("Count", "003"), ("TotalAmount", "000003.50")]));
+        let errors = control_totals.finish(4);
+        assert_eq!(
+            errors,
+            [ControlTotalError {
+                line_number: 4,
+                rule: "BatchTrailer.Count == count(Detail)".to_string(),
+                kind: ControlTotalErrorKind::Mismatch { expected: "2".to_string(), actual: "003".to_string() }
+            }]
+        );
+        assert_eq!(
+            ProcessedLineError::from(errors[0].clone()).message,
+            "[err:011]|controltotal|BatchTrailer.Count == count(Detail)|the total is 003 but was expected 2"
+        );
+
+        // File rule without the footer line
+        let mut control_totals = ControlTotals::new(&schema, schema.get_control_totals());
+        control_totals.add_line(&line(1, "BatchHeader", &[("BatchID", "001")]));
+        let errors: Vec<String> =
+            control_totals.finish(1).into_iter().map(|error| ProcessedLineError::from(error).message).collect();
+        assert_eq!(
+            errors,
+            ["[err:012]|controltotal|Footer.BatchCount == count(BatchHeader)|the line type Footer was not found"]
+        );
+    }
+
+    /// The values are summed and compared with the formats of their cells, without the empty optional cells.
+    #[test]
+    fn test_control_totals_formats() {
+        let schema_xml = r##"<schema>
+  <fixedwidthschema lineseparator="\n">
+    <line linetype="Detail" maxlength="17">
+      <cell name="RecordType" length="1"><linecondition><match type="string" pattern="D"/></linecondition></cell>
+      <cell name="Amount" length="7"><format type="implieddecimal" pattern="2"/></cell>
+      <cell name="Fee" length="9" emptypattern="0*"><format type="number" pattern="#,##0.00"/></cell>
+    </line>
+    <line linetype="Footer" maxlength="20">
+      <cell name="RecordType" length="1"><linecondition><match type="string" pattern="F"/></linecondition></cell>
+      <cell name="TotalAmount" length="9"><format type="number" pattern="#,##0.00"/></cell>
+      <cell name="TotalFee" length="10"><format type="implieddecimal" pattern="3"/></cell>
+    </line>
+    <controltotal rule="Footer.TotalAmount == sum(Detail.Amount)"/>
+    <controltotal rule="Footer.TotalFee == sum(Detail.Fee)"/>
+  </fixedwidthschema>
+</schema>"##;
+        let schema = Schema::new(TestSchemaFile::new("control_totals", schema_xml).path()).unwrap();
+
+        let check = |lines: &[&str]| -> Vec<String> {
+            let mut control_totals = ControlTotals::new(&schema, schema.get_control_totals());
+            for (index, line) in lines.iter().enumerate() {
+                control_totals.add_line(&schema.validate_line(index + 1, line.to_string()).unwrap());
+            }
+            control_totals
+                .finish(lines.len())
+                .into_iter()
+                .map(|error| ProcessedLineError::from(error).message)
+                .collect()
+        };
+
+        // 1000.25 + 234.25 = 1,234.50 and 1,000.00 + 2,000.50 = 3000.500, the blank fee is left out
+        let lines = ["D0100025 1,000.00", "D0023425         ", "D0000000 2,000.50", "F 1,234.500003000500"];
+        assert_eq!(check(&lines), Vec::<String>::new());
+
+        let lines = ["D0100025 1,000.00", "F 1,000.000001000000"];
+        assert_eq!(
+            check(&lines),
+            ["[err:011]|controltotal|Footer.TotalAmount == sum(Detail.Amount)|the total is 1,000.00 but was expected 1000.25"]
+        );
+    }
+
+    /// The example file holds its control totals, checked by `iter_mut` and `groups`.
+    #[test]
+    fn test_parser_control_totals() {
+        let config = || ParserConfig {
+            file_path: "./example/fixedwidth_data_groups.txt".to_string(),
+            file_schema: "./example/fixedwidth_schema_groups.xml".to_string(),
+            ..Default::default()
+        };
+
+        let mut parser = Parser::new(config()).unwrap();
+        assert!(parser.iter_mut().all(|line_result| line_result.is_ok()));
+        let mut parser = Parser::new(config()).unwrap();
+        assert!(parser.par_iter(2).all(|line_result| line_result.is_ok()));
+        let mut parser = Parser::new(config()).unwrap();
+        assert!(parser.groups().all(|record_result| record_result.is_ok()));
+
+        // A batch with a wrong total
+        let schema = Schema::new("./example/fixedwidth_schema_groups.xml").unwrap();
+        let mut grouper = crate::Grouper::new(&schema);
+        for line_result in Parser::new(config()).unwrap().iter_mut() {
+            let mut line = line_result.unwrap();
+            if line.line_number == 9 {
+                line.cell_values.insert("TotalAmount".to_string(), "000000.70".to_string());
+            }
+            grouper.push(Ok(line));
+        }
+        grouper.finish();
+        let errors: Vec<String> = std::iter::from_fn(|| grouper.next_record())
+            .filter_map(|record_result| match record_result {
+                Ok(ProcessedRecord::Group(_)) | Ok(ProcessedRecord::Line(_)) => None,
+                Err(err) => Some(format!("{}:{}", err.line_number, err.message)),
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            ["9:[err:011]|controltotal|BatchTrailer.TotalAmount == sum(Detail.Amount)|the total is 000000.70 but was expected 0.75"]
+        );
+    }
+}
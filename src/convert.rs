@@ -155,12 +155,12 @@ impl Convert {
     /// # Example
     ///
     /// ```rust
-    /// 
+    ///
     /// use rsapar::ParserConfig;
     /// use rsapar::Parser;
     /// use rsapar::ConvertConfig;
     /// use rsapar::Convert;
-    /// 
+    ///
     /// let tpl_config = ConvertConfig {
     ///     file_output_path: "./example/report_output.txt".to_string(),
     ///     file_template_path: "./example/convert_blocks.xml".to_string(),
@@ -168,10 +168,7 @@ impl Convert {
     ///
     /// let template = Convert::new(tpl_config).unwrap();
     ///
-    /// let config = ParserConfig {
-    ///     file_path: "./example/fixedwidth_data.txt".to_string(),
-    ///     file_schema: "./example/fixedwidth_schema.xml".to_string(),
-    /// };
+    /// let config = ParserConfig::new("./example/fixedwidth_data.txt", "./example/fixedwidth_schema.xml");
     /// let mut parser = Parser::new(config).unwrap();
     ///
     /// template.convert(&mut parser).unwrap();
@@ -359,7 +356,7 @@ impl Convert {
             }
 
             file_output.flush().unwrap(); // TODO: handle error
-            
+
             self.render_special_placeholders(&file_output_path, &config_special_placeholders).unwrap();
         }
 
@@ -558,13 +555,12 @@ impl Convert {
 
 #[cfg(test)]
 mod tests {
-    
+
     use super::*;
     use crate::{parser::Parser, ParserConfig};
 
     #[test]
     fn test_convert() {
-
         let file_output_path = "./example/report_output.txt";
         let file_template_path = "./example/convert_blocks.xml";
         let tpl_config = ConvertConfig {
@@ -576,8 +572,9 @@ mod tests {
         assert!(!template.blocks.is_empty());
 
         let config = ParserConfig {
-            file_path: "./example/fixedwidth_data.txt".to_string(),
+            file_path: crate::schema_generate::sample_data().to_string(),
             file_schema: "./example/fixedwidth_schema.xml".to_string(),
+            ..Default::default()
         };
 
         let mut parser = Parser::new(config).unwrap();
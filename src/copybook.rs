@@ -0,0 +1,578 @@
+//! Import of COBOL copybooks, see `Schema::from_copybook`.
+
+use anyhow::{anyhow, Error, Result};
+use std::collections::HashMap;
+use std::io::BufRead;
+use xml::common::TextPosition;
+
+use crate::schema::error_at;
+use crate::{Cell, FixedWidthSchema, Format, Line, LintIssue, Schema};
+
+/// Storage of a data item, from the USAGE clause
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Usage {
+    Display,
+    Packed, // COMP-3, PACKED-DECIMAL: two digits per byte and the sign in the last half byte
+    Binary, // COMP, COMP-4, COMP-5, BINARY: 2, 4 or 8 bytes by the number of digits
+    Float,  // COMP-1
+    Double, // COMP-2
+}
+
+/// Category of a picture string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Category {
+    Alphanumeric, // X or A
+    Numeric,      // 9, S, V and P
+    Edited,       // Numeric edited, e.g. ZZ,ZZ9.99 or -9(5)
+}
+
+/// A PICTURE clause, e.g. "S9(7)V99"
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Picture {
+    category: Category,
+    digits: usize, // Digits of a numeric picture
+    scale: usize,  // Digits after the implied decimal point "V"
+    signed: bool,
+    size: usize,                    // Characters of the DISPLAY value
+    number_pattern: Option<String>, // DecimalFormat pattern of the edited pictures with only Z, 9, "," and "."
+}
+
+/// Position of the sign of a signed DISPLAY item, from the SIGN clause
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sign {
+    leading: bool,
+    separate: bool,
+}
+
+/// A data description entry of the copybook
+#[derive(Debug, Clone)]
+struct Item {
+    level: u8,
+    name: String, // Empty for FILLER
+    redefines: Option<String>,
+    picture: Option<Picture>,
+    usage: Option<Usage>, // Inherited from the group when not set
+    occurs: usize,
+    sign: Sign,
+    justified: bool,
+    children: Vec<Item>,
+    position: TextPosition,
+}
+
+/// Words starting a clause, not item names
+const CLAUSE_WORDS: [&str; 12] =
+    ["PIC", "PICTURE", "REDEFINES", "USAGE", "OCCURS", "SIGN", "VALUE", "VALUES", "JUST", "JUSTIFIED", "BLANK", "SYNC"];
+
+impl Picture {
+    fn parse(picture: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid picture: {}", picture);
+        let upper = picture.to_uppercase();
+
+        // Symbols with their repetitions, e.g. "9(5)V99" is [('9', 5), ('V', 1), ('9', 2)]
+        let mut symbols: Vec<(char, usize)> = Vec::new();
+        let mut chars = upper.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '(' => {
+                    let count: String = chars.by_ref().take_while(|c| *c != ')').collect();
+                    let count: usize = count.parse().map_err(|_| invalid())?;
+                    match symbols.last_mut() {
+                        Some((_, repetitions)) if count > 0 => *repetitions += count - 1,
+                        _ => return Err(invalid()),
+                    }
+                }
+                'C' if chars.next_if_eq(&'R').is_some() => symbols.push(('C', 1)),
+                'D' if chars.next_if_eq(&'B').is_some() => symbols.push(('D', 1)),
+                'X' | 'A' | '9' | 'S' | 'V' | 'P' | 'Z' | '*' | '+' | '-' | '.' | ',' | 'B' | '0' | '/' | '$' => {
+                    symbols.push((c, 1))
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        if symbols.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut picture =
+            Picture { category: Category::Numeric, digits: 0, scale: 0, signed: false, size: 0, number_pattern: None };
+        let mut after_point = false;
+        for &(symbol, repetitions) in &symbols {
+            match symbol {
+                'X' | 'A' => picture.category = Category::Alphanumeric,
+                '9' => picture.digits += repetitions,
+                'S' => picture.signed = true,
+                'V' => after_point = true,
+                'P' => (), // Scaling positions are not stored
+                _ if picture.category == Category::Numeric => picture.category = Category::Edited,
+                _ => (),
+            }
+            if symbol == '9' && after_point {
+                picture.scale += repetitions;
+            }
+            picture.size += match symbol {
+                'S' | 'V' | 'P' => 0,
+                'C' | 'D' => 2 * repetitions, // CR and DB
+                _ => repetitions,
+            };
+        }
+
+        // Edited numbers like "ZZ,ZZ9.99" are DecimalFormat patterns like "##,##0.00"
+        let number_symbols = symbols.iter().all(|(symbol, _)| matches!(symbol, 'Z' | '9' | ',' | '.'));
+        if picture.category == Category::Edited && number_symbols {
+            let pattern = symbols.iter().map(|(symbol, repetitions)| {
+                let symbol = match symbol {
+                    'Z' => '#',
+                    '9' => '0',
+                    symbol => *symbol,
+                };
+                symbol.to_string().repeat(*repetitions)
+            });
+            picture.number_pattern = Some(pattern.collect());
+        }
+        Ok(picture)
+    }
+}
+
+impl Item {
+    /// Bytes or characters of one occurrence of an elementary item
+    fn elementary_size(&self, usage: Usage) -> Result<usize> {
+        let picture = self.picture.as_ref();
+        let digits = picture.map_or(0, |picture| picture.digits);
+        let size = match usage {
+            Usage::Float => 4,
+            Usage::Double => 8,
+            Usage::Packed => digits / 2 + 1,
+            Usage::Binary if digits <= 4 => 2,
+            Usage::Binary if digits <= 9 => 4,
+            Usage::Binary => 8,
+            Usage::Display => match picture {
+                Some(picture) if picture.signed && self.sign.separate => picture.size + 1,
+                Some(picture) => picture.size,
+                None => return Err(anyhow!("The elementary item {} has no picture", self.name)),
+            },
+        };
+        Ok(size)
+    }
+
+    /// Format of an elementary item: "integer" or "implieddecimal" for numeric DISPLAY items,
+    /// "number" for the edited numbers with only Z, 9, "," and "."
+    fn format(&self, usage: Usage) -> Option<Format> {
+        let picture = self.picture.as_ref()?;
+        let format =
+            |ctype: &str, pattern: String| Some(Format { ctype: ctype.to_string(), pattern, ..Default::default() });
+        match (usage, picture.category) {
+            (Usage::Display, Category::Numeric) if picture.signed && self.sign.leading && !self.sign.separate => {
+                None // The sign is overpunched in the first digit
+            }
+            (Usage::Display, Category::Numeric) if !picture.signed && picture.scale == 0 && picture.digits <= 18 => {
+                format("integer", String::new())
+            }
+            (Usage::Display, Category::Numeric) => format("implieddecimal", picture.scale.to_string()),
+            (Usage::Display, Category::Edited) => format("number", picture.number_pattern.to_owned()?),
+            _ => None,
+        }
+    }
+}
+
+/// Statements of a copybook with the position of their first word, without comments, sequence numbers and periods
+fn read_statements<R: BufRead>(reader: R) -> Result<Vec<(TextPosition, String)>> {
+    let mut statements = Vec::new();
+    let mut statement = String::new();
+    let mut in_statement = false;
+    let mut start = TextPosition::new();
+    let mut quote = None;
+    for (row, line) in reader.lines().enumerate() {
+        let line = line?;
+        // Fixed format: sequence number in columns 1-6, indicator in column 7, code in columns 8-72
+        let fixed_format =
+            line.len() > 6 && line.is_char_boundary(6) && line[..6].chars().all(|c| c.is_ascii_digit() || c == ' ');
+        let (code, column) = match fixed_format {
+            true if matches!(line.get(6..7), Some("*" | "/")) => continue,
+            true => (line.get(7..72).or_else(|| line.get(7..)).unwrap_or_default(), 7),
+            false if line.trim_start().starts_with('*') => continue,
+            false => (line.as_str(), 0),
+        };
+        // Inline comments of the free format
+        let code = code.split("*>").next().unwrap_or_default();
+
+        for (index, c) in code.char_indices() {
+            if !in_statement && !c.is_whitespace() {
+                in_statement = true;
+                start = TextPosition { row: row as u64, column: (column + index) as u64 };
+            }
+            match (c, quote) {
+                ('\'' | '"', None) => quote = Some(c),
+                (c, Some(open_quote)) if c == open_quote => quote = None,
+                _ => (),
+            }
+            // A period ends the statement unless it is part of a picture like "9(5).99"
+            let next = code[index + c.len_utf8()..].chars().next();
+            if c == '.' && quote.is_none() && next.map_or(true, char::is_whitespace) {
+                statements.push((start, std::mem::take(&mut statement)));
+                in_statement = false;
+            } else if in_statement {
+                statement.push(c);
+            }
+        }
+        statement.push(' ');
+    }
+    if in_statement {
+        return Err(error_at(Some(start), anyhow!("The statement is not ended by a period")));
+    }
+    Ok(statements)
+}
+
+/// Words of a statement, keeping the quoted literals together
+fn split_words(statement: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut quote = None;
+    for c in statement.chars() {
+        match (c, quote) {
+            (c, None) if c.is_whitespace() => {
+                words.push(String::new());
+                continue;
+            }
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(open_quote)) if c == open_quote => quote = None,
+            _ => (),
+        }
+        match words.last_mut() {
+            Some(word) => word.push(c),
+            None => words.push(c.to_string()),
+        }
+    }
+    words.retain(|word| !word.is_empty());
+    words
+}
+
+fn parse_usage(word: &str) -> Option<Usage> {
+    match word {
+        "DISPLAY" => Some(Usage::Display),
+        "COMP-3" | "COMPUTATIONAL-3" | "PACKED-DECIMAL" => Some(Usage::Packed),
+        "COMP" | "COMPUTATIONAL" | "COMP-4" | "COMPUTATIONAL-4" | "COMP-5" | "COMPUTATIONAL-5" | "BINARY" => {
+            Some(Usage::Binary)
+        }
+        "COMP-1" | "COMPUTATIONAL-1" => Some(Usage::Float),
+        "COMP-2" | "COMPUTATIONAL-2" => Some(Usage::Double),
+        _ => None,
+    }
+}
+
+/// Parse a data description entry, or None for the entries without data (levels 66, 77 and 88)
+fn parse_item(position: TextPosition, statement: &str) -> Result<Option<Item>> {
+    let words = split_words(statement);
+    let first_word = words.first().map_or("", String::as_str);
+    let level: u8 = match first_word.parse() {
+        Ok(level @ (1..=49 | 66 | 77 | 88)) => level,
+        _ => return Err(anyhow!("Expected a level number: {}", first_word)),
+    };
+    if matches!(level, 66 | 77 | 88) {
+        return Ok(None);
+    }
+
+    let mut words = words.into_iter().skip(1).map(|word| word.to_uppercase()).peekable();
+    let name = match words.next_if(|word| !CLAUSE_WORDS.contains(&word.as_str()) && parse_usage(word).is_none()) {
+        Some(name) if name == "FILLER" => String::new(),
+        Some(name) => name,
+        None => String::new(),
+    };
+    let mut item = Item {
+        level,
+        name,
+        redefines: None,
+        picture: None,
+        usage: None,
+        occurs: 1,
+        sign: Sign { leading: false, separate: false },
+        justified: false,
+        children: Vec::new(),
+        position,
+    };
+
+    while let Some(word) = words.next() {
+        match word.as_str() {
+            "REDEFINES" => item.redefines = words.next(),
+            "PIC" | "PICTURE" => {
+                words.next_if_eq("IS");
+                let picture = words.next().ok_or_else(|| anyhow!("Expected a picture after {}", word))?;
+                item.picture = Some(Picture::parse(&picture)?);
+            }
+            "USAGE" => {
+                words.next_if_eq("IS");
+                let usage = words.next().unwrap_or_default();
+                item.usage = Some(parse_usage(&usage).ok_or_else(|| anyhow!("Unknown usage: {}", usage))?);
+            }
+            "OCCURS" => {
+                let occurs = words.next().unwrap_or_default();
+                item.occurs = occurs.parse().map_err(|_| anyhow!("Invalid occurs: {}", occurs))?;
+                // OCCURS min TO max DEPENDING ON: the records have the maximum length
+                if words.next_if_eq("TO").is_some() {
+                    let occurs = words.next().unwrap_or_default();
+                    item.occurs = occurs.parse().map_err(|_| anyhow!("Invalid occurs: {}", occurs))?;
+                }
+            }
+            "LEADING" => item.sign.leading = true,
+            "SEPARATE" => item.sign.separate = true,
+            "JUST" | "JUSTIFIED" => item.justified = true,
+            word => {
+                // Other clauses (VALUE, SYNC, INDEXED BY...) do not change the layout
+                if let Some(usage) = parse_usage(word) {
+                    item.usage = Some(usage);
+                }
+            }
+        }
+    }
+    Ok(Some(item))
+}
+
+/// Cells of an item and its children from `offset`, returns the size of the item
+fn layout(
+    item: &Item, offset: usize, suffix: &str, parent_usage: Usage, cells: &mut Vec<Cell>,
+) -> Result<usize, Error> {
+    let usage = item.usage.unwrap_or(parent_usage);
+    let mut size = 0;
+    for occurrence in 1..=item.occurs {
+        let suffix = match item.occurs {
+            1 => suffix.to_owned(),
+            _ => format!("{}-{}", suffix, occurrence),
+        };
+        let start = offset + size;
+        if item.children.is_empty() {
+            let length = item.elementary_size(usage).map_err(|e| error_at(Some(item.position), e))?;
+            cells.push(Cell {
+                name: if item.name.is_empty() { String::new() } else { format!("{}{}", item.name, suffix) },
+                length,
+                start,
+                end: start + length,
+                format: item.format(usage),
+                alignment: if item.justified { "right".to_string() } else { String::new() },
+                padcharacter: " ".to_string(),
+                ignoreread: item.name.is_empty(), // Fillers
+                ..Default::default()
+            });
+            size += length;
+            continue;
+        }
+
+        // The children follow each other, or start with the child they redefine
+        let mut child_offsets: HashMap<&str, usize> = HashMap::new();
+        let (mut next, mut end) = (start, start);
+        for child in &item.children {
+            let child_start = match &child.redefines {
+                Some(redefined) => *child_offsets
+                    .get(redefined.as_str())
+                    .ok_or_else(|| error_at(Some(child.position), anyhow!("Unknown redefined item: {}", redefined)))?,
+                None => next,
+            };
+            let child_size = layout(child, child_start, &suffix, usage, cells)?;
+            child_offsets.insert(&child.name, child_start);
+            if child.redefines.is_none() {
+                next = child_start + child_size;
+            }
+            end = end.max(child_start + child_size);
+        }
+        size += end - start;
+    }
+    Ok(size)
+}
+
+impl Schema {
+    /// Convert a COBOL copybook to a fixed width schema, with a line type for each 01 level record
+    /// (or a "Record" line type when the copybook has no 01 level).
+    ///
+    /// - `PIC X(10)` is a cell of 10 characters, `PIC 9(5)` an "integer" and `PIC S9(7)V99` an "implieddecimal"
+    ///   with 2 decimals, with the sign overpunched in the last digit or `SIGN SEPARATE`.
+    ///   Edited pictures like `ZZ,ZZ9.99` are "number" formats.
+    /// - `COMP-3`, `COMP`, `BINARY`, `COMP-1` and `COMP-2` items have their length in bytes and no format.
+    /// - `OCCURS n` repeats the item with the suffixes "-1" to "-n" in the cell names
+    ///   (the maximum of `OCCURS min TO max DEPENDING ON` is used).
+    /// - `REDEFINES` cells overlap the redefined item, and the line allows overlaps.
+    /// - `FILLER` items are unnamed cells, which must be empty.
+    ///
+    /// Levels 66, 77 and 88 and the clauses not affecting the layout (VALUE, SYNC...) are ignored.
+    ///
+    /// The line types have no line conditions: with more than one 01 level record, only the first one is matched
+    /// until conditions are added to the schema, as reported by `Schema::lint`.
+    pub fn from_copybook<R: BufRead>(copybook_reader: R) -> Result<Self, Error> {
+        // Tree of the items by level number
+        let mut records: Vec<Item> = Vec::new();
+        let mut open_items: Vec<Item> = Vec::new();
+        let close_item = |open_items: &mut Vec<Item>, records: &mut Vec<Item>| {
+            let item = open_items.pop().unwrap();
+            match open_items.last_mut() {
+                Some(parent) => parent.children.push(item),
+                None => records.push(item),
+            }
+        };
+        for (position, statement) in read_statements(copybook_reader)? {
+            let Some(item) = parse_item(position, &statement).map_err(|e| error_at(Some(position), e))? else {
+                continue;
+            };
+            while open_items.last().is_some_and(|open_item| open_item.level >= item.level) {
+                close_item(&mut open_items, &mut records);
+            }
+            if let Some(parent) = open_items.last().filter(|parent| parent.picture.is_some()) {
+                let message = anyhow!("The elementary item {} cannot have subordinate items", parent.name);
+                return Err(error_at(Some(item.position), message));
+            }
+            open_items.push(item);
+        }
+        while !open_items.is_empty() {
+            close_item(&mut open_items, &mut records);
+        }
+        if records.is_empty() {
+            return Err(anyhow!("The copybook has no data items"));
+        }
+
+        // Items without a 01 level record are the cells of a single record
+        if records.iter().any(|record| record.level != 1) {
+            let record = Item {
+                level: 0,
+                name: "Record".to_string(),
+                redefines: None,
+                picture: None,
+                usage: None,
+                occurs: 1,
+                sign: Sign { leading: false, separate: false },
+                justified: false,
+                position: records[0].position,
+                children: records,
+            };
+            records = vec![record];
+        }
+
+        let mut lines = Vec::new();
+        let mut lines_without_condition = Vec::new();
+        for record in &records {
+            let mut cells = Vec::new();
+            let length = layout(record, 0, "", Usage::Display, &mut cells)?;
+            let linetype = if record.name.is_empty() { "Record".to_string() } else { record.name.to_owned() };
+            for (index, cell) in cells.iter().enumerate().filter(|(_, cell)| !cell.name.is_empty()) {
+                if cells[..index].iter().any(|previous| previous.name == cell.name) {
+                    return Err(anyhow!("Duplicate cell {} in line {}", cell.name, linetype));
+                }
+            }
+            let allowoverlaps = cells.windows(2).any(|pair| pair[1].start < pair[0].end);
+            lines_without_condition.push((linetype.to_owned(), Some(record.position)));
+            lines.push(Line {
+                linetype,
+                maxlength: length,
+                occurs: "*".to_string(),
+                cell: cells,
+                padcharacter: " ".to_string(),
+                allowoverlaps,
+                ..Default::default()
+            });
+        }
+
+        let fixed_width_schema = FixedWidthSchema { lineseparator: "\\n".to_string(), lines, ..Default::default() };
+        let lint_issues = LintIssue::lines_without_condition(&lines_without_condition).into_iter().collect();
+        Ok(Schema { fixedwidthschema: Some(fixed_width_schema), csvschema: None, lint_issues })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CellValue;
+
+    #[test]
+    fn test_picture() {
+        let picture = Picture::parse("S9(7)V99").unwrap();
+        assert_eq!((picture.category, picture.digits, picture.scale, picture.signed), (Category::Numeric, 9, 2, true));
+        assert_eq!(picture.size, 9);
+        assert_eq!(Picture::parse("x(10)").unwrap().size, 10);
+        assert_eq!(Picture::parse("XXBXX").unwrap().category, Category::Alphanumeric);
+        let edited = Picture::parse("ZZ,ZZ9.99").unwrap();
+        assert_eq!((edited.category, edited.size), (Category::Edited, 9));
+        assert_eq!(edited.number_pattern.as_deref(), Some("##,##0.00"));
+        assert_eq!(Picture::parse("-9(5)CR").unwrap().size, 8);
+        assert_eq!(Picture::parse("9(").unwrap_err().to_string(), "Invalid picture: 9(");
+        assert_eq!(Picture::parse("9K").unwrap_err().to_string(), "Invalid picture: 9K");
+    }
+
+    #[test]
+    fn test_from_copybook() {
+        let copybook = std::fs::File::open("./example/copybook/customer.cpy").unwrap();
+        let schema = Schema::from_copybook(std::io::BufReader::new(copybook)).unwrap();
+        let fixed_width_schema = schema.fixedwidthschema.as_ref().unwrap();
+        assert_eq!(fixed_width_schema.lines.len(), 1);
+        let line = &fixed_width_schema.lines[0];
+        assert_eq!((line.linetype.as_str(), line.maxlength, line.allowoverlaps), ("CUSTOMER-RECORD", 80, true));
+
+        // (name, 1-based start, length, format type and pattern)
+        let cells: Vec<_> = line
+            .cell
+            .iter()
+            .map(|cell| {
+                let format = cell.format.as_ref().map(|format| format!("{} {}", format.ctype, format.pattern));
+                (cell.name.as_str(), cell.start + 1, cell.length, format.unwrap_or_default())
+            })
+            .collect();
+        assert_eq!(
+            cells,
+            [
+                ("CUST-ID", 1, 6, "integer ".to_string()),
+                ("CUST-NAME", 7, 20, String::new()),
+                ("OPEN-DATE", 27, 8, "integer ".to_string()),
+                ("OPEN-YEAR", 27, 4, "integer ".to_string()),
+                ("OPEN-MONTH", 31, 2, "integer ".to_string()),
+                ("OPEN-DAY", 33, 2, "integer ".to_string()),
+                ("BALANCE", 35, 9, "implieddecimal 2".to_string()),
+                ("CREDIT-LIMIT", 44, 5, String::new()),
+                ("PHONE-1", 49, 10, String::new()),
+                ("PHONE-2", 59, 10, String::new()),
+                ("", 69, 2, String::new()),
+                ("LAST-AMOUNT", 71, 9, "number ##,##0.00".to_string()),
+                ("STATUS", 80, 1, String::new()),
+            ]
+        );
+
+        let line_text = concat!("000042JOHN SMITH          20240131", "00012345{", "     ", "5551234567          ");
+        let processed_line = schema.validate_line(1, format!("{}   1,234.50A", line_text)).unwrap();
+        assert_eq!(schema.get_typed_value(&processed_line, "BALANCE"), Ok(Some(CellValue::Number(1234.5))));
+        assert_eq!(schema.get_typed_value(&processed_line, "OPEN-MONTH"), Ok(Some(CellValue::Integer(1))));
+        assert_eq!(schema.get_typed_value(&processed_line, "LAST-AMOUNT"), Ok(Some(CellValue::Number(1234.5))));
+    }
+
+    #[test]
+    fn test_copybook_records() {
+        let copybook = "01 HEADER-REC.\n  05 REC-TYPE PIC X.\n  05 FILE-DATE PIC 9(8).\n\
+                        01 DETAIL-REC.\n  05 REC-TYPE PIC X.\n  05 AMOUNT PIC 9(5)V99.\n";
+        let schema = Schema::from_copybook(copybook.as_bytes()).unwrap();
+        let linetypes: Vec<&str> =
+            schema.fixedwidthschema.as_ref().unwrap().lines.iter().map(|line| line.linetype.as_str()).collect();
+        assert_eq!(linetypes, ["HEADER-REC", "DETAIL-REC"]);
+
+        // Without line conditions the detail lines are read as headers
+        let issues: Vec<String> = schema.lint().iter().map(LintIssue::to_string).collect();
+        assert_eq!(
+            issues,
+            ["line 4, column 1: Line DETAIL-REC is never matched, there is more than one line without conditions: \
+              HEADER-REC, DETAIL-REC"]
+        );
+        assert!(Schema::from_copybook("01 REC.\n  05 A PIC X.\n".as_bytes()).unwrap().lint().is_empty());
+    }
+
+    #[test]
+    fn test_copybook_errors() {
+        let error = |copybook: &str| Schema::from_copybook(copybook.as_bytes()).unwrap_err().to_string();
+        assert_eq!(error("       01 REC.\n          05 A PIC 9(."), "line 2, column 11: Invalid picture: 9(");
+        assert_eq!(
+            error("01 REC.\n  05 A PIC X(2).\n  05 B REDEFINES C PIC X."),
+            "line 3, column 3: Unknown redefined item: C"
+        );
+        assert_eq!(error("01 REC.\n  05 A PIC X(2)"), "line 2, column 3: The statement is not ended by a period");
+        assert_eq!(error("01 REC.\n  05 A PIC X.\n  05 A PIC X."), "Duplicate cell A in line REC");
+        assert_eq!(error("      * Only a comment\n"), "The copybook has no data items");
+
+        // A non-ASCII character in the indicator column is not a comment
+        assert!(Schema::from_copybook("      \u{e9}\n01 REC.\n  05 A PIC X.\n".as_bytes()).is_ok());
+
+        // Items without 01 level and free format comments
+        let schema =
+            Schema::from_copybook("05 CODE PIC XX. *> The code\n05 AMOUNT PIC 9(3) COMP-3.".as_bytes()).unwrap();
+        let line = &schema.fixedwidthschema.as_ref().unwrap().lines[0];
+        assert_eq!((line.linetype.as_str(), line.maxlength, line.cell[1].length), ("Record", 4, 2));
+    }
+}
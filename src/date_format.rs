@@ -0,0 +1,86 @@
+/// Convert a SimpleDateFormat (Java) pattern to a chrono pattern, e.g. "dd/MM/yyyy HH:mm" to "%d/%m/%Y %H:%M".
+/// Patterns with a '%' are chrono patterns and are returned unchanged.
+/// @see: [SimpleDateFormat](https://docs.oracle.com/javase/8/docs/api/java/text/SimpleDateFormat.html)
+pub fn java_date_pattern(pattern: &str) -> Result<String, String> {
+    if pattern.contains('%') {
+        return Ok(pattern.to_string());
+    }
+
+    let mut chrono_pattern = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        // Quoted text, where '' is a single quote
+        if c == '\'' {
+            if chars.next_if_eq(&'\'').is_some() {
+                chrono_pattern.push('\'');
+                continue;
+            }
+            loop {
+                match chars.next() {
+                    Some('\'') if chars.next_if_eq(&'\'').is_some() => chrono_pattern.push('\''),
+                    Some('\'') => break,
+                    Some(c) => chrono_pattern.push(c),
+                    None => return Err(format!("Unterminated quote in date pattern: {}", pattern)),
+                }
+            }
+            continue;
+        }
+
+        if !c.is_ascii_alphabetic() {
+            chrono_pattern.push(c);
+            continue;
+        }
+
+        // Count the repetitions of the pattern letter
+        let mut count = 1;
+        while chars.next_if_eq(&c).is_some() {
+            count += 1;
+        }
+        let specifier = match (c, count) {
+            ('y', 2) => "%y",
+            ('y', _) => "%Y",
+            ('M', 1..=2) => "%m",
+            ('M', 3) => "%b",
+            ('M', _) => "%B",
+            ('d', _) => "%d",
+            ('D', _) => "%j",
+            ('E', 1..=3) => "%a",
+            ('E', _) => "%A",
+            ('u', _) => "%u",
+            ('a', _) => "%p",
+            ('H', _) => "%H",
+            ('h', _) => "%I",
+            ('m', _) => "%M",
+            ('s', _) => "%S",
+            ('S', 3) => "%3f",
+            ('S', 6) => "%6f",
+            ('S', 9) => "%9f",
+            ('Z', _) => "%z",
+            ('X', 1) => "%#z",
+            ('X', 2) => "%z",
+            ('X', 3) => "%:z",
+            _ => {
+                return Err(format!("Unsupported letter {} in date pattern: {}", c.to_string().repeat(count), pattern))
+            }
+        };
+        chrono_pattern.push_str(specifier);
+    }
+    Ok(chrono_pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_java_date_pattern() {
+        assert_eq!(java_date_pattern("yyyyMMdd").unwrap(), "%Y%m%d");
+        assert_eq!(java_date_pattern("dd/MM/yyyy HH:mm").unwrap(), "%d/%m/%Y %H:%M");
+        assert_eq!(java_date_pattern("yyyy-MM-dd'T'HH:mm:ss.SSSXXX").unwrap(), "%Y-%m-%dT%H:%M:%S.%3f%:z");
+        assert_eq!(java_date_pattern("EEE, d MMM yy hh:mm a").unwrap(), "%a, %d %b %y %I:%M %p");
+        assert_eq!(java_date_pattern("HH 'o''clock'").unwrap(), "%H o'clock");
+        assert_eq!(java_date_pattern("%Y%m%d").unwrap(), "%Y%m%d");
+        assert!(java_date_pattern("yyyy G").is_err());
+        assert!(java_date_pattern("yyyy 'at").is_err());
+    }
+}
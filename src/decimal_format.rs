@@ -1,5 +1,6 @@
 use regex::Regex;
 use std::{
+    cmp::Ordering,
     collections::HashMap,
     sync::{Mutex, OnceLock},
 };
@@ -107,8 +108,8 @@ impl DecimalFormat {
                 match c {
                     '0' => regex_pattern.push_str("\\d"),  // Match a digit.
                     '#' => regex_pattern.push_str("\\d?"), // Match an optional digit.
-                    ',' => regex_pattern.push_str("\\,"),  
-                    '.' => regex_pattern.push_str("\\."),  
+                    ',' => regex_pattern.push_str("\\,"),
+                    '.' => regex_pattern.push_str("\\."),
                     ';' => regex_pattern.push_str("\\;"),
                     '¤' => regex_pattern.push_str("\\$"), /* TODO: Add the international */
                     // currency symbol.
@@ -121,6 +122,70 @@ impl DecimalFormat {
     }
 }
 
+/// Exact decimal number, to sum and compare amounts without rounding errors
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Decimal {
+    mantissa: i128,
+    scale: u32, // Number of decimal digits
+}
+
+impl Decimal {
+    /// Parse a number like "-000123.45"
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value.strip_prefix('+').unwrap_or(value)),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return None;
+        }
+
+        let mut mantissa: i128 = 0;
+        for digit in integer.chars().chain(fraction.chars()) {
+            mantissa = mantissa.checked_mul(10)?.checked_add(digit.to_digit(10)? as i128)?;
+        }
+        let mantissa = if negative { -mantissa } else { mantissa };
+        Some(Self { mantissa, scale: fraction.len() as u32 })
+    }
+
+    /// Number of `mantissa` units of the last of `scale` decimals, e.g. 12345 with scale 2 is 123.45
+    pub(crate) fn new(mantissa: i128, scale: u32) -> Self {
+        Self { mantissa, scale }
+    }
+
+    pub(crate) fn from_integer(mantissa: i128) -> Self {
+        Self { mantissa, scale: 0 }
+    }
+
+    pub(crate) fn rescale(self, scale: u32) -> Option<i128> {
+        self.mantissa.checked_mul(10i128.checked_pow(scale.checked_sub(self.scale)?)?)
+    }
+
+    pub(crate) fn checked_add(self, other: Self) -> Option<Self> {
+        let scale = self.scale.max(other.scale);
+        Some(Self { mantissa: self.rescale(scale)?.checked_add(other.rescale(scale)?)?, scale })
+    }
+
+    pub(crate) fn compare(self, other: Self) -> Option<Ordering> {
+        let scale = self.scale.max(other.scale);
+        Some(self.rescale(scale)?.cmp(&other.rescale(scale)?))
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = self.mantissa.unsigned_abs().to_string();
+        if self.scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = self.scale as usize + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - self.scale as usize);
+        write!(f, "{}{}.{}", sign, integer, fraction)
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -148,4 +213,18 @@ mod tests {
         let formatter = DecimalFormat::new(pattern).unwrap();
         assert!(formatter.validate_number("00204000.00").is_ok());
     }
+
+    #[test]
+    fn test_decimal() {
+        let sum = ["000000001.00", "2.5", "-0.25", "+10"]
+            .iter()
+            .map(|value| Decimal::parse(value).unwrap())
+            .fold(Decimal::default(), |sum, value| sum.checked_add(value).unwrap());
+        assert_eq!(sum.to_string(), "13.25");
+        assert_eq!(sum.compare(Decimal::parse("13.2500").unwrap()), Some(Ordering::Equal));
+        assert_eq!(Decimal::parse("-0.05").unwrap().to_string(), "-0.05");
+        assert!(Decimal::parse("").is_none());
+        assert!(Decimal::parse("1,00").is_none());
+        assert!(Decimal::parse("abc").is_none());
+    }
 }
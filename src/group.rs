@@ -0,0 +1,431 @@
+//! Grouping of processed lines into the groups declared in the schema.
+//!
+//! ```xml
+//! <group name="Batch" occurs="1..*">
+//!     <lineref linetype="BatchHeader"/>
+//!     <lineref linetype="Detail" occurs="*"/>
+//!     <lineref linetype="BatchTrailer"/>
+//! </group>
+//! ```
+//!
+//! A group starts with a line that can begin it and continues while the lines follow the order of its items.
+//! Lines whose line type is not referenced by any group are yielded as they are.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::{schema, ControlTotals, Group, GroupItem, Occurs, ProcessedLineError, ProcessedLineOk};
+
+/// A line or a group of lines yielded by `Parser::groups`
+#[derive(Debug)]
+pub enum ProcessedRecord {
+    Line(ProcessedLineOk),
+    Group(ProcessedGroup),
+}
+
+#[derive(Debug)]
+pub struct ProcessedGroup {
+    pub name: String,
+    pub line_number: usize, // Number of the first line of the group
+    pub records: Vec<ProcessedRecord>,
+}
+
+/// Group open while its lines are being read
+#[derive(Debug)]
+struct Frame<'s> {
+    group: &'s Group,
+    position: usize, // Index of the current item of the group
+    count: usize,    // Occurrences of the current item
+    line_number: usize,
+    records: Vec<ProcessedRecord>,
+    control_totals: ControlTotals<'s>, // Checked when the group is closed
+}
+
+/// Builds the groups of the schema from the processed lines, in file order.
+///
+/// Lines are added with `push` and the records are taken with `next_record` as soon as they are complete,
+/// so it can be used with any source of lines, e.g. `MmapParser` or `AsyncParser`.
+/// Call `finish` after the last line to close the open groups.
+///
+/// The group errors are yielded as soon as they are detected, before the group they belong to:
+/// - [err:008]|group|name|the line type {} is outside the group
+/// - [err:009]|group|name|occurs|{} was expected at least {} times but found {}
+/// - [err:010]|group|name|occurs|was expected at most {} times but found {}
+///
+/// The control totals of each group are checked when the group ends (see `ControlTotals`).
+#[derive(Debug)]
+pub struct Grouper<'s> {
+    schema: &'s schema::Schema,
+    groups: &'s [Group],
+    group_counts: Vec<usize>,                     // Occurrences of each top level group
+    grouped_linetypes: HashMap<&'s str, &'s str>, // Line type to the name of the group that references it
+    stack: Vec<Frame<'s>>,                        // Open groups, the innermost last
+    records: VecDeque<Result<ProcessedRecord, ProcessedLineError>>,
+    last_line_number: usize,
+}
+
+impl<'s> Grouper<'s> {
+    pub fn new(schema: &'s schema::Schema) -> Self {
+        let groups = schema.get_groups();
+        let mut grouped_linetypes = HashMap::new();
+        Self::collect_linetypes(groups, &mut grouped_linetypes);
+
+        Self {
+            schema,
+            groups,
+            group_counts: vec![0; groups.len()],
+            grouped_linetypes,
+            stack: Vec::new(),
+            records: VecDeque::new(),
+            last_line_number: 0,
+        }
+    }
+
+    fn collect_linetypes(groups: &'s [Group], grouped_linetypes: &mut HashMap<&'s str, &'s str>) {
+        for group in groups {
+            for item in &group.items {
+                match item {
+                    GroupItem::Line(line_ref) => {
+                        grouped_linetypes.entry(&line_ref.linetype).or_insert(&group.name);
+                    }
+                    GroupItem::Group(nested_group) => {
+                        Self::collect_linetypes(std::slice::from_ref(nested_group), grouped_linetypes)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Add the next processed line
+    pub fn push(&mut self, line_result: Result<ProcessedLineOk, ProcessedLineError>) {
+        match line_result {
+            Ok(line) => {
+                self.last_line_number = line.line_number;
+                self.push_line(line);
+            }
+            // Invalid lines do not change the groups
+            Err(err) => self.records.push_back(Err(err)),
+        }
+    }
+
+    /// Close the open groups at the end of the file
+    pub fn finish(&mut self) {
+        while !self.stack.is_empty() {
+            self.close_group(self.last_line_number);
+        }
+
+        for (group, &count) in self.groups.iter().zip(&self.group_counts) {
+            if count < group.occurs.min {
+                self.records.push_back(Err(ProcessedLineError {
+                    line_number: self.last_line_number,
+                    message: format!(
+                        "[err:009]|group|{}|occurs|{} was expected at least {} times but found {}",
+                        group.name, group.name, group.occurs.min, count
+                    ),
+                    linetype: None,
+                }));
+            }
+        }
+    }
+
+    /// Take the next complete record
+    pub fn next_record(&mut self) -> Option<Result<ProcessedRecord, ProcessedLineError>> {
+        self.records.pop_front()
+    }
+
+    fn push_line(&mut self, line: ProcessedLineOk) {
+        let line_number = line.line_number;
+        loop {
+            let Some(frame) = self.stack.last_mut() else {
+                // Outside any group: start a top level group or yield the line as it is
+                let group_index = self.groups.iter().position(|group| Self::can_start(group, &line.linetype));
+                match group_index {
+                    Some(group_index) => {
+                        let group = &self.groups[group_index];
+                        self.group_counts[group_index] += 1;
+                        let count = self.group_counts[group_index];
+                        if group.occurs.max.is_some_and(|max| count > max) {
+                            self.records.push_back(Err(ProcessedLineError {
+                                line_number,
+                                message: format!(
+                                    "[err:010]|group|{}|occurs|was expected at most {} times but found {}",
+                                    group.name,
+                                    group.occurs.max.unwrap_or_default(),
+                                    count
+                                ),
+                                linetype: None,
+                            }));
+                        }
+                        self.stack.push(Frame::new(self.schema, group, line_number));
+                        continue;
+                    }
+                    None => {
+                        match self.grouped_linetypes.get(line.linetype.as_str()) {
+                            Some(group_name) => self.records.push_back(Err(ProcessedLineError {
+                                line_number,
+                                message: format!(
+                                    "[err:008]|group|{}|the line type {} is outside the group",
+                                    group_name, line.linetype
+                                ),
+                                linetype: Some(line.linetype.to_owned()),
+                            })),
+                            None => self.records.push_back(Ok(ProcessedRecord::Line(line))),
+                        }
+                        return;
+                    }
+                }
+            };
+
+            // The line does not continue the innermost group: close it and try the enclosing one
+            let Some(position) = frame.find_position(&line.linetype) else {
+                self.close_group(line_number);
+                continue;
+            };
+
+            if position != frame.position {
+                frame.position = position;
+                frame.count = 0;
+            }
+            frame.count += 1;
+
+            match &frame.group.items[position] {
+                GroupItem::Line(_) => {
+                    // The line counts for the control totals of the enclosing groups too
+                    for frame in &mut self.stack {
+                        frame.control_totals.add_line(&line);
+                    }
+                    if let Some(frame) = self.stack.last_mut() {
+                        frame.records.push(ProcessedRecord::Line(line));
+                    }
+                    self.close_saturated_groups(line_number);
+                    return;
+                }
+                GroupItem::Group(nested_group) => self.stack.push(Frame::new(self.schema, nested_group, line_number)),
+            }
+        }
+    }
+
+    /// Check if the line type can be the first line of the group
+    fn can_start(group: &Group, linetype: &str) -> bool {
+        for item in &group.items {
+            if Self::item_can_start(item, linetype) {
+                return true;
+            }
+            if item.occurs().min > 0 {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn item_can_start(item: &GroupItem, linetype: &str) -> bool {
+        match item {
+            GroupItem::Line(line_ref) => line_ref.linetype == linetype,
+            GroupItem::Group(group) => Self::can_start(group, linetype),
+        }
+    }
+
+    /// Close the groups that cannot take more lines, so they are yielded without waiting for the next line
+    fn close_saturated_groups(&mut self, line_number: usize) {
+        while let Some(frame) = self.stack.last() {
+            let last_item = frame.position + 1 == frame.group.items.len();
+            if !last_item || frame.group.items[frame.position].occurs().max != Some(frame.count) {
+                break;
+            }
+            self.close_group(line_number);
+        }
+    }
+
+    /// Close the innermost group, checking the occurrences of its remaining items
+    fn close_group(&mut self, line_number: usize) {
+        let Some(frame) = self.stack.pop() else {
+            return;
+        };
+
+        for (position, item) in frame.group.items.iter().enumerate().skip(frame.position) {
+            let count = if position == frame.position { frame.count } else { 0 };
+            let Occurs { min, .. } = item.occurs();
+            if count < min {
+                self.records.push_back(Err(ProcessedLineError {
+                    line_number,
+                    message: format!(
+                        "[err:009]|group|{}|occurs|{} was expected at least {} times but found {}",
+                        frame.group.name,
+                        item.name(),
+                        min,
+                        count
+                    ),
+                    linetype: None,
+                }));
+                break;
+            }
+        }
+
+        for error in frame.control_totals.finish(line_number) {
+            self.records.push_back(Err(error.into()));
+        }
+
+        let group = ProcessedGroup {
+            name: frame.group.name.to_owned(),
+            line_number: frame.line_number,
+            records: frame.records,
+        };
+        match self.stack.last_mut() {
+            Some(parent_frame) => parent_frame.records.push(ProcessedRecord::Group(group)),
+            None => self.records.push_back(Ok(ProcessedRecord::Group(group))),
+        }
+    }
+}
+
+impl<'s> Frame<'s> {
+    fn new(schema: &'s schema::Schema, group: &'s Group, line_number: usize) -> Self {
+        let control_totals = ControlTotals::new(schema, &group.controltotals);
+        Self { group, position: 0, count: 0, line_number, records: Vec::new(), control_totals }
+    }
+
+    /// Find the item of the group that takes the line type, from the current item onwards.
+    /// The items skipped must have their minimum occurrences.
+    fn find_position(&self, linetype: &str) -> Option<usize> {
+        let items = &self.group.items;
+        if let Some(item) = items.get(self.position) {
+            let occurs = item.occurs();
+            if occurs.max.map_or(true, |max| self.count < max) && Grouper::item_can_start(item, linetype) {
+                return Some(self.position);
+            }
+            if self.count < occurs.min {
+                return None;
+            }
+        }
+
+        for (position, item) in items.iter().enumerate().skip(self.position + 1) {
+            if Grouper::item_can_start(item, linetype) {
+                return Some(position);
+            }
+            if item.occurs().min > 0 {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::*;
+    use crate::{Parser, ParserConfig};
+
+    /// Line types and group names of the record, e.g. "Batch[BatchHeader,BatchTrailer]"
+    fn describe_record(record: &ProcessedRecord) -> String {
+        match record {
+            ProcessedRecord::Line(line) => line.linetype.to_owned(),
+            ProcessedRecord::Group(group) => {
+                let records: Vec<String> = group.records.iter().map(describe_record).collect();
+                format!("{}[{}]", group.name, records.join(","))
+            }
+        }
+    }
+
+    fn describe(record: &Result<ProcessedRecord, ProcessedLineError>) -> String {
+        match record {
+            Ok(record) => describe_record(record),
+            Err(err) => err.message.to_owned(),
+        }
+    }
+
+    fn processed_line(line_number: usize, linetype: &str) -> ProcessedLineOk {
+        ProcessedLineOk {
+            line_number,
+            cell_values: IndexMap::new(),
+            linetype: linetype.to_string(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn group_lines(schema: &schema::Schema, linetypes: &[&str]) -> Vec<String> {
+        let mut grouper = Grouper::new(schema);
+        let mut records = Vec::new();
+        for (index, linetype) in linetypes.iter().enumerate() {
+            grouper.push(Ok(processed_line(index + 1, linetype)));
+            records.extend(std::iter::from_fn(|| grouper.next_record()));
+        }
+        grouper.finish();
+        records.extend(std::iter::from_fn(|| grouper.next_record()));
+        records.iter().map(describe).collect()
+    }
+
+    #[test]
+    fn test_parser_groups() {
+        let config = ParserConfig {
+            file_path: "./example/fixedwidth_data_groups.txt".to_string(),
+            file_schema: "./example/fixedwidth_schema_groups.xml".to_string(),
+            ..Default::default()
+        };
+        let mut parser = Parser::new(config).unwrap();
+        let records: Vec<String> = parser.groups().map(|record| describe(&record)).collect();
+        assert_eq!(
+            records,
+            [
+                "Header",
+                "Batch[BatchHeader,Entry[Detail,Addenda],Entry[Detail],BatchTrailer]",
+                "Batch[BatchHeader,Entry[Detail],BatchTrailer]",
+                "Footer"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_errors() {
+        // The lines have no values for the control totals
+        let mut schema = schema::Schema::new("./example/fixedwidth_schema_groups.xml").unwrap();
+        if let Some(fixed_width_schema) = &mut schema.fixedwidthschema {
+            fixed_width_schema.groups[0].controltotals.clear();
+        }
+
+        // A detail line before the batch header
+        assert_eq!(
+            group_lines(&schema, &["Header", "Detail", "BatchHeader", "BatchTrailer", "Footer"]),
+            [
+                "Header",
+                "[err:008]|group|Entry|the line type Detail is outside the group",
+                "Batch[BatchHeader,BatchTrailer]",
+                "Footer"
+            ]
+        );
+
+        // The batch trailer is missing
+        assert_eq!(
+            group_lines(&schema, &["BatchHeader", "Detail", "Footer"]),
+            [
+                "[err:009]|group|Batch|occurs|BatchTrailer was expected at least 1 times but found 0",
+                "Batch[BatchHeader,Entry[Detail]]",
+                "Footer"
+            ]
+        );
+
+        // An addenda line is repeated: the batch cannot continue
+        assert_eq!(
+            group_lines(&schema, &["BatchHeader", "Detail", "Addenda", "Addenda"]),
+            [
+                "[err:009]|group|Batch|occurs|BatchTrailer was expected at least 1 times but found 0",
+                "Batch[BatchHeader,Entry[Detail,Addenda]]",
+                "[err:008]|group|Entry|the line type Addenda is outside the group",
+            ]
+        );
+
+        // The file ends inside a batch
+        assert_eq!(
+            group_lines(&schema, &["BatchHeader", "Detail"]),
+            [
+                "[err:009]|group|Batch|occurs|BatchTrailer was expected at least 1 times but found 0",
+                "Batch[BatchHeader,Entry[Detail]]",
+            ]
+        );
+
+        // No batch at all
+        assert_eq!(
+            group_lines(&schema, &["Header", "Footer"]),
+            ["Header", "Footer", "[err:009]|group|Batch|occurs|Batch was expected at least 1 times but found 0"]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Compatibility with the schemas of JSaPar 2.x.
+//!
+//! JSaPar schema files are loaded by `Schema::new` unchanged:
+//!
+//! - Elements in the JSaPar namespace (`http://jsapar.tigris.org/JSaParSchema/2.0` or `1.0`) or without namespace
+//!   are read, elements and attributes in any other namespace (e.g. `xsi:schemaLocation`) are ignored.
+//! - `<locale language="de" country="DE"/>` sets the decimal and grouping separators of the number formats.
+//! - `<fixedwidthschema ignoreemptylines="true">` skips the empty lines of the file, keeping their line numbers.
+//! - `<linecondition><match pattern="..."/></linecondition>` matches the cell against a regex,
+//!   `<match type="string" pattern="..."/>` compares it with the literal pattern.
+//! - `<emptycondition><match pattern="..."/></emptycondition>` is the same as the `emptypattern` attribute.
+//! - `<cellformat>` (JSaPar 1.x) is the same as `<format>`, with the JSaPar format types:
+//!   `decimal` is `number`, `local_date` is `date`, `local_time` is `time`,
+//!   `local_date_time` and `zoned_date_time` are `datetime`, `character` is `string`
+//!   and `implied_decimal` is `implieddecimal`.
+//!   Date patterns may be Java SimpleDateFormat patterns (see `java_date_pattern`).
+//! - `default`, `mandatory` and `ignoreread` cell attributes have the same meaning as in JSaPar.
+//!
+//! See the schemas and the expected results in `example/jsapar`.
+
+use xml::name::OwnedName;
+
+/// Namespaces of the JSaPar schemas
+pub const JSAPAR_NAMESPACES: [&str; 2] =
+    ["http://jsapar.tigris.org/JSaParSchema/2.0", "http://jsapar.tigris.org/JSaParSchema/1.0"];
+
+/// Check if an element or attribute is part of the schema: without namespace or in the JSaPar namespace
+pub(crate) fn is_schema_name(name: &OwnedName) -> bool {
+    match &name.namespace {
+        None => true,
+        Some(namespace) => JSAPAR_NAMESPACES.contains(&namespace.as_str()),
+    }
+}
+
+/// The format type of a JSaPar format type, e.g. "decimal" is "number"
+pub(crate) fn format_type(jsapar_type: &str) -> &str {
+    match jsapar_type {
+        "decimal" => "number",
+        "local_date" => "date",
+        "local_time" => "time",
+        "local_date_time" | "zoned_date_time" => "datetime",
+        "character" => "string",
+        "implied_decimal" => "implieddecimal",
+        format_type => format_type,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Parser, ParserConfig, ProcessedLineError, ProcessedLineOk};
+
+    /// Format a processed line as in the expected results
+    fn format_result(line_result: Result<ProcessedLineOk, ProcessedLineError>) -> String {
+        match line_result {
+            Ok(line) => {
+                let cells = line.cell_values.iter().map(|(name, value)| format!("{}={:?}", name, value));
+                std::iter::once(format!("{}|{}", line.line_number, line.linetype))
+                    .chain(cells)
+                    .collect::<Vec<_>>()
+                    .join("|")
+            }
+            Err(err) => format!("{}|{}", err.line_number, err.message),
+        }
+    }
+
+    /// Each JSaPar schema of `example/jsapar` parses its data file as in the expected results:
+    /// `line_number|linetype|name="value"|...` for valid lines and `line_number|message` for errors.
+    #[test]
+    fn test_jsapar_corpus() {
+        for name in ["persons", "orders"] {
+            let config = || ParserConfig {
+                file_path: format!("./example/jsapar/{}_data.txt", name),
+                file_schema: format!("./example/jsapar/{}_schema.xml", name),
+                strict_schema: true,
+                ..Default::default()
+            };
+            let expected = std::fs::read_to_string(format!("./example/jsapar/{}_expected.txt", name)).unwrap();
+
+            let results: Vec<String> = Parser::new(config()).unwrap().iter_mut().map(format_result).collect();
+            assert_eq!(results, expected.lines().collect::<Vec<_>>(), "{}", name);
+
+            let results: Vec<String> = Parser::new(config()).unwrap().par_iter(2).map(format_result).collect();
+            assert_eq!(results, expected.lines().collect::<Vec<_>>(), "{}", name);
+        }
+    }
+}
@@ -1,12 +1,44 @@
+mod compression;
+mod control_total;
+mod copybook;
+mod date_format;
 mod decimal_format;
+mod group;
+mod jsapar;
 mod parser;
 mod schema;
+mod schema_diff;
+mod schema_generate;
+mod schema_include;
+mod schema_infer;
+mod schema_spec;
 
 #[cfg(feature = "experimental_convert")]
 mod convert;
 #[cfg(feature = "experimental_convert")]
 pub use convert::*;
 
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "mmap")]
+pub use mmap::*;
+
+#[cfg(any(feature = "json", feature = "yaml"))]
+mod schema_document;
+
+#[cfg(feature = "async")]
+mod async_parser;
+#[cfg(feature = "async")]
+pub use async_parser::*;
+
+pub use compression::*;
+pub use control_total::*;
+pub use date_format::*;
 pub use decimal_format::*;
+pub use group::*;
+pub use jsapar::JSAPAR_NAMESPACES;
 pub use parser::*;
-pub use schema::*;
\ No newline at end of file
+pub use schema::*;
+pub use schema_diff::*;
+pub use schema_generate::*;
+pub use schema_infer::*;
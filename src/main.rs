@@ -0,0 +1,450 @@
+//! Command-line validation of data files against a schema, and comparison of two versions of a schema.
+//!
+//! ```text
+//! rsapar validate --schema schema.xml data.txt
+//! gunzip -c data.txt.gz | rsapar validate --schema schema.xml -
+//! rsapar diff old_schema.xml new_schema.xml
+//! ```
+//!
+//! The errors and warnings are printed with their line numbers, followed by a summary by error kind and line type.
+//! Warnings do not change the exit code.
+//! `diff` prints the changes of the schema, see `Schema::diff`, and fails when one of them is incompatible.
+//! The exit code is `EXIT_VALID`, `EXIT_INVALID` or `EXIT_UNREADABLE`, and `EXIT_USAGE` for wrong arguments.
+
+use indexmap::IndexMap;
+use std::io::{BufRead, Write};
+use std::process::ExitCode;
+
+use rsapar::{
+    message_code, Parser, ParserConfig, ProcessedLineError, ProcessedRecord, Schema, ERROR_KINDS, WARNING_KINDS,
+};
+
+/// All the lines are valid
+const EXIT_VALID: u8 = 0;
+/// Some lines, groups or control totals are invalid, or the new schema is not compatible with the old one
+const EXIT_INVALID: u8 = 1;
+/// The schema or the input cannot be read, or the input cannot be split into lines
+const EXIT_UNREADABLE: u8 = 2;
+/// Wrong command-line arguments
+const EXIT_USAGE: u8 = 64;
+
+const USAGE: &str = "\
+Usage: rsapar validate --schema <SCHEMA> [--strict] [--quiet] [FILE]
+       rsapar diff <OLD_SCHEMA> <NEW_SCHEMA>
+
+Validate FILE, or the standard input if FILE is missing or \"-\", against the schema.
+Print the changes from OLD_SCHEMA to NEW_SCHEMA and fail if one of them is incompatible.
+
+Options:
+  -s, --schema <SCHEMA>  Schema file (XML, JSON or YAML)
+      --strict           Fail on the mistakes of the schema reported by its lint
+  -q, --quiet            Print only the summary, not each error
+  -h, --help             Print this help
+  -V, --version          Print the version
+
+Exit codes: 0 valid or compatible, 1 invalid or incompatible, 2 unreadable schema or input, 64 wrong arguments";
+
+/// Line type of the lines that match no line type of the schema
+const UNKNOWN_LINETYPE: &str = "(unknown)";
+
+#[derive(Debug, Default)]
+struct ValidateArgs {
+    schema: String,
+    file: Option<String>, // None for the standard input
+    strict: bool,
+    quiet: bool,
+}
+
+/// Counts of the validated lines and errors
+#[derive(Debug, Default)]
+struct Summary {
+    lines: usize,
+    invalid_lines: usize,
+    errors_by_kind: IndexMap<String, usize>,             // Error code to count
+    warnings_by_kind: IndexMap<String, usize>,           // Warning code to count
+    lines_by_linetype: IndexMap<String, (usize, usize)>, // Line type to valid and invalid lines
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let stdin = std::io::BufReader::new(std::io::stdin());
+    ExitCode::from(run(&args, stdin, &mut std::io::stdout().lock(), &mut std::io::stderr().lock()))
+}
+
+/// Run the command with its arguments, without the program name, and return the exit code
+fn run(args: &[String], stdin: impl BufRead + Send + 'static, out: &mut impl Write, err: &mut impl Write) -> u8 {
+    match args.first().map(String::as_str) {
+        Some("validate") => match parse_validate_args(&args[1..]) {
+            Ok(validate_args) => validate(&validate_args, stdin, out, err),
+            Err(message) => {
+                let _ = writeln!(err, "error: {}\n\n{}", message, USAGE);
+                EXIT_USAGE
+            }
+        },
+        Some("diff") => match &args[1..] {
+            [old_schema, new_schema] if !old_schema.starts_with('-') && !new_schema.starts_with('-') => {
+                diff(old_schema, new_schema, out, err)
+            }
+            _ => {
+                let _ = writeln!(err, "error: diff needs the old and the new schema files\n\n{}", USAGE);
+                EXIT_USAGE
+            }
+        },
+        Some("-h" | "--help") => {
+            let _ = writeln!(out, "{}", USAGE);
+            EXIT_VALID
+        }
+        Some("-V" | "--version") => {
+            let _ = writeln!(out, "rsapar {}", env!("CARGO_PKG_VERSION"));
+            EXIT_VALID
+        }
+        Some(command) => {
+            let _ = writeln!(err, "error: unknown command {}\n\n{}", command, USAGE);
+            EXIT_USAGE
+        }
+        None => {
+            let _ = writeln!(err, "{}", USAGE);
+            EXIT_USAGE
+        }
+    }
+}
+
+fn parse_validate_args(args: &[String]) -> Result<ValidateArgs, String> {
+    let mut validate_args = ValidateArgs::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-s" | "--schema" => {
+                validate_args.schema = args.next().ok_or("--schema needs a schema file")?.to_owned();
+            }
+            "--strict" => validate_args.strict = true,
+            "-q" | "--quiet" => validate_args.quiet = true,
+            option if option.starts_with("--schema=") => {
+                validate_args.schema = option["--schema=".len()..].to_owned();
+            }
+            option if option.starts_with('-') && option != "-" => return Err(format!("unknown option {}", option)),
+            file if validate_args.file.is_none() => validate_args.file = Some(file.to_owned()),
+            file => return Err(format!("unexpected argument {}", file)),
+        }
+    }
+
+    if validate_args.schema.is_empty() {
+        return Err("missing --schema".to_string());
+    }
+    // "-" is the standard input
+    validate_args.file = validate_args.file.filter(|file| file != "-");
+    Ok(validate_args)
+}
+
+/// Validate the input with `Parser::groups`, printing the errors, the warnings and the summary
+fn validate(
+    args: &ValidateArgs, stdin: impl BufRead + Send + 'static, out: &mut impl Write, err: &mut impl Write,
+) -> u8 {
+    let config = ParserConfig::new(args.file.to_owned().unwrap_or_default(), args.schema.to_owned())
+        .with_strict_schema(args.strict)
+        .with_warn_mixed_line_separators(true);
+    let parser = match &args.file {
+        Some(_) => Parser::new(config),
+        None => Parser::with_reader(config, stdin),
+    };
+    let mut parser = match parser {
+        Ok(parser) => parser,
+        Err(e) => {
+            let _ = writeln!(err, "error: {:#}", e);
+            return EXIT_UNREADABLE;
+        }
+    };
+
+    let mut summary = Summary::default();
+    for line in parser.schema.fixedwidthschema.iter().flat_map(|fixed_width_schema| &fixed_width_schema.lines) {
+        summary.lines_by_linetype.insert(line.linetype.to_owned(), (0, 0));
+    }
+    let mut read_error = None;
+
+    for record in parser.groups() {
+        match record {
+            Ok(record) => summary.add_record(&record, args.quiet, out),
+            // Errors without code are read errors: the rest of the input cannot be read or split into lines
+            Err(error) if error.code().is_none() => {
+                read_error = Some(error.message);
+                break;
+            }
+            Err(error) => summary.add_error(&error, args.quiet, out),
+        }
+    }
+
+    summary.print(out);
+    match read_error {
+        Some(read_error) => {
+            let _ = writeln!(err, "error: cannot read the input: {}", read_error);
+            EXIT_UNREADABLE
+        }
+        None if summary.errors_by_kind.is_empty() => EXIT_VALID,
+        None => EXIT_INVALID,
+    }
+}
+
+/// Print the changes from the old schema to the new one, failing if one of them is incompatible
+fn diff(old_schema: &str, new_schema: &str, out: &mut impl Write, err: &mut impl Write) -> u8 {
+    let schemas = Schema::new(old_schema).and_then(|old| Ok((old, Schema::new(new_schema)?)));
+    let (old_schema, new_schema) = match schemas {
+        Ok(schemas) => schemas,
+        Err(e) => {
+            let _ = writeln!(err, "error: {:#}", e);
+            return EXIT_UNREADABLE;
+        }
+    };
+
+    let schema_diff = old_schema.diff(&new_schema);
+    for change in &schema_diff.changes {
+        let _ = writeln!(out, "{}", change);
+    }
+    let _ = writeln!(
+        out,
+        "\n{} changes, {} incompatible",
+        schema_diff.changes.len(),
+        schema_diff.incompatible_changes().count()
+    );
+    match schema_diff.is_compatible() {
+        true => EXIT_VALID,
+        false => EXIT_INVALID,
+    }
+}
+
+impl Summary {
+    fn add_line(&mut self, linetype: &str, valid: bool) {
+        self.lines += 1;
+        let (valid_lines, invalid_lines) = self.lines_by_linetype.entry(linetype.to_owned()).or_default();
+        match valid {
+            true => *valid_lines += 1,
+            false => {
+                *invalid_lines += 1;
+                self.invalid_lines += 1;
+            }
+        }
+    }
+
+    /// Count the valid lines of a record and print their warnings
+    fn add_record(&mut self, record: &ProcessedRecord, quiet: bool, out: &mut impl Write) {
+        match record {
+            ProcessedRecord::Line(line) => {
+                self.add_line(&line.linetype, true);
+                for warning in &line.warnings {
+                    let code = message_code(warning).unwrap_or("???");
+                    *self.warnings_by_kind.entry(code.to_owned()).or_default() += 1;
+                    if !quiet {
+                        let _ = writeln!(out, "line {}: {}", line.line_number, warning);
+                    }
+                }
+            }
+            ProcessedRecord::Group(group) => {
+                for record in &group.records {
+                    self.add_record(record, quiet, out);
+                }
+            }
+        }
+    }
+
+    /// Count an error, and its line if it is a line error, and print it
+    fn add_error(&mut self, error: &ProcessedLineError, quiet: bool, out: &mut impl Write) {
+        let code = error.code().unwrap_or("???");
+        match (&error.linetype, code) {
+            (Some(linetype), _) => self.add_line(linetype, false),
+            (None, "001") => self.add_line(UNKNOWN_LINETYPE, false),
+            (None, _) => {} // Errors of the groups and control totals
+        }
+        *self.errors_by_kind.entry(code.to_owned()).or_default() += 1;
+        if !quiet {
+            let _ = writeln!(out, "line {}: {}", error.line_number, error.message);
+        }
+    }
+
+    fn print(&mut self, out: &mut impl Write) {
+        let errors: usize = self.errors_by_kind.values().sum();
+        let warnings: usize = self.warnings_by_kind.values().sum();
+        let _ = writeln!(
+            out,
+            "\n{} lines: {} valid, {} invalid, {} errors, {} warnings",
+            self.lines,
+            self.lines - self.invalid_lines,
+            self.invalid_lines,
+            errors,
+            warnings
+        );
+
+        if !self.errors_by_kind.is_empty() {
+            self.errors_by_kind.sort_keys();
+            let _ = writeln!(out, "\nErrors by kind:");
+            for (code, count) in &self.errors_by_kind {
+                let kind = ERROR_KINDS.iter().find(|(kind_code, _)| kind_code == code).map_or("", |(_, kind)| kind);
+                let _ = writeln!(out, "  err:{} {:<28} {:>8}", code, kind, count);
+            }
+        }
+
+        if !self.warnings_by_kind.is_empty() {
+            self.warnings_by_kind.sort_keys();
+            let _ = writeln!(out, "\nWarnings by kind:");
+            for (code, count) in &self.warnings_by_kind {
+                let kind = WARNING_KINDS.iter().find(|(kind_code, _)| kind_code == code).map_or("", |(_, kind)| kind);
+                let _ = writeln!(out, "  wrn:{} {:<28} {:>8}", code, kind, count);
+            }
+        }
+
+        let _ = writeln!(out, "\nLines by line type:{:>21}{:>10}", "valid", "invalid");
+        for (linetype, (valid_lines, invalid_lines)) in &self.lines_by_linetype {
+            if valid_lines + invalid_lines > 0 {
+                let _ = writeln!(out, "  {:<30}{:>8}{:>10}", linetype, valid_lines, invalid_lines);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Run the command on an input and return its exit code, output and error output
+    fn run_with(args: &[&str], stdin: &str) -> (u8, String, String) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = run(&args, Cursor::new(stdin.to_string()), &mut out, &mut err);
+        (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    /// File written to the temporary directory for a command, removed when dropped
+    struct TempFile(std::path::PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, content: impl AsRef<[u8]>) -> Self {
+            let file_path = std::env::temp_dir().join(format!("rsapar_cli_{}_{}", std::process::id(), name));
+            std::fs::write(&file_path, content).unwrap();
+            Self(file_path)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Generate a header, the body lines and a footer of `example/fixedwidth_schema.xml`
+    fn generate_data(body_lines: usize) -> String {
+        let schema = rsapar::Schema::new("./example/fixedwidth_schema.xml").unwrap();
+        let occurs = [("Header", 1), ("Body", body_lines), ("Footer", 1)];
+        let options = rsapar::GenerateOptions {
+            occurs: occurs.iter().map(|&(name, occurs)| (name.to_string(), occurs)).collect(),
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        schema.generate(&mut data, &options).unwrap();
+        String::from_utf8(data).unwrap()
+    }
+
+    #[test]
+    fn test_validate_file() {
+        let data_file = TempFile::new("data.txt", generate_data(200));
+        let (code, out, err) =
+            run_with(&["validate", "--schema", "./example/fixedwidth_schema.xml", data_file.path()], "");
+        assert_eq!(code, EXIT_VALID, "{}", err);
+        assert!(out.starts_with("\n202 lines: 202 valid, 0 invalid, 0 errors, 0 warnings\n"));
+        assert!(out.contains("\n  Body                               200         0\n"));
+
+        let (code, out, _) = run_with(
+            &["validate", "-s", "./example/fixedwidth_schema_groups.xml", "-q", "./example/fixedwidth_data_groups.txt"],
+            "",
+        );
+        assert_eq!(code, EXIT_VALID);
+        assert!(out.contains("  BatchHeader                          2         0\n"));
+    }
+
+    #[test]
+    fn test_validate_stdin() {
+        let data = generate_data(1);
+        let mut lines = data.lines();
+        let header = lines.next().unwrap();
+        let body = lines.next().unwrap();
+        let stdin = format!("{}\n{}\n{}", header, body, &body[1..]);
+
+        let (code, out, _) = run_with(&["validate", "--schema=./example/fixedwidth_schema.xml", "-"], &stdin);
+        assert_eq!(code, EXIT_INVALID);
+        assert!(out.starts_with("line 3: [err:"));
+        assert!(out.contains("\n3 lines: 2 valid, 1 invalid,"));
+        assert!(out.contains("\nErrors by kind:\n"));
+
+        // Only the summary
+        let (code, out, _) = run_with(&["validate", "--schema=./example/fixedwidth_schema.xml", "--quiet"], &stdin);
+        assert_eq!(code, EXIT_INVALID);
+        assert!(out.starts_with("\n3 lines: 2 valid, 1 invalid,"));
+    }
+
+    #[test]
+    fn test_validate_warnings() {
+        // The warnings are printed and counted, but the input is still valid
+        let (code, out, err) = run_with(
+            &[
+                "validate",
+                "-s",
+                "./example/fixedwidth_schema_auto.xml",
+                "./example/fixedwidth_data_mixed_separators.txt",
+            ],
+            "",
+        );
+        assert_eq!(code, EXIT_VALID, "{}", err);
+        assert!(out.starts_with("line 3: [wrn:001]|"));
+        assert!(out.contains("\n4 lines: 4 valid, 0 invalid, 0 errors, 1 warnings\n"));
+        assert!(out.contains("\nWarnings by kind:\n  wrn:001 mixed line separators "));
+    }
+
+    #[test]
+    fn test_validate_unreadable() {
+        let (code, _, err) =
+            run_with(&["validate", "-s", "./example/fixedwidth_schema.xml", "./example/missing.txt"], "");
+        assert_eq!(code, EXIT_UNREADABLE);
+        assert!(err.starts_with("error: "));
+
+        let (code, _, _) =
+            run_with(&["validate", "-s", "./example/missing.xml", "./example/fixedwidth_data_groups.txt"], "");
+        assert_eq!(code, EXIT_UNREADABLE);
+    }
+
+    #[test]
+    fn test_diff() {
+        let (code, out, _) =
+            run_with(&["diff", "./example/fixedwidth_schema.xml", "./example/fixedwidth_schema.xml"], "");
+        assert_eq!(code, EXIT_VALID);
+        assert_eq!(out, "\n0 changes, 0 incompatible\n");
+
+        // A longer FileName and a RecordDate not emitted anymore in the Header
+        let schema = std::fs::read_to_string("./example/fixedwidth_schema.xml").unwrap();
+        let schema = schema
+            .replacen(r#"maxlength="20""#, r#"maxlength="21""#, 1)
+            .replacen(r#"name="FileName" length="11""#, r#"name="FileName" length="12""#, 1)
+            .replacen(r#"name="RecordDate" length="8""#, r#"name="RecordDate" length="8" ignoreread="true""#, 1);
+        let schema_file = TempFile::new("diff.xml", schema);
+        let (code, out, err) = run_with(&["diff", "./example/fixedwidth_schema.xml", schema_file.path()], "");
+        assert_eq!(code, EXIT_INVALID, "{}", err);
+        assert!(out.contains("Header.FileName: length 11 -> 12 (incompatible)\n"));
+        assert!(out.contains("Header.RecordDate: ignoreread false -> true (incompatible)\n"));
+
+        let (code, _, err) = run_with(&["diff", "./example/fixedwidth_schema.xml", "./example/missing.xml"], "");
+        assert_eq!(code, EXIT_UNREADABLE);
+        assert!(err.starts_with("error: "));
+    }
+
+    #[test]
+    fn test_usage() {
+        assert_eq!(run_with(&[], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["check"], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["validate", "./example/fixedwidth_data_groups.txt"], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["validate", "-s", "schema.xml", "--fast"], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["validate", "-s", "schema.xml", "a.txt", "b.txt"], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["diff", "./example/fixedwidth_schema.xml"], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["--help"], "").0, EXIT_VALID);
+    }
+}
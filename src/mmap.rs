@@ -0,0 +1,215 @@
+//! Memory-mapped parsing of files on local disk (feature "mmap").
+//! The lines and cell values are borrowed from the mapped file instead of being copied.
+//! The file must not be modified or truncated by other processes while it is being parsed.
+
+use anyhow::{anyhow, Context, Error, Result};
+use memmap2::Mmap;
+use std::fs::File;
+
+use crate::control_total::WithControlTotals;
+use crate::parser::{LineSplit, MixedLineSeparators};
+use crate::{schema, Compression, ParserConfig, ProcessedLine, ProcessedLineError};
+
+/// A line of the mapped file, borrowed from it.
+#[derive(Debug)]
+pub struct ReadLineRef<'a> {
+    pub line_number: usize,
+    pub line_content: &'a str,
+    pub line_separator: Option<&'static str>, // The separator found at the end of the line (lineseparator="auto")
+}
+
+#[derive(Debug)]
+pub struct MmapParser {
+    pub config: ParserConfig,
+    pub schema: schema::Schema,
+    line_split: LineSplit,
+    mmap: Mmap, // The input file mapped into memory
+}
+
+/// Splits the mapped file into lines in the same way as the `Parser` file buffer.
+struct SliceLines<'a> {
+    data: &'a [u8],
+    position: usize,
+    line_split: &'a LineSplit,
+    current_line: usize,
+    finished: bool,
+}
+
+impl<'a> Iterator for SliceLines<'a> {
+    type Item = std::io::Result<ReadLineRef<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let rest = &self.data[self.position..];
+        let (line, line_separator, consumed) = match self.line_split {
+            LineSplit::Separator(finder) => match finder.find(rest) {
+                Some(index) => (&rest[..index], None, index + finder.needle().len()),
+                None => {
+                    self.finished = true;
+                    (rest, None, rest.len())
+                }
+            },
+            LineSplit::Auto => match memchr::memchr2(b'\n', b'\r', rest) {
+                Some(index) if rest[index] == b'\n' => (&rest[..index], Some("\\n"), index + 1),
+                Some(index) if rest.get(index + 1) == Some(&b'\n') => (&rest[..index], Some("\\r\\n"), index + 2),
+                Some(index) => (&rest[..index], Some("\\r"), index + 1),
+                None => {
+                    self.finished = true;
+                    (rest, None, rest.len())
+                }
+            },
+            LineSplit::RecordLength { schema, condition_length } => {
+                if rest.is_empty() {
+                    self.finished = true;
+                    return None;
+                }
+
+                let line_prefix = String::from_utf8_lossy(&rest[..(*condition_length).min(rest.len())]);
+                match schema.find_record_length(&line_prefix) {
+                    Some(record_length) => {
+                        let record_length = record_length.min(rest.len());
+                        (&rest[..record_length], None, record_length)
+                    }
+                    None => {
+                        // Without the record length the rest of the file cannot be split.
+                        self.finished = true;
+                        return Some(Err(LineSplit::record_length_error(self.current_line + 1)));
+                    }
+                }
+            }
+        };
+        self.position += consumed;
+
+        let line = match std::str::from_utf8(line) {
+            Ok(line) => line,
+            Err(e) => return Some(Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e))),
+        };
+
+        self.current_line += 1;
+        Some(Ok(ReadLineRef { line_number: self.current_line, line_content: line, line_separator }))
+    }
+}
+
+/// The `MmapParser` struct is a `Parser` for files on local disk that maps the file into memory
+/// and yields lines and cell values borrowed from it, without allocating a `String` per line or cell.
+impl MmapParser {
+    /// Creates a new `MmapParser` instance with the given configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration for the parser, including the file path and schema.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MmapParser` instance if successful, or an `Error` if an error occurred.
+    pub fn new(config: ParserConfig) -> Result<Self, Error> {
+        // Open the file specified in the configuration and map it into memory
+        let file = File::open(&config.file_path).context("Failed to open file")?;
+        // SAFETY: the file must not be modified while it is mapped (see the module documentation).
+        let mmap = unsafe { Mmap::map(&file) }.context("Failed to map file")?;
+
+        // The values are borrowed from the mapped file, so compressed files cannot be read
+        if let Some(compression) = Compression::detect(&mmap[..mmap.len().min(4)], &config.file_path) {
+            return Err(anyhow!("The file is compressed with {:?}, use `Parser` to read it", compression));
+        }
+
+        // Create a new schema instance based on the file schema specified in the configuration
+        let schema = schema::Schema::from_config(&config)?;
+
+        // Split by the newline characters defined in the schema, or by record length if there are none
+        let line_split = LineSplit::from_schema(&schema)?;
+
+        Ok(Self { config, schema, line_split, mmap })
+    }
+
+    /// Returns an iterator over the lines of the file, borrowed from the mapped file.
+    ///
+    /// This method does not process the lines according to the schema.
+    /// Use the `iter` method to process each line according to the schema.
+    ///
+    /// # Returns
+    ///
+    /// An iterator that yields each line of the file as a `Result` containing either a `ReadLineRef` or an `std::io::Error`.
+    pub fn lines(&self) -> impl Iterator<Item = Result<ReadLineRef<'_>, std::io::Error>> + '_ {
+        SliceLines { data: &self.mmap, position: 0, line_split: &self.line_split, current_line: 0, finished: false }
+    }
+
+    /// Returns an iterator that processes each line of the file according to the schema.
+    ///
+    /// # Returns
+    ///
+    /// An iterator that yields each processed line as a `Result` containing either a `ProcessedLine`,
+    /// borrowing its values from the mapped file, or a `ProcessedLineError`.
+    pub fn iter(&self) -> impl Iterator<Item = Result<ProcessedLine<'_>, ProcessedLineError>> + '_ {
+        let warn_mixed_line_separators = self.config.warn_mixed_line_separators;
+        let mut mixed_line_separators = MixedLineSeparators::default();
+
+        let read_lines = self.lines().filter(|result_read_line| {
+            !matches!(result_read_line, Ok(read_line) if self.schema.is_ignored_line(read_line.line_content))
+        });
+        let lines = read_lines.map(move |result_read_line| {
+            let read_line = match result_read_line {
+                Ok(read_line) => read_line,
+                Err(err) => {
+                    return Err(ProcessedLineError::new(0, format!("{:?}", err)));
+                }
+            };
+
+            let mut processed_line = self.schema.validate_line_ref(read_line.line_number, read_line.line_content)?;
+            if warn_mixed_line_separators {
+                if let Some(warning) = mixed_line_separators.check(read_line.line_separator) {
+                    processed_line.warnings.push(warning);
+                }
+            }
+            Ok(processed_line)
+        });
+
+        // The control totals of the file are checked after the last line
+        WithControlTotals::new(lines, &self.schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    /// The memory-mapped parser yields the same lines as the buffered parser.
+    #[test]
+    fn test_mmap_parser() {
+        for (file_path, file_schema) in [
+            (crate::schema_generate::sample_data(), "./example/fixedwidth_schema.xml"),
+            ("./example/fixedwidth_data_mixed_separators.txt", "./example/fixedwidth_schema_auto.xml"),
+            ("./example/fixedwidth_data_groups.txt", "./example/fixedwidth_schema_groups.xml"),
+        ] {
+            let config = || ParserConfig {
+                file_path: file_path.to_string(),
+                file_schema: file_schema.to_string(),
+                warn_mixed_line_separators: true,
+                ..Default::default()
+            };
+
+            let mmap_parser = MmapParser::new(config()).unwrap();
+            let mut parser = Parser::new(config()).unwrap();
+
+            let mut count = 0;
+            for (mmap_result, result) in mmap_parser.iter().zip(parser.iter_mut()) {
+                let mmap_line = mmap_result.unwrap();
+                let line = result.unwrap();
+                assert_eq!(mmap_line.line_number, line.line_number);
+                assert_eq!(mmap_line.linetype, line.linetype);
+                assert_eq!(mmap_line.warnings, line.warnings);
+                assert!(mmap_line
+                    .cell_values
+                    .iter()
+                    .map(|(name, value)| (*name, *value))
+                    .eq(line.cell_values.iter().map(|(name, value)| (name.as_str(), value.as_str()))));
+                count += 1;
+            }
+            assert_eq!(count, mmap_parser.lines().count());
+        }
+    }
+}
@@ -1,138 +1,777 @@
-use anyhow::Context;
+use anyhow::anyhow;
 use anyhow::Error;
 use anyhow::Result;
 
-use crossbeam::channel::Receiver;
+use crossbeam::channel::{bounded, unbounded, Receiver, Sender};
 use indexmap::map::IndexMap;
-use std::fs::File;
+use memchr::memmem;
+use std::collections::BTreeMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread::JoinHandle;
 
-use std::io::{BufRead, BufReader};
+use std::io::BufRead;
 
+use crate::compression;
+use crate::control_total::WithControlTotals;
+use crate::group::{Grouper, ProcessedRecord};
 use crate::schema;
 
 pub type WorkerFunction =
     fn(Receiver<(usize, String)>, schema::Schema) -> Vec<Result<ProcessedLineOk, ProcessedLineError>>;
 
+/// Number of lines sent to a worker at once by `Parser::par_iter`
+const PAR_ITER_BATCH_SIZE: usize = 1024;
+/// Number of batches per worker that may be read ahead of the line being yielded by `Parser::par_iter`
+const PAR_ITER_BATCHES_PER_WORKER: usize = 2;
+
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct ProcessedLineOk {
     pub line_number: usize,
     pub cell_values: IndexMap<String, String>,
     pub linetype: String,
+    pub warnings: Vec<String>,
+}
+
+/// A processed line borrowing the cell values from the line text and the names from the schema.
+/// See `Schema::validate_line_ref` and `MmapParser` (feature "mmap").
+#[derive(Debug)]
+pub struct ProcessedLine<'a> {
+    pub line_number: usize,
+    pub cell_values: IndexMap<&'a str, &'a str>,
+    pub linetype: &'a str,
+    pub warnings: Vec<String>,
+}
+
+impl ProcessedLine<'_> {
+    /// Copies the borrowed values into a `ProcessedLineOk`.
+    pub fn into_owned(self) -> ProcessedLineOk {
+        ProcessedLineOk {
+            line_number: self.line_number,
+            cell_values: self
+                .cell_values
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), value.to_owned()))
+                .collect(),
+            linetype: self.linetype.to_owned(),
+            warnings: self.warnings,
+        }
+    }
 }
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct ProcessedLineError {
     pub line_number: usize,
     pub message: String,
+    pub linetype: Option<String>, // Line type of the invalid line, None for unknown lines and other errors
+}
+
+/// Kinds of the errors of the parsers by the code of their message, e.g. "007" for "[err:007]|Amount|number|..."
+pub const ERROR_KINDS: [(&str, &str); 28] = [
+    ("001", "unknown line type"),
+    ("002", "line length"),
+    ("003", "cell out of the line"),
+    ("004", "date format"),
+    ("005", "string pattern"),
+    ("006", "string pattern not compiled"),
+    ("007", "number format"),
+    ("008", "line outside its group"),
+    ("009", "group occurs min"),
+    ("010", "group occurs max"),
+    ("011", "control total mismatch"),
+    ("012", "control total line missing"),
+    ("013", "control total not a number"),
+    ("014", "mandatory"),
+    ("015", "minvalue"),
+    ("016", "maxvalue"),
+    ("017", "values"),
+    ("018", "minlength"),
+    ("019", "maxlength"),
+    ("020", "typed value"),
+    ("021", "boolean format"),
+    ("022", "integer format"),
+    ("023", "float format"),
+    ("024", "time format"),
+    ("025", "datetime format"),
+    ("026", "enum format"),
+    ("027", "filler not empty"),
+    ("028", "implieddecimal format"),
+];
+
+/// Kinds of the warnings of the processed lines by the code of their message, e.g. "001" for "[wrn:001]|..."
+pub const WARNING_KINDS: [(&str, &str); 1] = [("001", "mixed line separators")];
+
+/// Get the code of an error or warning message, e.g. "007" for "[err:007]|..." or "001" for "[wrn:001]|..."
+pub fn message_code(message: &str) -> Option<&str> {
+    let code = message.strip_prefix("[err:").or_else(|| message.strip_prefix("[wrn:"))?.get(..3)?;
+    code.chars().all(|c| c.is_ascii_digit()).then_some(code)
+}
+
+impl ProcessedLineError {
+    pub fn new(line_number: usize, message: impl Into<String>) -> Self {
+        Self { line_number, message: message.into(), linetype: None }
+    }
+
+    /// Get the code of the error, see `ERROR_KINDS`
+    /// Returns None for the errors reading the file, which have no code
+    pub fn code(&self) -> Option<&str> {
+        message_code(&self.message)
+    }
 }
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct ReadLine {
     pub line_number: usize,
     pub line_content: String,
+    pub line_separator: Option<&'static str>, // The separator found at the end of the line (lineseparator="auto")
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
+#[non_exhaustive]
 pub struct ParserConfig {
     pub file_path: String,
     pub file_schema: String,
+    pub warn_mixed_line_separators: bool, // Warn on lines not ending like the first one (lineseparator="auto")
+    pub strict_schema: bool,              // Fail on the mistakes reported by `Schema::lint`
+    pub projection: Vec<String>,          // Cells emitted in the cell values, all if empty (see `Schema::project`)
+}
+
+/// Options are added without breaking the callers: build the configuration with `ParserConfig::new`
+/// and the `with_` methods.
+impl ParserConfig {
+    /// Creates the configuration to parse `file_path` with the schema file `file_schema`, other options off.
+    pub fn new(file_path: impl Into<String>, file_schema: impl Into<String>) -> Self {
+        Self { file_path: file_path.into(), file_schema: file_schema.into(), ..Default::default() }
+    }
+
+    pub fn with_warn_mixed_line_separators(mut self, warn_mixed_line_separators: bool) -> Self {
+        self.warn_mixed_line_separators = warn_mixed_line_separators;
+        self
+    }
+
+    pub fn with_strict_schema(mut self, strict_schema: bool) -> Self {
+        self.strict_schema = strict_schema;
+        self
+    }
+
+    pub fn with_projection(mut self, projection: Vec<String>) -> Self {
+        self.projection = projection;
+        self
+    }
+}
+
+/// How the file is split into lines
+#[derive(Debug)]
+pub(crate) enum LineSplit {
+    /// Lines end with the newline characters defined in the schema (already unescaped)
+    Separator(memmem::Finder<'static>),
+    /// The file has no line separators, each record is split by its length (see `Schema::find_record_length`)
+    RecordLength { schema: schema::Schema, condition_length: usize },
+    /// Lines end with "\n", "\r\n" or "\r", whichever is found first
+    Auto,
+}
+
+impl LineSplit {
+    /// Creates the line split for the "lineseparator" and "recordlength" attributes of the schema.
+    pub(crate) fn from_schema(schema: &schema::Schema) -> Result<Self, Error> {
+        if schema.is_record_length_delimited() {
+            let condition_length = schema.get_line_condition_length();
+            return Ok(LineSplit::RecordLength { schema: schema.clone(), condition_length });
+        }
+        if schema.is_auto_line_separator() {
+            return Ok(LineSplit::Auto);
+        }
+
+        let newline_characters = Self::unescape(schema.get_newline_characters());
+        if newline_characters.is_empty() {
+            return Err(anyhow!("Invalid line separator: {}", schema.get_newline_characters()));
+        }
+        Ok(LineSplit::Separator(memmem::Finder::new(&newline_characters).into_owned()))
+    }
+
+    /// Converts the newline characters as written in the schema (e.g. "\r\n") to bytes.
+    pub(crate) fn unescape(newline_characters: &str) -> Vec<u8> {
+        let mut newline_characters_bytes = Vec::new();
+        let mut chars = newline_characters.chars();
+        while let Some(ch) = chars.next() {
+            if ch == '\\' {
+                match chars.next() {
+                    Some('n') => newline_characters_bytes.push(b'\n'),
+                    Some('r') => newline_characters_bytes.push(b'\r'),
+                    Some('t') => newline_characters_bytes.push(b'\t'),
+                    Some('f') => newline_characters_bytes.push(b'\x0C'),
+                    Some('0') => newline_characters_bytes.push(0),
+                    Some(other) => newline_characters_bytes.push(other as u8),
+                    None => break,
+                }
+            } else {
+                newline_characters_bytes.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+        }
+        newline_characters_bytes
+    }
+
+    /// Error for a record whose line type is not found, so the rest of the file cannot be split.
+    pub(crate) fn record_length_error(record_number: usize) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("no match found for schema line type to split record {}", record_number),
+        )
+    }
+}
+
+/// Warns on lines ending with a different separator than the first line (lineseparator="auto")
+#[derive(Debug, Default)]
+pub(crate) struct MixedLineSeparators {
+    first_line_separator: Option<&'static str>,
+}
+
+impl MixedLineSeparators {
+    /// Returns the warning message if the line separator differs from the first one found.
+    pub(crate) fn check(&mut self, line_separator: Option<&'static str>) -> Option<String> {
+        let line_separator = line_separator?;
+        match self.first_line_separator {
+            None => {
+                self.first_line_separator = Some(line_separator);
+                None
+            }
+            Some(expected) if line_separator != expected => Some(format!(
+                "[wrn:001]|line|lineseparator|the line ends with {} but was expected {}",
+                line_separator, expected
+            )),
+            Some(_) => None,
+        }
+    }
 }
 
+/// Splits the chunks read from the file into lines according to the `LineSplit`.
+/// The bytes of the current line are appended to the buffer given by the reader,
+/// so the same scanner is used for blocking and async readers.
 #[derive(Debug)]
+pub(crate) struct LineScanner {
+    line_split: LineSplit,
+    finished: bool,               // The last line was returned, or the rest of the file cannot be split
+    pending_cr: bool,             // The last chunk ended with "\r", it may be followed by "\n" (LineSplit::Auto)
+    record_length: Option<usize>, // Length of the current record once its line type is resolved (LineSplit::RecordLength)
+    records: usize,               // Number of records returned (LineSplit::RecordLength)
+    pub(crate) line_separator: Option<&'static str>, // The separator found at the end of the last line (LineSplit::Auto)
+    pub(crate) detected_line_separator: Option<&'static str>, // The first separator found in the file (LineSplit::Auto)
+}
+
+impl LineScanner {
+    pub(crate) fn new(line_split: LineSplit) -> Self {
+        Self {
+            line_split,
+            finished: false,
+            pending_cr: false,
+            record_length: None,
+            records: 0,
+            line_separator: None,
+            detected_line_separator: None,
+        }
+    }
+
+    /// Scans the next chunk read from the file.
+    /// The bytes of the current line are appended to `buf`.
+    /// Returns the number of bytes of `chunk` consumed, and the length of the line in `buf` if it is complete.
+    pub(crate) fn scan(&mut self, buf: &mut Vec<u8>, chunk: &[u8]) -> (usize, Option<std::io::Result<usize>>) {
+        match self.line_split {
+            LineSplit::Separator(_) => self.scan_separated_line(buf, chunk),
+            LineSplit::Auto => self.scan_auto_line(buf, chunk),
+            LineSplit::RecordLength { .. } => self.scan_record(buf, chunk),
+        }
+    }
+
+    /// Check if the rest of the file cannot be split, so the reader can stop reading.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns the next line once the end of the file is reached, or None if there are no more lines.
+    pub(crate) fn end_of_file(&mut self, buf: &mut Vec<u8>) -> Option<std::io::Result<usize>> {
+        match self.line_split {
+            LineSplit::Separator(_) | LineSplit::Auto => {
+                if self.pending_cr {
+                    // The file ends with "\r"
+                    self.pending_cr = false;
+                    return Some(Ok(self.end_line(buf.len(), Some("\\r"))));
+                }
+                if self.finished {
+                    return None;
+                }
+                self.finished = true;
+                Some(Ok(self.end_line(buf.len(), None)))
+            }
+            LineSplit::RecordLength { .. } => {
+                if buf.is_empty() {
+                    return None;
+                }
+                // The last record may be shorter than its line type
+                let record_length = match self.record_length.take() {
+                    Some(record_length) => record_length,
+                    None => match self.resolve_record_length(buf) {
+                        Ok(record_length) => record_length,
+                        Err(e) => return Some(Err(e)),
+                    },
+                };
+                Some(Ok(self.end_line(record_length.min(buf.len()), None)))
+            }
+        }
+    }
+
+    /// Search the line separator in the chunk, it may also start at the end of the previous chunk.
+    fn scan_separated_line(&mut self, buf: &mut Vec<u8>, chunk: &[u8]) -> (usize, Option<std::io::Result<usize>>) {
+        let finder = match &self.line_split {
+            LineSplit::Separator(finder) => finder,
+            LineSplit::RecordLength { .. } | LineSplit::Auto => {
+                unreachable!("the file is not split by a line separator")
+            }
+        };
+        let separator = finder.needle();
+
+        // The separator started at the end of the previous chunk
+        let split_separator =
+            (1..separator.len()).rev().find(|&k| buf.ends_with(&separator[..k]) && chunk.starts_with(&separator[k..]));
+        if let Some(k) = split_separator {
+            buf.truncate(buf.len() - k);
+            return (separator.len() - k, Some(Ok(buf.len())));
+        }
+
+        match finder.find(chunk) {
+            Some(index) => {
+                buf.extend_from_slice(&chunk[..index]);
+                (index + separator.len(), Some(Ok(buf.len())))
+            }
+            None => {
+                buf.extend_from_slice(chunk);
+                (chunk.len(), None)
+            }
+        }
+    }
+
+    /// Search "\n", "\r\n" or "\r" in the chunk.
+    fn scan_auto_line(&mut self, buf: &mut Vec<u8>, chunk: &[u8]) -> (usize, Option<std::io::Result<usize>>) {
+        if self.pending_cr {
+            // The previous chunk ended with "\r"
+            self.pending_cr = false;
+            return if chunk.first() == Some(&b'\n') {
+                (1, Some(Ok(self.end_line(buf.len(), Some("\\r\\n")))))
+            } else {
+                (0, Some(Ok(self.end_line(buf.len(), Some("\\r")))))
+            };
+        }
+
+        let index = match memchr::memchr2(b'\n', b'\r', chunk) {
+            Some(index) => index,
+            None => {
+                buf.extend_from_slice(chunk);
+                return (chunk.len(), None);
+            }
+        };
+        buf.extend_from_slice(&chunk[..index]);
+
+        match (chunk[index], chunk.get(index + 1)) {
+            (b'\n', _) => (index + 1, Some(Ok(self.end_line(buf.len(), Some("\\n"))))),
+            (_, Some(b'\n')) => (index + 2, Some(Ok(self.end_line(buf.len(), Some("\\r\\n"))))),
+            (_, Some(_)) => (index + 1, Some(Ok(self.end_line(buf.len(), Some("\\r"))))),
+            (_, None) => {
+                // Look ahead in the next chunk for "\r\n"
+                self.pending_cr = true;
+                (index + 1, None)
+            }
+        }
+    }
+
+    /// Read the leading characters to resolve the line type and its length, then the rest of the record.
+    /// Any bytes already in `buf` beyond the end of the previous record belong to this one.
+    fn scan_record(&mut self, buf: &mut Vec<u8>, chunk: &[u8]) -> (usize, Option<std::io::Result<usize>>) {
+        let condition_length = match &self.line_split {
+            LineSplit::RecordLength { condition_length, .. } => *condition_length,
+            LineSplit::Separator(_) | LineSplit::Auto => unreachable!("the file is not split by record length"),
+        };
+
+        let mut consumed = 0;
+        let record_length = match self.record_length {
+            Some(record_length) => record_length,
+            None => {
+                consumed = condition_length.saturating_sub(buf.len()).min(chunk.len());
+                buf.extend_from_slice(&chunk[..consumed]);
+                if buf.len() < condition_length {
+                    return (consumed, None);
+                }
+                match self.resolve_record_length(buf) {
+                    Ok(record_length) => *self.record_length.insert(record_length),
+                    Err(e) => return (consumed, Some(Err(e))),
+                }
+            }
+        };
+
+        let missing = record_length.saturating_sub(buf.len()).min(chunk.len() - consumed);
+        buf.extend_from_slice(&chunk[consumed..consumed + missing]);
+        consumed += missing;
+
+        if buf.len() < record_length {
+            return (consumed, None);
+        }
+        self.record_length = None;
+        (consumed, Some(Ok(self.end_line(record_length, None))))
+    }
+
+    /// Resolve the length of the record from its leading characters.
+    fn resolve_record_length(&mut self, buf: &mut Vec<u8>) -> std::io::Result<usize> {
+        let (schema, condition_length) = match &self.line_split {
+            LineSplit::RecordLength { schema, condition_length } => (schema, *condition_length),
+            LineSplit::Separator(_) | LineSplit::Auto => unreachable!("the file is not split by record length"),
+        };
+
+        let line_prefix = String::from_utf8_lossy(&buf[..condition_length.min(buf.len())]);
+        match schema.find_record_length(&line_prefix) {
+            Some(record_length) => Ok(record_length),
+            None => {
+                // Without the record length the rest of the file cannot be split.
+                self.finished = true;
+                buf.clear();
+                Err(LineSplit::record_length_error(self.records + 1))
+            }
+        }
+    }
+
+    /// Keep the separator found at the end of the line and return the line length.
+    fn end_line(&mut self, line_length: usize, line_separator: Option<&'static str>) -> usize {
+        self.line_separator = line_separator;
+        if self.detected_line_separator.is_none() {
+            self.detected_line_separator = line_separator;
+        }
+        self.records += 1;
+        line_length
+    }
+}
+
 struct FileBuffer<R: BufRead> {
     reader: R,
     current_line: usize,
-    newline_characters: Vec<u8>, // The newline characters used to separate lines
-    buf: Vec<u8>,
-    finished: bool,
+    scanner: LineScanner,
+    buf: Vec<u8>, // Bytes of the current line, reused between lines
+    eof: bool,    // The end of the file was reached
 }
 
 #[derive(Debug)]
 pub struct Parser {
     pub config: ParserConfig,
     pub schema: schema::Schema,
-    file_buffer: FileBuffer<BufReader<File>>, // File buffer for reading lines from the input file
+    file_buffer: FileBuffer<Box<dyn BufRead + Send>>, // File buffer for reading lines from the input file
+}
+
+impl<R: BufRead> std::fmt::Debug for FileBuffer<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The reader may be a decompressor, which does not implement `Debug`
+        f.debug_struct("FileBuffer")
+            .field("current_line", &self.current_line)
+            .field("scanner", &self.scanner)
+            .field("eof", &self.eof)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<R: BufRead> FileBuffer<R> {
-    fn new(reader: R, newline_characters: String) -> Self {
-        Self {
-            reader,
-            current_line: 0,
-            newline_characters: newline_characters.into_bytes(),
-            buf: Vec::new(),
-            finished: false,
+    fn new(reader: R, line_split: LineSplit) -> Self {
+        Self { reader, current_line: 0, scanner: LineScanner::new(line_split), buf: Vec::new(), eof: false }
+    }
+
+    /// Read chunks from the reader until the scanner finds the end of the line.
+    /// Returns the length of the line in `buf`.
+    fn read_line(&mut self) -> Option<std::io::Result<usize>> {
+        loop {
+            if self.eof || self.scanner.is_finished() {
+                return self.scanner.end_of_file(&mut self.buf);
+            }
+
+            let chunk = match self.reader.fill_buf() {
+                Ok(chunk) => chunk,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e)),
+            };
+            if chunk.is_empty() {
+                self.eof = true;
+                continue;
+            }
+
+            let (consumed, line_length) = self.scanner.scan(&mut self.buf, chunk);
+            self.reader.consume(consumed);
+            if line_length.is_some() {
+                return line_length;
+            }
         }
     }
 }
 
-/// This implementation is specialized for reading lines from a file with custom newline characters.
+/// This implementation is specialized for reading lines from a file with custom newline characters
+/// or fixed length records without line separators.
 impl<R: BufRead> Iterator for FileBuffer<R> {
     type Item = std::io::Result<ReadLine>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.finished && self.buf.is_empty() {
-            return None;
-        }
+        let line_length = match self.read_line()? {
+            Ok(line_length) => line_length,
+            Err(e) => return Some(Err(e)),
+        };
 
-        let newline_characters_str = match String::from_utf8(self.newline_characters.to_owned()) {
-            Ok(v) => v,
-            Err(e) => return Some(Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e))),
+        let line = match take_line(&mut self.buf, line_length) {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e)),
         };
 
-        let mut newline_characters_bytes = Vec::new();
-        let mut chars = newline_characters_str.chars();
-        while let Some(ch) = chars.next() {
-            if ch == '\\' {
-                match chars.next() {
-                    Some('n') => newline_characters_bytes.push(b'\n'),
-                    Some('r') => newline_characters_bytes.push(b'\r'),
-                    Some('t') => newline_characters_bytes.push(b'\t'),
-                    Some('f') => newline_characters_bytes.push(b'\x0C'),
-                    Some('0') => newline_characters_bytes.push(0),
-                    Some(other) => newline_characters_bytes.push(other as u8),
-                    None => break,
+        self.current_line += 1;
+        Some(Ok(ReadLine {
+            line_number: self.current_line,
+            line_content: line,
+            line_separator: self.scanner.line_separator,
+        }))
+    }
+}
+
+/// Copy the line out of `buf` so its allocation is reused for the next line.
+pub(crate) fn take_line(buf: &mut Vec<u8>, line_length: usize) -> std::io::Result<String> {
+    let line = std::str::from_utf8(&buf[..line_length]).map(str::to_owned);
+    buf.drain(..line_length);
+    line.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Map each line of the file to a processed line based on the schema validation,
+/// followed by the errors of the control totals of the file
+fn validate_lines<'a>(
+    file_buffer: &'a mut FileBuffer<Box<dyn BufRead + Send>>, schema: &'a schema::Schema,
+    warn_mixed_line_separators: bool,
+) -> impl Iterator<Item = Result<ProcessedLineOk, ProcessedLineError>> + 'a {
+    let mut mixed_line_separators = MixedLineSeparators::default();
+
+    let read_lines = std::iter::from_fn(move || file_buffer.next()).filter(|result_read_line| {
+        !matches!(result_read_line, Ok(read_line) if schema.is_ignored_line(&read_line.line_content))
+    });
+    let lines = read_lines.map(move |result_read_line| {
+        let read_line = match result_read_line {
+            Ok(read_line) => read_line,
+            Err(err) => {
+                return Err(ProcessedLineError::new(0, format!("{:?}", err)));
+            }
+        };
+
+        let result: Result<ProcessedLineOk, ProcessedLineError> =
+            schema.validate_line(read_line.line_number, read_line.line_content);
+        match result {
+            Ok(mut processed_line) => {
+                if warn_mixed_line_separators {
+                    if let Some(warning) = mixed_line_separators.check(read_line.line_separator) {
+                        processed_line.warnings.push(warning);
+                    }
                 }
-            } else {
-                newline_characters_bytes.push(ch as u8);
+                Ok(processed_line)
             }
+            Err(processed_line) => Err(processed_line),
         }
+    });
 
-        let mut match_index = 0;
+    // The control totals of the file are checked after the last line
+    WithControlTotals::new(lines, schema)
+}
 
-        loop {
-            let mut byte = [0; 1];
-            match self.reader.read_exact(&mut byte) {
-                Ok(()) => {
-                    self.buf.push(byte[0]);
-                    if byte[0] == newline_characters_bytes[match_index] {
-                        match_index += 1;
-                        if match_index == newline_characters_bytes.len() {
-                            self.buf.truncate(self.buf.len() - newline_characters_bytes.len());
+/// A batch of lines validated by a worker of `Parser::par_iter`, with the separator found at the end of each line
+type ParBatch = Vec<(Result<ProcessedLineOk, ProcessedLineError>, Option<&'static str>)>;
+
+/// Iterator of `Parser::par_iter`.
+/// The lines are read in batches on the calling thread, validated by the workers and yielded
+/// in the original order. At most `max_batches` batches are read ahead of the line being yielded.
+struct ParIter<'a> {
+    file_buffer: &'a mut FileBuffer<Box<dyn BufRead + Send>>,
+    batch_sender: Option<Sender<(usize, Vec<std::io::Result<ReadLine>>)>>,
+    result_receiver: Receiver<(usize, ParBatch)>,
+    workers: Vec<JoinHandle<()>>,
+    max_batches: usize,
+    sent_batches: usize,
+    next_batch: usize,
+    pending_batches: BTreeMap<usize, ParBatch>, // Batches validated before the previous ones
+    current_batch: std::vec::IntoIter<(Result<ProcessedLineOk, ProcessedLineError>, Option<&'static str>)>,
+    warn_mixed_line_separators: bool,
+    mixed_line_separators: MixedLineSeparators,
+}
+
+impl<'a> ParIter<'a> {
+    fn new(
+        file_buffer: &'a mut FileBuffer<Box<dyn BufRead + Send>>, schema: &schema::Schema,
+        warn_mixed_line_separators: bool, n_workers: usize,
+    ) -> Self {
+        let n_workers = match n_workers {
+            0 => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            n_workers => n_workers,
+        };
+        let max_batches = n_workers * PAR_ITER_BATCHES_PER_WORKER;
+
+        let (batch_sender, batch_receiver) = bounded::<(usize, Vec<std::io::Result<ReadLine>>)>(max_batches);
+        let (result_sender, result_receiver) = unbounded();
+
+        // The schema is shared by all the workers
+        let schema = Arc::new(schema.clone());
+
+        let workers = (0..n_workers)
+            .map(|_| {
+                let batch_receiver = batch_receiver.clone();
+                let result_sender = result_sender.clone();
+                let schema = Arc::clone(&schema);
+
+                std::thread::spawn(move || {
+                    for (batch_number, read_lines) in batch_receiver {
+                        // A panic is the result of its batch, otherwise the iterator would wait for it forever
+                        let first_line_number = read_lines
+                            .iter()
+                            .find_map(|result_read_line| result_read_line.as_ref().ok())
+                            .map_or(0, |read_line| read_line.line_number);
+                        let results = catch_unwind(AssertUnwindSafe(|| Self::validate_batch(&schema, read_lines)))
+                            .unwrap_or_else(|panic| {
+                                let message = format!(
+                                    "worker thread panicked while validating the lines: {}",
+                                    Self::panic_message(&*panic)
+                                );
+                                vec![(Err(ProcessedLineError::new(first_line_number, message)), None)]
+                            });
+                        if result_sender.send((batch_number, results)).is_err() {
                             break;
                         }
-                    } else {
-                        match_index = 0;
                     }
+                })
+            })
+            .collect();
+
+        Self {
+            file_buffer,
+            batch_sender: Some(batch_sender),
+            result_receiver,
+            workers,
+            max_batches,
+            sent_batches: 0,
+            next_batch: 0,
+            pending_batches: BTreeMap::new(),
+            current_batch: Vec::new().into_iter(),
+            warn_mixed_line_separators,
+            mixed_line_separators: MixedLineSeparators::default(),
+        }
+    }
+
+    /// Validate a batch of lines on a worker.
+    fn validate_batch(schema: &schema::Schema, read_lines: Vec<std::io::Result<ReadLine>>) -> ParBatch {
+        read_lines
+            .into_iter()
+            .filter(|result_read_line| match result_read_line {
+                Ok(read_line) => !schema.is_ignored_line(&read_line.line_content),
+                Err(_) => true,
+            })
+            .map(|result_read_line| match result_read_line {
+                Ok(read_line) => {
+                    (schema.validate_line(read_line.line_number, read_line.line_content), read_line.line_separator)
                 }
-                Err(e) => {
-                    if e.kind() == std::io::ErrorKind::UnexpectedEof {
-                        self.finished = true;
-                        break;
-                    } else {
-                        return Some(Err(e));
+                Err(err) => (Err(ProcessedLineError::new(0, format!("{:?}", err))), None),
+            })
+            .collect()
+    }
+
+    /// The message of a panic raised with `panic!` or `todo!`
+    fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+        match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+            (Some(panic_message), _) => panic_message,
+            (None, Some(panic_message)) => panic_message,
+            (None, None) => "unknown panic",
+        }
+    }
+
+    /// Read batches of lines and send them to the workers until `max_batches` are pending.
+    fn send_batches(&mut self) {
+        while self.sent_batches - self.next_batch < self.max_batches {
+            let batch_sender = match &self.batch_sender {
+                Some(batch_sender) => batch_sender,
+                None => return,
+            };
+
+            let read_lines: Vec<std::io::Result<ReadLine>> =
+                self.file_buffer.by_ref().take(PAR_ITER_BATCH_SIZE).collect();
+            if read_lines.is_empty() {
+                // End of file, the workers stop once the pending batches are validated
+                self.batch_sender = None;
+                return;
+            }
+
+            if batch_sender.send((self.sent_batches, read_lines)).is_err() {
+                self.batch_sender = None;
+                return;
+            }
+            self.sent_batches += 1;
+        }
+    }
+
+    /// Wait for the batch following the last one yielded.
+    fn receive_next_batch(&mut self) -> Option<ParBatch> {
+        loop {
+            if let Some(batch) = self.pending_batches.remove(&self.next_batch) {
+                self.next_batch += 1;
+                return Some(batch);
+            }
+            match self.result_receiver.recv() {
+                Ok((batch_number, batch)) => {
+                    self.pending_batches.insert(batch_number, batch);
+                }
+                Err(_) => return None, // All the workers stopped
+            }
+        }
+    }
+}
+
+impl Iterator for ParIter<'_> {
+    type Item = Result<ProcessedLineOk, ProcessedLineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((result, line_separator)) = self.current_batch.next() {
+                return match result {
+                    Ok(mut processed_line) => {
+                        if self.warn_mixed_line_separators {
+                            if let Some(warning) = self.mixed_line_separators.check(line_separator) {
+                                processed_line.warnings.push(warning);
+                            }
+                        }
+                        Some(Ok(processed_line))
                     }
+                    Err(processed_line) => Some(Err(processed_line)),
+                };
+            }
+
+            self.send_batches();
+            if self.next_batch == self.sent_batches {
+                return None;
+            }
+
+            match self.receive_next_batch() {
+                Some(batch) => self.current_batch = batch.into_iter(),
+                None => {
+                    self.batch_sender = None;
+                    self.sent_batches = self.next_batch;
+                    return Some(Err(ProcessedLineError::new(
+                        0,
+                        "worker thread stopped before validating all the lines",
+                    )));
                 }
             }
         }
+    }
+}
 
-        let line = match String::from_utf8(self.buf.clone()) {
-            Ok(line) => line,
-            Err(e) => return Some(Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e))),
-        };
-        self.current_line += 1;
-        self.buf.clear();
-        Some(Ok(ReadLine { line_number: self.current_line, line_content: line }))
+impl Drop for ParIter<'_> {
+    fn drop(&mut self) {
+        // Closing the channel stops the workers
+        self.batch_sender = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
     }
 }
 
@@ -150,32 +789,37 @@ impl Parser {
     ///
     /// A `Result` containing the `Parser` instance if successful, or an `Error` if an error occurred.
     pub fn new(config: ParserConfig) -> Result<Self, Error> {
-        // Open the file specified in the configuration
-        let file = File::open(&config.file_path).context("Failed to open file");
-        let file = match file {
-            Ok(file) => file,
-            Err(err) => {
-                return Err(err);
-            }
-        };
-
-        // Create a buffered reader for efficient reading of the file
-        let reader = BufReader::new(file);
+        // Open the file specified in the configuration, decompressing it if it is compressed,
+        // with a buffered reader for efficient reading of the file
+        let reader = compression::open_input(&config.file_path)?;
 
         // Create a new schema instance based on the file schema specified in the configuration
-        let schema = schema::Schema::new(&config.file_schema);
-        let schema = match schema {
-            Ok(schema) => schema,
-            Err(err) => {
-                return Err(err);
-            }
-        };
+        let schema = schema::Schema::from_config(&config)?;
 
-        // Get the newline characters defined in the schema
-        let schema_line_newline_characters = schema.get_newline_characters();
+        // Split by the newline characters defined in the schema, or by record length if there are none
+        let line_split = LineSplit::from_schema(&schema)?;
 
         // Create a file buffer to handle reading and processing of lines
-        let file_buffer = FileBuffer::new(reader, schema_line_newline_characters.to_owned());
+        let file_buffer = FileBuffer::new(reader, line_split);
+
+        Ok(Self { config, schema, file_buffer })
+    }
+
+    /// Creates a new `Parser` instance reading the lines from `reader`, e.g. the standard input.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration for the parser. The `file_path` is not used, the lines are read from `reader`.
+    /// * `reader` - The input, decompressed if it starts with the magic bytes of a compression format.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Parser` instance if successful, or an `Error` if an error occurred.
+    pub fn with_reader(config: ParserConfig, reader: impl BufRead + Send + 'static) -> Result<Self, Error> {
+        let reader = compression::read_input(reader, "")?;
+        let schema = schema::Schema::from_config(&config)?;
+        let line_split = LineSplit::from_schema(&schema)?;
+        let file_buffer = FileBuffer::new(reader, line_split);
 
         Ok(Self { config, schema, file_buffer })
     }
@@ -184,6 +828,8 @@ impl Parser {
     ///
     /// This method does not process the lines according to the schema.
     /// The schema attribute "lineseparator" is used for the line break.
+    /// If "lineseparator" is empty or "recordlength" is set, the file is split by record length.
+    /// If "lineseparator" is "auto", lines end with "\n", "\r\n" or "\r".
     /// Use the `iter_mut` method to process each line according to the schema.
     ///
     /// # Returns
@@ -199,26 +845,72 @@ impl Parser {
     ///
     /// An iterator that yields each processed line as a `Result` containing either a `ProcessedLineOk` or a `ProcessedLineError`.
     pub fn iter_mut(&mut self) -> impl Iterator<Item = Result<ProcessedLineOk, ProcessedLineError>> + '_ {
-        // Clone the schema to ensure each iteration uses a separate instance
-        let schema = self.schema.clone();
+        validate_lines(&mut self.file_buffer, &self.schema, self.config.warn_mixed_line_separators)
+    }
 
-        // Map each line of the file to a processed line based on the schema validation
-        self.lines().map(move |result_read_line| {
-            let read_line = match result_read_line {
-                Ok(read_line) => read_line,
-                Err(err) => {
-                    return Err(ProcessedLineError { line_number: 0, message: format!("{:?}", err) });
-                }
-            };
+    /// Returns an iterator that processes each line of the file according to the schema
+    /// and collects the lines into the groups declared in the schema (`<group>`).
+    ///
+    /// Lines whose line type is not referenced by any group are yielded as they are.
+    /// See `Grouper` for the group errors.
+    ///
+    /// # Returns
+    ///
+    /// An iterator that yields each line or complete group as a `Result` containing either a `ProcessedRecord` or a `ProcessedLineError`.
+    pub fn groups(&mut self) -> impl Iterator<Item = Result<ProcessedRecord, ProcessedLineError>> + '_ {
+        let mut lines = validate_lines(&mut self.file_buffer, &self.schema, self.config.warn_mixed_line_separators);
+        let mut grouper = Grouper::new(&self.schema);
+        let mut finished = false;
 
-            let result: Result<ProcessedLineOk, ProcessedLineError> =
-                schema.validate_line(read_line.line_number, read_line.line_content.to_owned());
-            match result {
-                Ok(processed_line) => Ok(processed_line),
-                Err(processed_line) => Err(processed_line),
+        std::iter::from_fn(move || loop {
+            if let Some(record) = grouper.next_record() {
+                return Some(record);
+            }
+            if finished {
+                return None;
+            }
+            match lines.next() {
+                Some(line_result) => grouper.push(line_result),
+                None => {
+                    grouper.finish();
+                    finished = true;
+                }
             }
         })
     }
+
+    /// Returns an iterator that processes the lines of the file in parallel according to the schema.
+    ///
+    /// The lines are read in batches and validated by a pool of worker threads sharing the schema.
+    /// The results are yielded in the original line order, and only a few batches per worker
+    /// are read ahead, so the memory used does not depend on the size of the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `n_workers` - The number of worker threads, or 0 to use the available parallelism.
+    ///
+    /// # Returns
+    ///
+    /// An iterator that yields each processed line as a `Result` containing either a `ProcessedLineOk` or a `ProcessedLineError`.
+    pub fn par_iter(
+        &mut self, n_workers: usize,
+    ) -> impl Iterator<Item = Result<ProcessedLineOk, ProcessedLineError>> + '_ {
+        let par_iter =
+            ParIter::new(&mut self.file_buffer, &self.schema, self.config.warn_mixed_line_separators, n_workers);
+
+        // The control totals of the file are checked after the last line
+        WithControlTotals::new(par_iter, &self.schema)
+    }
+
+    /// Returns the line separator found in the file when the schema uses lineseparator="auto".
+    ///
+    /// # Returns
+    ///
+    /// The first separator found, escaped as in the schema ("\\n", "\\r\\n" or "\\r"),
+    /// or `None` if no line has been read yet, the file has a single line or the separator is not "auto".
+    pub fn detected_line_separator(&self) -> Option<&'static str> {
+        self.file_buffer.scanner.detected_line_separator
+    }
 }
 
 #[cfg(test)]
@@ -226,6 +918,7 @@ impl Parser {
 mod tests {
     use crossbeam::channel::{unbounded, Receiver, Sender};
     use rayon::iter::{ParallelBridge, ParallelIterator};
+    use std::io::BufReader;
     use std::thread;
 
     use super::*;
@@ -235,8 +928,9 @@ mod tests {
     fn test_parser() {
         // Create a ParserConfig with file paths for data and schema files.
         let config = ParserConfig {
-            file_path: "./example/fixedwidth_data.txt".to_string(),
+            file_path: crate::schema_generate::sample_data().to_string(),
             file_schema: "./example/fixedwidth_schema.xml".to_string(),
+            ..Default::default()
         };
 
         // Create a new Parser instance with the given config.
@@ -251,13 +945,203 @@ mod tests {
         }
     }
 
+    /// Test function for the codes of the error and warning messages, all described in `ERROR_KINDS` and `WARNING_KINDS`.
+    #[test]
+    fn test_error_kinds() {
+        let sources = [
+            include_str!("schema.rs"),
+            include_str!("group.rs"),
+            include_str!("control_total.rs"),
+            include_str!("parser.rs"),
+        ];
+        for source in sources {
+            for (index, _) in source.match_indices("[err:").chain(source.match_indices("[wrn:")) {
+                let Some(code) = message_code(&source[index..]) else {
+                    continue;
+                };
+                let kinds: &[(&str, &str)] =
+                    if source[index..].starts_with("[err:") { &ERROR_KINDS } else { &WARNING_KINDS };
+                assert!(kinds.iter().any(|(kind_code, _)| *kind_code == code), "{}", &source[index..index + 9]);
+            }
+        }
+
+        // The errors of invalid lines have their line type
+        let schema = schema::Schema::new("./example/fixedwidth_schema_groups.xml").unwrap();
+        let error = schema.validate_line(1, "DU01000001.0X".to_string()).unwrap_err();
+        assert_eq!((error.code(), error.linetype.as_deref()), (Some("007"), Some("Detail")));
+        let error = schema.validate_line(1, "X".to_string()).unwrap_err();
+        assert_eq!((error.code(), error.linetype.as_deref()), (Some("001"), None));
+        assert_eq!(ProcessedLineError::new(0, "stream did not contain valid UTF-8").code(), None);
+    }
+
+    /// Test function for a parser reading from a reader instead of a file.
+    #[test]
+    fn test_parser_with_reader() {
+        let config = ParserConfig { file_schema: "./example/fixedwidth_schema.xml".to_string(), ..Default::default() };
+        let data = std::fs::read(crate::schema_generate::sample_data()).unwrap();
+        let mut parser = Parser::with_reader(config, BufReader::new(std::io::Cursor::new(data))).unwrap();
+        assert_eq!(parser.iter_mut().filter(|line_result| line_result.is_ok()).count(), 10002);
+    }
+
+    /// Test function for a file without line separators, split by the length of each line type.
+    #[test]
+    fn test_parser_record_length() {
+        let schema = schema::Schema::new("./example/fixedwidth_schema_noseparator.xml").unwrap();
+        assert!(schema.is_record_length_delimited());
+
+        let data =
+            "H20240524TTTTTTTTTTT000000000002.00jhl01@6u24.com000100000002.00475pw@cj14.comF11WWW110000000000.00";
+        for capacity in [1, 7, 8192] {
+            let reader = BufReader::with_capacity(capacity, std::io::Cursor::new(data.as_bytes()));
+            let file_buffer = FileBuffer::new(reader, LineSplit::from_schema(&schema).unwrap());
+
+            let linetypes: Vec<String> = file_buffer
+                .map(|read_line| {
+                    let read_line = read_line.unwrap();
+                    schema.validate_line(read_line.line_number, read_line.line_content).unwrap().linetype
+                })
+                .collect();
+            assert_eq!(linetypes, vec!["Header", "Body", "Body", "Footer"]);
+        }
+    }
+
+    /// Test function for line separators split across the chunks read from the file.
+    #[test]
+    fn test_file_buffer_chunk_boundaries() {
+        let data = "H1\r\nBODY\r2\r\r\n\r\nF3";
+        for capacity in 1..8 {
+            let reader = BufReader::with_capacity(capacity, std::io::Cursor::new(data.as_bytes()));
+            let separator = memmem::Finder::new(b"\r\n").into_owned();
+            let lines: Vec<String> = FileBuffer::new(reader, LineSplit::Separator(separator))
+                .map(|read_line| read_line.unwrap().line_content)
+                .collect();
+            assert_eq!(lines, vec!["H1", "BODY\r2\r", "", "F3"]);
+
+            let reader = BufReader::with_capacity(capacity, std::io::Cursor::new(data.as_bytes()));
+            let lines: Vec<(String, Option<&str>)> = FileBuffer::new(reader, LineSplit::Auto)
+                .map(|read_line| read_line.unwrap())
+                .map(|read_line| (read_line.line_content, read_line.line_separator))
+                .collect();
+            assert_eq!(
+                lines,
+                vec![
+                    ("H1".to_string(), Some("\\r\\n")),
+                    ("BODY".to_string(), Some("\\r")),
+                    ("2".to_string(), Some("\\r")),
+                    ("".to_string(), Some("\\r\\n")),
+                    ("".to_string(), Some("\\r\\n")),
+                    ("F3".to_string(), None),
+                ]
+            );
+        }
+    }
+
+    /// Test function for a projection of the cells emitted in the cell values.
+    #[test]
+    fn test_parser_projection() {
+        let config = ParserConfig {
+            file_path: "./example/fixedwidth_data_groups.txt".to_string(),
+            file_schema: "./example/fixedwidth_schema_groups.xml".to_string(),
+            projection: vec!["BatchID".to_string(), "UserID".to_string()],
+            ..Default::default()
+        };
+        let mut parser = Parser::new(config).unwrap();
+        let processed_lines: Vec<ProcessedLineOk> = parser.iter_mut().map(Result::unwrap).collect();
+
+        let cell_names = |line_number: usize| -> Vec<&str> {
+            processed_lines[line_number - 1].cell_values.keys().map(String::as_str).collect()
+        };
+        assert_eq!(cell_names(1), Vec::<&str>::new());
+        assert_eq!(cell_names(2), ["BatchID"]);
+        // The cells of the control totals are kept
+        assert_eq!(cell_names(3), ["UserID", "Amount"]);
+        assert_eq!(cell_names(6), ["Count", "TotalAmount"]);
+
+        let config = ParserConfig {
+            file_path: "./example/fixedwidth_data_groups.txt".to_string(),
+            file_schema: "./example/fixedwidth_schema_groups.xml".to_string(),
+            projection: vec!["Unknown".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(Parser::new(config).unwrap_err().to_string(), "Unknown cell in projection: Unknown");
+    }
+
+    /// Test function for a file with "\r\n" and "\n" line separators and lineseparator="auto".
+    #[test]
+    fn test_parser_auto_line_separator() {
+        let config = ParserConfig {
+            file_path: "./example/fixedwidth_data_mixed_separators.txt".to_string(),
+            file_schema: "./example/fixedwidth_schema_auto.xml".to_string(),
+            warn_mixed_line_separators: true,
+            ..Default::default()
+        };
+
+        let mut parser = Parser::new(config).unwrap();
+
+        let processed_lines: Vec<ProcessedLineOk> = parser.iter_mut().map(|line_result| line_result.unwrap()).collect();
+        assert_eq!(processed_lines.len(), 4);
+        assert!(processed_lines[1].warnings.is_empty());
+        assert_eq!(
+            processed_lines[2].warnings,
+            vec!["[wrn:001]|line|lineseparator|the line ends with \\n but was expected \\r\\n"]
+        );
+        assert_eq!(parser.detected_line_separator(), Some("\\r\\n"));
+    }
+
+    /// Test function for the parser module using `par_iter`, the lines are yielded in the original order.
+    #[test]
+    fn test_parser_par_iter() {
+        let config = || ParserConfig {
+            file_path: crate::schema_generate::sample_data().to_string(),
+            file_schema: "./example/fixedwidth_schema.xml".to_string(),
+            ..Default::default()
+        };
+
+        let mut parser = Parser::new(config()).unwrap();
+        let expected: Vec<(usize, String)> =
+            parser.iter_mut().map(|line_result| line_result.unwrap()).map(|l| (l.line_number, l.linetype)).collect();
+
+        for n_workers in [1, 4] {
+            let mut parser = Parser::new(config()).unwrap();
+            let processed_lines: Vec<(usize, String)> = parser
+                .par_iter(n_workers)
+                .map(|line_result| line_result.unwrap())
+                .map(|l| (l.line_number, l.linetype))
+                .collect();
+            assert_eq!(processed_lines, expected);
+        }
+
+        // Stop before the end of the file
+        let mut parser = Parser::new(config()).unwrap();
+        assert_eq!(parser.par_iter(4).take(3).count(), 3);
+
+        // A panic of a worker is the error of its batch, the other batches are still yielded
+        let mut parser = Parser::new(config()).unwrap();
+        let mut schema = parser.schema.clone();
+        schema.fixedwidthschema = None; // Panics in `Schema::validate_line`
+        let errors: Vec<ProcessedLineError> = ParIter::new(&mut parser.file_buffer, &schema, false, 4)
+            .map(|line_result| line_result.unwrap_err())
+            .collect();
+        let batches = (expected.len() + PAR_ITER_BATCH_SIZE - 1) / PAR_ITER_BATCH_SIZE;
+        assert_eq!(errors.len(), batches);
+        for (batch_number, error) in errors.iter().enumerate() {
+            assert_eq!(error.line_number, batch_number * PAR_ITER_BATCH_SIZE + 1);
+            assert!(
+                error.message.starts_with("worker thread panicked while validating the lines: "),
+                "{}",
+                error.message
+            );
+        }
+    }
+
     /// Test function for the parser module using multiple threads.
     #[test]
     fn test_parser_thread() {
         // Create a ParserConfig with file paths for data and schema files.
         let config = ParserConfig {
-            file_path: "./example/fixedwidth_data.txt".to_string(),
+            file_path: crate::schema_generate::sample_data().to_string(),
             file_schema: "./example/fixedwidth_schema.xml".to_string(),
+            ..Default::default()
         };
 
         let n_workers = 4;
@@ -283,7 +1167,7 @@ mod tests {
                     match schema.validate_line(line_number, line_content) {
                         Ok(_) => {}
                         Err(v) => {
-                            return_errors.push(ProcessedLineError { line_number: v.line_number, message: v.message });
+                            return_errors.push(v);
                         }
                     }
                 }
@@ -332,8 +1216,9 @@ mod tests {
     fn test_parser_iter_par() {
         // Create a ParserConfig with file paths for data and schema files.
         let config = ParserConfig {
-            file_path: "./example/fixedwidth_data.txt".to_string(),
+            file_path: crate::schema_generate::sample_data().to_string(),
             file_schema: "./example/fixedwidth_schema.xml".to_string(),
+            ..Default::default()
         };
 
         // Create a new Parser instance with the given config.
@@ -360,7 +1245,7 @@ mod tests {
                             Err(processed_line) => Err(processed_line),
                         }
                     }
-                    Err(e) => Err(ProcessedLineError { line_number: 0, message: format!("{}", e) }),
+                    Err(e) => Err(ProcessedLineError::new(0, format!("{}", e))),
                 }
             })
             .for_each(|result_processed_line| match result_processed_line {
@@ -1,17 +1,44 @@
 use anyhow::{anyhow, Context, Error, Result};
-use chrono::NaiveDate;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
 use indexmap::map::IndexMap;
-use std::{collections::HashSet, fs::File, io::BufReader};
-use xml::reader::{EventReader, XmlEvent};
+use std::collections::HashSet;
+use xml::attribute::OwnedAttribute;
+use xml::common::TextPosition;
+use xml::reader::XmlEvent;
 
-use crate::{decimal_format, ProcessedLineError, ProcessedLineOk};
+use crate::decimal_format::Decimal;
+use crate::{
+    date_format, decimal_format, jsapar, schema_include, ParserConfig, ProcessedLine, ProcessedLineError,
+    ProcessedLineOk,
+};
+
+/// Format types with "minvalue" and "maxvalue"
+const RANGE_FORMAT_TYPES: [&str; 7] = ["number", "integer", "float", "implieddecimal", "date", "time", "datetime"];
+
+/// Format types of `<format type="...">`
+pub const FORMAT_TYPES: [&str; 10] =
+    ["string", "number", "date", "boolean", "integer", "float", "implieddecimal", "time", "datetime", "enum"];
 
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Format {
     pub ctype: String,
     pub pattern: String,
     pub regex_pattern: Option<regex::Regex>,
+    pub true_values: Vec<String>,      // Literals of "boolean", e.g. pattern="Y|yes;N|no"
+    pub false_values: Vec<String>,     //
+    pub values: Vec<String>,           // Values of "enum", e.g. pattern="A|B|C"
+    pub timezone: Option<FixedOffset>, // Offset of "datetime" values without offset in the pattern
+    pub locale: Option<Locale>,        // Separators of "number" and "float" values, from the <locale> of the schema
+}
+
+/// Locale of the numbers, from `<locale language="de" country="DE"/>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    pub language: String,
+    pub country: String,
+    pub decimal_separator: char,
+    pub grouping_separator: char,
 }
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -27,6 +54,7 @@ pub struct Line {
     pub cell: Vec<Cell>,
     pub minlength: usize,
     pub padcharacter: String,
+    pub allowoverlaps: bool, // Cells with a "start" may overlap the previous cells, e.g. COBOL REDEFINES
 }
 
 #[allow(dead_code)]
@@ -39,180 +67,959 @@ pub struct Cell {
     pub format: Option<Format>,
     pub linecondition_type: Option<String>,
     pub linecondition_pattern: Option<String>,
+    pub linecondition_regex: Option<regex::Regex>, // Matched by the trimmed value unless the type is "string"
     pub alignment: String,
     pub padcharacter: String,
+    pub mandatory: bool,              // The trimmed value must not be empty
+    pub minvalue: Option<String>,     // Minimum number or date, in the format of the cell
+    pub maxvalue: Option<String>,     // Maximum number or date, in the format of the cell
+    pub values: Vec<String>,          // Allowed values ("values" attribute separated by "|")
+    pub minlength: usize,             // Minimum length of the trimmed value (0 = not set)
+    pub maxlength: usize,             // Maximum length of the trimmed value (0 = not set)
+    pub emptypattern: Option<String>, // Regex of the trimmed values considered empty, besides ""
+    pub emptypattern_regex: Option<regex::Regex>,
+    pub default: Option<String>, // Value yielded for empty cells
+    pub ignoreread: bool,        // Validated but not emitted in the cell values (fillers, "ignoreread" and projection)
+}
+
+/// Typed value of a cell, see `Cell::parse_value`
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellValue {
+    String(String),
+    Number(f64),
+    Date(NaiveDate),
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    Time(NaiveTime),
+    DateTime(NaiveDateTime),
+    ZonedDateTime(DateTime<FixedOffset>), // "datetime" with an offset in the pattern or a "timezone"
+    Enum(String),
+}
+
+impl CellValue {
+    /// Compare two values of the same format
+    pub fn compare(&self, other: &CellValue) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (CellValue::Number(a), CellValue::Number(b)) | (CellValue::Float(a), CellValue::Float(b)) => {
+                a.partial_cmp(b)
+            }
+            (CellValue::Integer(a), CellValue::Integer(b)) => Some(a.cmp(b)),
+            (CellValue::Date(a), CellValue::Date(b)) => Some(a.cmp(b)),
+            (CellValue::Time(a), CellValue::Time(b)) => Some(a.cmp(b)),
+            (CellValue::DateTime(a), CellValue::DateTime(b)) => Some(a.cmp(b)),
+            (CellValue::ZonedDateTime(a), CellValue::ZonedDateTime(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+impl Format {
+    /// Parse a trimmed value according to the format
+    /// Returns None if the value does not match the format
+    pub fn parse_typed(&self, value: &str) -> Option<CellValue> {
+        match self.ctype.as_str() {
+            "string" => Some(CellValue::String(value.to_owned())),
+            "number" => self.normalize_number(value).replace(',', "").parse().ok().map(CellValue::Number),
+            "date" => NaiveDate::parse_from_str(value, &self.pattern).ok().map(CellValue::Date),
+            "boolean" if self.true_values.iter().any(|true_value| true_value == value) => {
+                Some(CellValue::Boolean(true))
+            }
+            "boolean" if self.false_values.iter().any(|false_value| false_value == value) => {
+                Some(CellValue::Boolean(false))
+            }
+            "integer" => value.parse().ok().map(CellValue::Integer),
+            "float" => {
+                self.normalize_number(value).parse::<f64>().ok().filter(|float| float.is_finite()).map(CellValue::Float)
+            }
+            "implieddecimal" => self
+                .parse_implied_decimal(value)
+                .and_then(|number| number.to_string().parse().ok())
+                .map(CellValue::Number),
+            "time" => NaiveTime::parse_from_str(value, &self.pattern).ok().map(CellValue::Time),
+            "datetime" if Self::has_offset(&self.pattern) => {
+                DateTime::parse_from_str(value, &self.pattern).ok().map(CellValue::ZonedDateTime)
+            }
+            "datetime" => {
+                let datetime = NaiveDateTime::parse_from_str(value, &self.pattern).ok()?;
+                match &self.timezone {
+                    Some(timezone) => timezone.from_local_datetime(&datetime).single().map(CellValue::ZonedDateTime),
+                    None => Some(CellValue::DateTime(datetime)),
+                }
+            }
+            "enum" if self.values.iter().any(|enum_value| enum_value == value) => {
+                Some(CellValue::Enum(value.to_owned()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Parse the digits of an "implieddecimal" value with the number of decimals of the pattern,
+    /// e.g. "12345" is 123.45 with pattern="2". The sign may lead or trail the digits ("-12345" or "12345-"),
+    /// or be overpunched in the last digit as in COBOL signed fields ("1234N" is -123.45).
+    fn parse_implied_decimal(&self, value: &str) -> Option<Decimal> {
+        let decimals: u32 = self.pattern.parse().ok()?;
+        let (negative, digits, last_digit) = match value.chars().last()? {
+            '{' => (false, &value[..value.len() - 1], Some('0')),
+            '}' => (true, &value[..value.len() - 1], Some('0')),
+            last @ 'A'..='I' => (false, &value[..value.len() - 1], char::from_digit(last as u32 - 'A' as u32 + 1, 10)),
+            last @ 'J'..='R' => (true, &value[..value.len() - 1], char::from_digit(last as u32 - 'J' as u32 + 1, 10)),
+            '-' => (true, &value[..value.len() - 1], None),
+            '+' => (false, &value[..value.len() - 1], None),
+            _ => match value.strip_prefix('-') {
+                Some(digits) => (true, digits, None),
+                None => (false, value.strip_prefix('+').unwrap_or(value), None),
+            },
+        };
+        let digits = format!("{}{}", digits, last_digit.map(String::from).unwrap_or_default());
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let mantissa: i128 = digits.parse().ok()?;
+        Some(Decimal::new(if negative { -mantissa } else { mantissa }, decimals))
+    }
+
+    /// Parse a trimmed value as an exact decimal, normalized as `parse_typed` does for the "number", "float"
+    /// and "implieddecimal" formats, e.g. "1,234.50" or "0012345" with pattern="2".
+    /// The values of the other formats are read as plain decimals like "-123.45".
+    pub(crate) fn parse_decimal(&self, value: &str) -> Option<Decimal> {
+        match self.ctype.as_str() {
+            "number" => Decimal::parse(&self.normalize_number(value).replace(',', "")),
+            "float" => Decimal::parse(&self.normalize_number(value)),
+            "implieddecimal" => self.parse_implied_decimal(value),
+            _ => Decimal::parse(value),
+        }
+    }
+
+    /// Replace the separators of the locale by "." and "," in a number
+    pub fn normalize_number<'a>(&self, value: &'a str) -> std::borrow::Cow<'a, str> {
+        match &self.locale {
+            Some(locale) if locale.decimal_separator != '.' || locale.grouping_separator != ',' => value
+                .chars()
+                .map(|c| match c {
+                    c if c == locale.decimal_separator => '.',
+                    // Spaces are accepted for the no-break space of French, Swedish, ...
+                    c if c == locale.grouping_separator || (locale.grouping_separator == '\u{a0}' && c == ' ') => ',',
+                    c => c,
+                })
+                .collect(),
+            _ => value.into(),
+        }
+    }
+
+    /// Check if a chrono pattern has an offset ("%z", "%:z", ...)
+    pub(crate) fn has_offset(pattern: &str) -> bool {
+        ["%z", "%:z", "%::z", "%:::z", "%#z"].iter().any(|offset| pattern.contains(offset))
+    }
+
+    /// Parse the "timezone" attribute: "UTC", "Z" or an offset like "+02:00"
+    fn parse_timezone(timezone: &str) -> Result<FixedOffset, Error> {
+        match timezone {
+            "UTC" | "Z" => Ok(FixedOffset::east_opt(0).unwrap()),
+            offset => offset.parse().map_err(|_| anyhow!("Invalid timezone: {}", timezone)),
+        }
+    }
+}
+
+impl Locale {
+    /// Locale of a language and country, with the separators of the numbers of Java
+    pub fn new(language: &str, country: &str) -> Result<Self, Error> {
+        let (decimal_separator, grouping_separator) = match (language, country) {
+            ("de" | "fr" | "it", "CH") => ('.', '\''),
+            ("en" | "ja" | "zh" | "ko" | "he" | "th" | "ga" | "ms" | "hi", _) => ('.', ','),
+            ("de" | "es" | "it" | "pt" | "nl" | "da" | "id" | "tr" | "el" | "ro" | "hr" | "sl" | "sr", _) => (',', '.'),
+            (
+                "fr" | "sv" | "fi" | "nb" | "no" | "nn" | "ru" | "pl" | "cs" | "sk" | "uk" | "hu" | "bg" | "et" | "lv"
+                | "lt",
+                _,
+            ) => (',', '\u{a0}'),
+            _ => return Err(anyhow!("Unsupported locale: {}_{}", language, country)),
+        };
+        Ok(Self { language: language.to_string(), country: country.to_string(), decimal_separator, grouping_separator })
+    }
+}
+
+/// Number of occurrences allowed, from the "occurs" attribute
+/// Example: "1", "*" (any), "0..1", "1..*", "2..5"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Occurs {
+    pub min: usize,
+    pub max: Option<usize>, // None = unbounded
+}
+
+/// Reference to a line type inside a group
+#[derive(Debug, Clone)]
+pub struct LineRef {
+    pub linetype: String,
+    pub occurs: Occurs,
+}
+
+#[derive(Debug, Clone)]
+pub enum GroupItem {
+    Line(LineRef),
+    Group(Group),
+}
+
+/// A group of lines, e.g. a batch with a header, N detail lines and a trailer
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub name: String,
+    pub occurs: Occurs,
+    pub items: Vec<GroupItem>, // Line references and nested groups, in the expected order
+    pub controltotals: Vec<ControlTotal>, // Checked when the group ends
+}
+
+/// Aggregate of the lines of a group or file compared by a control total
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum { linetype: String, cell: String },
+    Count { linetype: String },
+}
+
+/// Cross-line rule checking a cell of a trailer line against the other lines,
+/// e.g. "Footer.TotalAmount == sum(Body.Amount)" or "Footer.Count == count(Body)"
+#[derive(Debug, Clone)]
+pub struct ControlTotal {
+    pub rule: String,
+    pub linetype: String, // Line with the total, e.g. "Footer"
+    pub cell: String,     // Cell with the total, e.g. "TotalAmount"
+    pub aggregate: Aggregate,
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct FixedWidthSchema {
     pub lineseparator: String,
+    pub recordlength: usize, // Fixed record length for files without line separators (0 = not set)
+    pub ignoreemptylines: bool, // Empty lines are skipped, their line numbers are kept for the next lines
+    pub locale: Option<Locale>,
     pub lines: Vec<Line>,
+    pub groups: Vec<Group>,
+    pub controltotals: Vec<ControlTotal>, // Checked at the end of the file
 }
 
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
 pub struct CsvSchema {
-    pub lines: Vec<Line>, // TODO: implement CSV schema
+    pub lineseparator: String,
+    pub cellseparator: String,
+    pub firstlineasschema: bool, // The first line has the names of the cells
+    pub lines: Vec<Line>,        // TODO: implement CSV schema
 }
 
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
 pub struct Schema {
     pub fixedwidthschema: Option<FixedWidthSchema>,
-    pub csvschema: Option<CsvSchema>, // TODO: implement CSV schema
+    pub csvschema: Option<CsvSchema>,       // TODO: implement CSV schema
+    pub(crate) lint_issues: Vec<LintIssue>, // Found when loading the schema, see `Schema::lint`
+}
+
+/// A mistake in the schema that does not prevent loading it, see `Schema::lint`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    pub row: u64,    // Line of the element in the XML file, 1-based (0 for JSON and YAML schemas)
+    pub column: u64, // 1-based
+    pub message: String,
+}
+
+impl LintIssue {
+    fn new(position: Option<TextPosition>, message: String) -> Self {
+        match position {
+            Some(position) => Self { row: position.row + 1, column: position.column + 1, message },
+            None => Self { row: 0, column: 0, message },
+        }
+    }
+
+    /// Only one line without conditions can be matched (see `get_first_line_without_condition`)
+    pub(crate) fn lines_without_condition(lines_without_condition: &[(String, Option<TextPosition>)]) -> Option<Self> {
+        let [_, (linetype, position), ..] = lines_without_condition else {
+            return None;
+        };
+        let linetypes: Vec<&str> = lines_without_condition.iter().map(|(linetype, _)| linetype.as_str()).collect();
+        let message = format!(
+            "Line {} is never matched, there is more than one line without conditions: {}",
+            linetype,
+            linetypes.join(", ")
+        );
+        Some(Self::new(*position, message))
+    }
+}
+
+impl std::fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.row {
+            0 => write!(f, "{}", self.message),
+            row => write!(f, "line {}, column {}: {}", row, self.column, self.message),
+        }
+    }
+}
+
+/// Prefix an error with the position of the element in the XML file, if any
+pub(crate) fn error_at(position: Option<TextPosition>, error: Error) -> Error {
+    match position {
+        Some(position) => anyhow!("line {}, column {}: {:#}", position.row + 1, position.column + 1, error),
+        None => error,
+    }
+}
+
+/// Parse a length attribute, or 0 with a lint issue if it is not a number
+fn parse_length(attr: &OwnedAttribute, position: Option<TextPosition>, lint_issues: &mut Vec<LintIssue>) -> usize {
+    attr.value.parse().unwrap_or_else(|_| {
+        let message = format!("Invalid {}=\"{}\", expected a number", attr.name.local_name, attr.value);
+        lint_issues.push(LintIssue::new(position, message));
+        0
+    })
+}
+
+impl Occurs {
+    /// Parse the "occurs" attribute
+    pub fn parse(occurs: &str) -> Result<Self, Error> {
+        let parse_bound = |bound: &str| match bound.trim() {
+            "*" => Ok(None),
+            bound => bound.parse().map(Some).with_context(|| format!("Invalid occurs: {}", occurs)),
+        };
+
+        let (min, max) = match occurs.split_once("..") {
+            Some((min, max)) => (parse_bound(min)?.unwrap_or(0), parse_bound(max)?),
+            None => match parse_bound(occurs)? {
+                Some(count) => (count, Some(count)),
+                None => (0, None),
+            },
+        };
+        if max.is_some_and(|max| max == 0 || max < min) {
+            return Err(anyhow!("Invalid occurs: {}", occurs));
+        }
+
+        Ok(Self { min, max })
+    }
+}
+
+/// Format as the "occurs" attribute
+impl std::fmt::Display for Occurs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.min, self.max) {
+            (min, Some(max)) if min == max => write!(f, "{}", min),
+            (0, None) => write!(f, "*"),
+            (min, Some(max)) => write!(f, "{}..{}", min, max),
+            (min, None) => write!(f, "{}..*", min),
+        }
+    }
+}
+
+impl Cell {
+    /// Get the alignment of the value in the cell
+    /// The default alignment is "right" for numbers, integers, floats and implied decimals and "left" otherwise
+    pub fn get_alignment(&self) -> &str {
+        match self.alignment.as_str() {
+            "" if self.format.as_ref().is_some_and(|format| {
+                matches!(format.ctype.as_str(), "number" | "integer" | "float" | "implieddecimal")
+            }) =>
+            {
+                "right"
+            }
+            "" => "left",
+            alignment => alignment,
+        }
+    }
+
+    /// Check if a trimmed value is empty: "" or matching the "emptypattern" of the cell
+    pub fn is_empty_value(&self, value: &str) -> bool {
+        value.is_empty() || self.emptypattern_regex.as_ref().is_some_and(|re| re.is_match(value))
+    }
+
+    /// Parse a value yielded for this cell according to its format
+    /// Returns:
+    /// - Ok(None) if the value is empty and the cell has no default value
+    /// - Ok(Some(value)) with the value, or the default value if the value is empty
+    /// - Err(message) if the value does not match the format
+    pub fn parse_value(&self, value: &str) -> Result<Option<CellValue>, String> {
+        let value = match (self.is_empty_value(value), &self.default) {
+            (false, _) => value,
+            (true, Some(default)) => default,
+            (true, None) => return Ok(None),
+        };
+        Schema::validate_format(self, value)?;
+
+        let cell_value = match &self.format {
+            Some(format) => format.parse_typed(value),
+            None => Some(CellValue::String(value.to_owned())),
+        };
+        match cell_value {
+            Some(cell_value) => Ok(Some(cell_value)),
+            None => Err(format!("[err:020]|{}|parse|the value {} cannot be parsed", self.name, value)),
+        }
+    }
+}
+
+impl ControlTotal {
+    /// Parse a rule "Line.Cell == sum(Line.Cell)" or "Line.Cell == count(Line)"
+    pub fn parse(rule: &str) -> Result<Self, Error> {
+        let invalid_rule = || anyhow!("Invalid control total rule: {}", rule);
+
+        let (total, aggregate) = rule.split_once("==").ok_or_else(invalid_rule)?;
+        let (linetype, cell) = total.trim().split_once('.').ok_or_else(invalid_rule)?;
+        let (function, argument) = aggregate
+            .trim()
+            .strip_suffix(')')
+            .and_then(|aggregate| aggregate.split_once('('))
+            .ok_or_else(invalid_rule)?;
+
+        let aggregate = match (function.trim(), argument.trim().split_once('.')) {
+            ("sum", Some((linetype, cell))) => {
+                Aggregate::Sum { linetype: linetype.to_string(), cell: cell.to_string() }
+            }
+            ("count", None) => Aggregate::Count { linetype: argument.trim().to_string() },
+            _ => return Err(invalid_rule()),
+        };
+
+        Ok(Self { rule: rule.trim().to_string(), linetype: linetype.to_string(), cell: cell.to_string(), aggregate })
+    }
+}
+
+impl GroupItem {
+    pub fn occurs(&self) -> Occurs {
+        match self {
+            GroupItem::Line(line_ref) => line_ref.occurs,
+            GroupItem::Group(group) => group.occurs,
+        }
+    }
+
+    /// Line type or group name
+    pub fn name(&self) -> &str {
+        match self {
+            GroupItem::Line(line_ref) => &line_ref.linetype,
+            GroupItem::Group(group) => &group.name,
+        }
+    }
 }
 
 impl Schema {
-    /// Load schema from XML file
+    /// Load schema from XML file, with its `<include>` and `<cellgroupref>` elements expanded (see `schema_include`),
+    /// or from a JSON or YAML file by its extension (features "json" and "yaml")
     pub fn new(path: &str) -> Result<Self, Error> {
-        let file = File::open(path)?;
-        let file = BufReader::new(file);
-        let parser = EventReader::new(file);
+        match std::path::Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some("json") => return Self::from_document_file(path, "json"),
+            Some("yaml" | "yml") => return Self::from_document_file(path, "yaml"),
+            _ => (),
+        }
 
-        let mut schema = Schema { fixedwidthschema: None, csvschema: None };
+        // The includes and cell groups are expanded before loading the schema
+        let events = schema_include::read_schema_events(std::path::Path::new(path))?;
+        Self::from_events(events.into_iter().map(Ok))
+    }
+
+    #[allow(unused_variables)]
+    fn from_document_file(path: &str, feature: &str) -> Result<Self, Error> {
+        let document = std::fs::read_to_string(path)?;
+        match feature {
+            #[cfg(feature = "json")]
+            "json" => Self::from_json(&document),
+            #[cfg(feature = "yaml")]
+            "yaml" => Self::from_yaml(&document),
+            _ => Err(anyhow!("The schema is a {} file, enable the \"{}\" feature to read it", feature, feature)),
+        }
+    }
+
+    /// Load schema from the events of an XML document, with their position in the XML file if any
+    pub(crate) fn from_events(
+        events: impl Iterator<Item = Result<(XmlEvent, Option<TextPosition>), Error>>,
+    ) -> Result<Self, Error> {
+        let mut schema = Schema { fixedwidthschema: None, csvschema: None, lint_issues: Vec::new() };
+        let mut lint_issues = Vec::new();
+        let mut line_position = None; // Position of the current <line>
+        let mut lines_without_condition: Vec<(String, Option<TextPosition>)> = Vec::new();
 
         let mut temp_line = Line { padcharacter: String::from(" "), ..Default::default() };
         let mut in_line = false;
         let mut in_cell = false;
+        let mut in_emptycondition = false;
         let mut temp_format: Option<Format> = None;
-        let mut end_cell = 0;
+        let mut end_cell = 0; // End of the previous cell
+        let mut max_end_cell = 0; // End of the cells so far, greater than end_cell after overlapping cells
 
         let mut seen_linetypes = HashSet::new();
+        let mut temp_groups: Vec<Group> = Vec::new(); // Groups being parsed, the innermost last
+
+        for event in events {
+            let (mut event, position) = event?;
+            // Attributes of other namespaces, e.g. xsi:schemaLocation, are not part of the schema
+            if let XmlEvent::StartElement { attributes, .. } = &mut event {
+                attributes.retain(|attr| jsapar::is_schema_name(&attr.name));
+            }
 
-        for e in parser {
-            match e {
-                Ok(XmlEvent::StartElement { name, attributes, .. }) => match name.local_name.as_str() {
-                    "fixedwidthschema" => {
-                        schema.fixedwidthschema =
-                            Some(FixedWidthSchema { lineseparator: "\n".to_string(), ..Default::default() });
-                        for attr in attributes {
-                            if attr.name.local_name == "lineseparator" {
+            // The errors are reported at the position of the element
+            let handle_event = || -> Result<(), Error> {
+                match event {
+                    // Elements of other namespaces are not part of the schema
+                    XmlEvent::StartElement { name, .. } | XmlEvent::EndElement { name }
+                        if !jsapar::is_schema_name(&name) => {}
+                    XmlEvent::StartElement { name, attributes, .. } => match name.local_name.as_str() {
+                        "fixedwidthschema" => {
+                            schema.fixedwidthschema =
+                                Some(FixedWidthSchema { lineseparator: "\n".to_string(), ..Default::default() });
+                            for attr in attributes {
                                 if let Some(fixed_width_schema) = &mut schema.fixedwidthschema {
-                                    fixed_width_schema.lineseparator = attr.value;
+                                    match attr.name.local_name.as_str() {
+                                        "lineseparator" => fixed_width_schema.lineseparator = attr.value,
+                                        "recordlength" => {
+                                            fixed_width_schema.recordlength =
+                                                parse_length(&attr, position, &mut lint_issues)
+                                        }
+                                        "ignoreemptylines" => {
+                                            fixed_width_schema.ignoreemptylines = attr.value == "true"
+                                        }
+                                        _ => (),
+                                    }
                                 }
                             }
                         }
-                    }
-                    "line" => {
-                        in_line = true;
-                        temp_line = Line { padcharacter: String::from(" "), ..Default::default() };
-                        for attr in attributes {
-                            match attr.name.local_name.as_str() {
-                                "linetype" => {
-                                    if seen_linetypes.contains(&attr.value) {
-                                        return Err(anyhow!("Duplicate linetype: {}", attr.value));
+                        "locale" if !in_line => {
+                            let mut language = String::new();
+                            let mut country = String::new();
+                            for attr in attributes {
+                                match attr.name.local_name.as_str() {
+                                    "language" => language = attr.value,
+                                    "country" => country = attr.value,
+                                    _ => (),
+                                }
+                            }
+                            if let Some(fixed_width_schema) = &mut schema.fixedwidthschema {
+                                fixed_width_schema.locale = Some(Locale::new(&language, &country)?);
+                            }
+                        }
+                        "line" => {
+                            in_line = true;
+                            line_position = position;
+                            temp_line = Line { padcharacter: String::from(" "), ..Default::default() };
+                            for attr in attributes {
+                                match attr.name.local_name.as_str() {
+                                    "linetype" => {
+                                        if seen_linetypes.contains(&attr.value) {
+                                            return Err(anyhow!("Duplicate linetype: {}", attr.value));
+                                        }
+                                        seen_linetypes.insert(attr.value.clone());
+                                        temp_line.linetype = attr.value;
                                     }
-                                    seen_linetypes.insert(attr.value.clone());
-                                    temp_line.linetype = attr.value;
+                                    "occurs" => temp_line.occurs = attr.value, // TODO: not used by the parser yet.
+                                    "maxlength" => {
+                                        temp_line.maxlength = parse_length(&attr, position, &mut lint_issues)
+                                    }
+                                    "minlength" => {
+                                        // TODO: not used by the parser yet.
+                                        temp_line.minlength = parse_length(&attr, position, &mut lint_issues)
+                                    }
+                                    "padcharacter" => temp_line.padcharacter = attr.value,
+                                    "allowoverlaps" => temp_line.allowoverlaps = attr.value == "true",
+                                    _ => (),
                                 }
-                                "occurs" => temp_line.occurs = attr.value, // TODO: not used by the parser yet.
-                                "maxlength" => temp_line.maxlength = attr.value.parse().unwrap_or(0),
-                                "minlength" => {
-                                    // TODO: not used by the parser yet.
-                                    temp_line.minlength = attr.value.parse().unwrap_or(0)
+                            }
+                        }
+                        "group" if !in_line => {
+                            // Groups occur any number of times unless "occurs" is set
+                            let mut temp_group = Group {
+                                name: String::new(),
+                                occurs: Occurs::parse("*")?,
+                                items: Vec::new(),
+                                controltotals: Vec::new(),
+                            };
+                            for attr in attributes {
+                                match attr.name.local_name.as_str() {
+                                    "name" => temp_group.name = attr.value,
+                                    "occurs" => temp_group.occurs = Occurs::parse(&attr.value)?,
+                                    _ => (),
                                 }
-                                "padcharacter" => temp_line.padcharacter = attr.value,
-                                _ => (),
                             }
+                            temp_groups.push(temp_group);
                         }
-                    }
-                    "cell" if in_line => {
-                        in_cell = true;
-
-                        let mut temp_cell =
-                            Cell { padcharacter: temp_line.padcharacter.to_owned(), ..Default::default() };
-
-                        for attr in attributes {
-                            match attr.name.local_name.as_str() {
-                                "name" => temp_cell.name = attr.value,
-                                "length" => temp_cell.length = attr.value.parse().unwrap_or(0),
-                                "alignment" => temp_cell.alignment = attr.value,
-                                "padcharacter" => temp_cell.padcharacter = attr.value,
-                                _ => (),
+                        "lineref" if !temp_groups.is_empty() => {
+                            // Line references occur once unless "occurs" is set
+                            let mut line_ref = LineRef { linetype: String::new(), occurs: Occurs::parse("1")? };
+                            for attr in attributes {
+                                match attr.name.local_name.as_str() {
+                                    "linetype" => line_ref.linetype = attr.value,
+                                    "occurs" => line_ref.occurs = Occurs::parse(&attr.value)?,
+                                    _ => (),
+                                }
+                            }
+                            if let Some(group) = temp_groups.last_mut() {
+                                group.items.push(GroupItem::Line(line_ref));
+                            }
+                        }
+                        "controltotal" if !in_line => {
+                            for attr in attributes {
+                                if attr.name.local_name == "rule" {
+                                    let control_total = ControlTotal::parse(&attr.value)?;
+                                    match (temp_groups.last_mut(), &mut schema.fixedwidthschema) {
+                                        (Some(group), _) => group.controltotals.push(control_total),
+                                        (None, Some(fixed_width_schema)) => {
+                                            fixed_width_schema.controltotals.push(control_total)
+                                        }
+                                        (None, None) => (),
+                                    }
+                                }
                             }
                         }
+                        "cell" if in_line => {
+                            in_cell = true;
 
-                        end_cell += temp_cell.length;
-
-                        temp_line.cell.push(Cell {
-                            name: temp_cell.name,
-                            length: temp_cell.length,
-                            start: end_cell - temp_cell.length,
-                            end: end_cell,
-                            alignment: temp_cell.alignment,
-                            padcharacter: temp_cell.padcharacter,
-                            ..Default::default()
-                        });
-                    }
-                    "format" if in_cell => {
-                        let mut ctype = String::new();
-                        let mut pattern = String::new();
-                        for attr in attributes {
-                            match attr.name.local_name.as_str() {
-                                "type" => ctype = attr.value.to_lowercase(),
-                                "pattern" => pattern = attr.value,
-                                _ => (),
+                            let mut temp_cell =
+                                Cell { padcharacter: temp_line.padcharacter.to_owned(), ..Default::default() };
+                            let mut start = None; // 1-based position of the cell, after the previous cell if not set
+
+                            for attr in attributes {
+                                match attr.name.local_name.as_str() {
+                                    "name" => temp_cell.name = attr.value,
+                                    "length" => temp_cell.length = parse_length(&attr, position, &mut lint_issues),
+                                    "start" => match attr.value.parse::<usize>() {
+                                        Ok(position) if position > 0 => start = Some(position - 1),
+                                        _ => return Err(anyhow!("Invalid start (1-based): {}", attr.value)),
+                                    },
+                                    "alignment" => temp_cell.alignment = attr.value,
+                                    "padcharacter" => temp_cell.padcharacter = attr.value,
+                                    "mandatory" => temp_cell.mandatory = attr.value == "true",
+                                    "minvalue" => temp_cell.minvalue = Some(attr.value),
+                                    "maxvalue" => temp_cell.maxvalue = Some(attr.value),
+                                    "values" => temp_cell.values = attr.value.split('|').map(str::to_owned).collect(),
+                                    "minlength" => {
+                                        temp_cell.minlength = parse_length(&attr, position, &mut lint_issues)
+                                    }
+                                    "maxlength" => {
+                                        temp_cell.maxlength = parse_length(&attr, position, &mut lint_issues)
+                                    }
+                                    "emptypattern" => {
+                                        temp_cell.emptypattern_regex = Some(Self::compile_empty_pattern(&attr.value)?);
+                                        temp_cell.emptypattern = Some(attr.value);
+                                    }
+                                    "default" => temp_cell.default = Some(attr.value),
+                                    "ignoreread" => temp_cell.ignoreread = attr.value == "true",
+                                    _ => (),
+                                }
+                            }
+                            // Unnamed cells are fillers
+                            if temp_cell.name.is_empty() {
+                                temp_cell.ignoreread = true;
+                            } else if temp_line.cell.iter().any(|cell| cell.name == temp_cell.name) {
+                                let message =
+                                    format!("Duplicate cell {} in line {}", temp_cell.name, temp_line.linetype);
+                                lint_issues.push(LintIssue::new(position, message));
+                            }
+
+                            temp_cell.start = start.unwrap_or(end_cell);
+                            temp_cell.end = temp_cell.start + temp_cell.length;
+                            if temp_cell.start > max_end_cell {
+                                return Err(anyhow!(
+                                    "Gap before cell {} of line {} at position {}, add a filler cell",
+                                    temp_cell.name,
+                                    temp_line.linetype,
+                                    max_end_cell + 1
+                                ));
+                            }
+                            if temp_cell.start < max_end_cell && !temp_line.allowoverlaps {
+                                return Err(anyhow!(
+                                    "Overlapping cell {} of line {} at position {}, set allowoverlaps=\"true\" to allow it",
+                                    temp_cell.name,
+                                    temp_line.linetype,
+                                    temp_cell.start + 1
+                                ));
                             }
+                            end_cell = temp_cell.end;
+                            max_end_cell = max_end_cell.max(end_cell);
+
+                            temp_line.cell.push(temp_cell);
                         }
+                        // <cellformat> of JSaPar 1.x
+                        "format" | "cellformat" if in_cell => {
+                            let mut ctype = String::new();
+                            let mut pattern = String::new();
+                            let mut timezone = None;
+                            for attr in attributes {
+                                match attr.name.local_name.as_str() {
+                                    "type" => ctype = jsapar::format_type(&attr.value.to_lowercase()).to_owned(),
+                                    "pattern" => pattern = attr.value,
+                                    "timezone" => timezone = Some(Format::parse_timezone(&attr.value)?),
+                                    _ => (),
+                                }
+                            }
+                            if !FORMAT_TYPES.contains(&ctype.as_str()) {
+                                return Err(anyhow!("Unknown format type: {}", ctype));
+                            }
 
-                        let mut regex_pattern = None;
-                        if ctype == "string" {
-                            regex_pattern = match regex::Regex::new(&pattern) {
-                                Ok(re) => Some(re),
-                                Err(e) => {
-                                    return Err(e).context(format!("Error compiling regex pattern: {}", pattern));
+                            // Default patterns
+                            if pattern.is_empty() {
+                                match ctype.as_str() {
+                                    "boolean" => pattern = "true;false".to_string(),
+                                    "time" => pattern = "%H:%M:%S".to_string(),
+                                    "datetime" => pattern = "%Y-%m-%d %H:%M:%S".to_string(),
+                                    "implieddecimal" => pattern = "0".to_string(),
+                                    "enum" => return Err(anyhow!("The enum format needs a pattern with its values")),
+                                    _ => (),
                                 }
+                            }
+
+                            if ctype == "implieddecimal" && pattern.parse::<u8>().is_err() {
+                                return Err(anyhow!(
+                                    "The implieddecimal format needs the number of decimals as pattern: {}",
+                                    pattern
+                                ));
+                            }
+
+                            // Java patterns of JSaPar schemas, e.g. "yyyyMMdd", are translated to chrono patterns
+                            if matches!(ctype.as_str(), "date" | "time" | "datetime") {
+                                pattern = date_format::java_date_pattern(&pattern).map_err(|e| anyhow!(e))?;
+                            }
+
+                            let mut regex_pattern = None;
+                            if ctype == "string" {
+                                regex_pattern = match regex::Regex::new(&pattern) {
+                                    Ok(re) => Some(re),
+                                    Err(e) => {
+                                        return Err(e).context(format!("Error compiling regex pattern: {}", pattern));
+                                    }
+                                };
+                            }
+
+                            let literals = |literals: &str| literals.split('|').map(str::to_owned).collect::<Vec<_>>();
+                            let (true_values, false_values, values) = match ctype.as_str() {
+                                "boolean" => match pattern.split_once(';') {
+                                    Some((true_values, false_values)) => {
+                                        (literals(true_values), literals(false_values), vec![])
+                                    }
+                                    None => return Err(anyhow!("Invalid boolean pattern: {}", pattern)),
+                                },
+                                "enum" => (vec![], vec![], literals(&pattern)),
+                                _ => (vec![], vec![], vec![]),
                             };
+
+                            temp_format = Some(Format {
+                                ctype,
+                                pattern,
+                                regex_pattern,
+                                true_values,
+                                false_values,
+                                values,
+                                timezone,
+                                locale: schema.fixedwidthschema.as_ref().and_then(|schema| schema.locale.clone()),
+                            });
                         }
-                        
-                        temp_format = Some(Format { ctype, pattern, regex_pattern});
-                    }
-                    "match" if in_cell => {
-                        let mut matchtype = String::new();
-                        let mut matchpattern = String::new();
+                        "emptycondition" if in_cell => in_emptycondition = true,
+                        "match" if in_cell => {
+                            let mut matchtype = String::new();
+                            let mut matchpattern = String::new();
 
-                        for attr in attributes {
-                            if attr.name.local_name == "type" {
-                                matchtype = attr.value.to_lowercase();
+                            for attr in attributes {
+                                if attr.name.local_name == "type" {
+                                    matchtype = attr.value.to_lowercase();
+                                }
+                                if attr.name.local_name == "pattern" {
+                                    matchpattern = attr.value;
+                                }
                             }
-                            if attr.name.local_name == "pattern" {
-                                matchpattern = attr.value;
+                            if let Some(cell) = temp_line.cell.last_mut() {
+                                if in_emptycondition {
+                                    cell.emptypattern_regex = Some(Self::compile_empty_pattern(&matchpattern)?);
+                                    cell.emptypattern = Some(matchpattern);
+                                    return Ok(());
+                                }
+
+                                // The trimmed value must match the whole regex, unless the type is "string"
+                                cell.linecondition_regex = match matchtype.as_str() {
+                                    "string" => None,
+                                    "" | "regex" => {
+                                        Some(regex::Regex::new(&format!("^(?:{})$", matchpattern)).with_context(
+                                            || format!("Error compiling linecondition pattern: {}", matchpattern),
+                                        )?)
+                                    }
+                                    _ => return Err(anyhow!("Unknown linecondition type: {}", matchtype)),
+                                };
+                                cell.linecondition_type = Some(matchtype);
+                                cell.linecondition_pattern = Some(matchpattern);
                             }
                         }
-                        if let Some(cell) = temp_line.cell.last_mut() {
-                            cell.linecondition_type = Some(matchtype);
-                            cell.linecondition_pattern = Some(matchpattern);
+                        element @ ("format" | "cellformat" | "linecondition" | "emptycondition" | "match")
+                            if !in_cell =>
+                        {
+                            let message = format!("<{}> outside a cell is ignored", element);
+                            lint_issues.push(LintIssue::new(position, message));
                         }
-                    }
-                    _ => (),
-                },
-                Ok(XmlEvent::EndElement { name, .. }) => match name.local_name.as_str() {
-                    "cell" => {
-                        if in_cell {
+                        _ => (),
+                    },
+                    XmlEvent::EndElement { name, .. } => match name.local_name.as_str() {
+                        "cell" if in_cell => {
                             if let Some(cell) = temp_line.cell.last_mut() {
                                 cell.format = temp_format.take();
+                                // The format is needed to read the value range and the default value
+                                Self::check_cell_value_range(cell)?;
+                                if let Some(default) = &cell.default {
+                                    Self::validate_format(cell, default).map_err(|message| {
+                                        anyhow!("Invalid default value for cell {}: {}", cell.name, message)
+                                    })?;
+                                }
                             }
                             in_cell = false;
                         }
-                    }
-                    "line" => {
-                        if in_line {
+                        "emptycondition" => in_emptycondition = false,
+                        "line" if in_line => {
+                            if temp_line.maxlength > 0 && max_end_cell != temp_line.maxlength {
+                                let message = format!(
+                                    "The cells of line {} end at {} but the maxlength is {}",
+                                    temp_line.linetype, max_end_cell, temp_line.maxlength
+                                );
+                                lint_issues.push(LintIssue::new(line_position, message));
+                            }
+                            if temp_line.cell.iter().all(|cell| cell.linecondition_pattern.is_none()) {
+                                lines_without_condition.push((temp_line.linetype.to_owned(), line_position));
+                            }
                             if let Some(fixed_width_schema) = &mut schema.fixedwidthschema {
                                 fixed_width_schema.lines.push(temp_line.to_owned());
                             }
 
                             in_line = false;
                             end_cell = 0;
+                            max_end_cell = 0;
                         }
+                        "group" if !temp_groups.is_empty() => {
+                            let group = temp_groups.pop().unwrap();
+                            match temp_groups.last_mut() {
+                                Some(parent_group) => parent_group.items.push(GroupItem::Group(group)),
+                                None => {
+                                    if let Some(fixed_width_schema) = &mut schema.fixedwidthschema {
+                                        fixed_width_schema.groups.push(group);
+                                    }
+                                }
+                            }
+                        }
+                        _ => (),
+                    },
+                    _ => (),
+                }
+                Ok(())
+            };
+            handle_event().map_err(|e| error_at(position, e))?;
+        }
+
+        lint_issues.extend(LintIssue::lines_without_condition(&lines_without_condition));
+        schema.lint_issues = lint_issues;
+
+        // The groups may reference lines declared after them
+        if let Some(fixed_width_schema) = &schema.fixedwidthschema {
+            Self::check_group_line_refs(&fixed_width_schema.groups, &seen_linetypes)?;
+            schema.check_control_totals(&fixed_width_schema.controltotals)?;
+            schema.check_group_control_totals(&fixed_width_schema.groups)?;
+        }
+
+        Ok(schema)
+    }
+
+    /// Compile an "emptypattern" or `<emptycondition>`, the whole trimmed value must match
+    fn compile_empty_pattern(pattern: &str) -> Result<regex::Regex, Error> {
+        regex::Regex::new(&format!("^(?:{})$", pattern))
+            .with_context(|| format!("Error compiling emptypattern: {}", pattern))
+    }
+
+    /// Load schema from XML file, failing on the mistakes reported by `Schema::lint`
+    pub fn new_strict(path: &str) -> Result<Self, Error> {
+        let schema = Self::new(path)?;
+        if !schema.lint_issues.is_empty() {
+            let issues: Vec<String> = schema.lint_issues.iter().map(LintIssue::to_string).collect();
+            return Err(anyhow!("Invalid schema {}:\n{}", path, issues.join("\n")));
+        }
+        Ok(schema)
+    }
+
+    /// Load the schema of a parser: strict if `strict_schema` is set, with the cells of the `projection`
+    pub(crate) fn from_config(config: &ParserConfig) -> Result<Self, Error> {
+        let mut schema = match config.strict_schema {
+            true => Self::new_strict(&config.file_schema)?,
+            false => Self::new(&config.file_schema)?,
+        };
+        schema.project(&config.projection)?;
+        Ok(schema)
+    }
+
+    /// Mistakes found when loading the schema, with their position in the XML file:
+    /// invalid lengths, cells not ending at the maxlength of the line, `<format>` outside a cell,
+    /// duplicate cell names and more than one line without conditions.
+    pub fn lint(&self) -> &[LintIssue] {
+        &self.lint_issues
+    }
+
+    /// Check that the line references of the groups refer to declared line types
+    fn check_group_line_refs(groups: &[Group], linetypes: &HashSet<String>) -> Result<(), Error> {
+        for group in groups {
+            for item in &group.items {
+                match item {
+                    GroupItem::Line(line_ref) if !linetypes.contains(&line_ref.linetype) => {
+                        return Err(anyhow!("Unknown linetype in group {}: {}", group.name, line_ref.linetype));
+                    }
+                    GroupItem::Group(nested_group) => {
+                        Self::check_group_line_refs(std::slice::from_ref(nested_group), linetypes)?
                     }
                     _ => (),
-                },
-                Err(e) => return Err(e).context("Error parsing XML"),
-                _ => (),
+                }
             }
         }
+        Ok(())
+    }
 
-        Ok(schema)
+    /// Check that "minvalue" and "maxvalue" are numbers or dates in the format of the cell
+    fn check_cell_value_range(cell: &Cell) -> Result<(), Error> {
+        for bound in [&cell.minvalue, &cell.maxvalue].into_iter().flatten() {
+            let valid_bound = match &cell.format {
+                Some(format) if format.ctype == "number" => {
+                    Decimal::parse(&format.normalize_number(bound).replace(',', "")).is_some()
+                }
+                Some(format) if RANGE_FORMAT_TYPES.contains(&format.ctype.as_str()) => {
+                    format.parse_typed(bound).is_some()
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "minvalue and maxvalue need a {} format: cell {}",
+                        RANGE_FORMAT_TYPES.join(", "),
+                        cell.name
+                    ))
+                }
+            };
+            if !valid_bound {
+                return Err(anyhow!("Invalid minvalue or maxvalue for the format of cell {}: {}", cell.name, bound));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the control totals refer to declared line types and cells
+    fn check_control_totals(&self, control_totals: &[ControlTotal]) -> Result<(), Error> {
+        for control_total in control_totals {
+            let (linetype, cell) = match &control_total.aggregate {
+                Aggregate::Sum { linetype, cell } => (linetype, Some(cell)),
+                Aggregate::Count { linetype } => (linetype, None),
+            };
+            for (linetype, cell) in [(&control_total.linetype, Some(&control_total.cell)), (linetype, cell)] {
+                let line = self.get_binding().lines.iter().find(|line| &line.linetype == linetype);
+                let known_cell = match (line, cell) {
+                    (Some(line), Some(cell)) => {
+                        line.cell.iter().any(|line_cell| &line_cell.name == cell && !line_cell.ignoreread)
+                    }
+                    (line, None) => line.is_some(),
+                    (None, Some(_)) => false,
+                };
+                if !known_cell {
+                    return Err(anyhow!("Unknown linetype or ignored cell in control total: {}", control_total.rule));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_group_control_totals(&self, groups: &[Group]) -> Result<(), Error> {
+        for group in groups {
+            self.check_control_totals(&group.controltotals)?;
+            for item in &group.items {
+                if let GroupItem::Group(nested_group) = item {
+                    self.check_group_control_totals(std::slice::from_ref(nested_group))?;
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Get all line conditions from the schema
@@ -275,6 +1082,76 @@ impl Schema {
         line
     }
 
+    /// Get the groups of lines declared in the schema
+    pub fn get_groups(&self) -> &[Group] {
+        &self.get_binding().groups
+    }
+
+    /// Get the typed value of a cell of a processed line, see `Cell::parse_value`
+    pub fn get_typed_value(&self, line: &ProcessedLineOk, cell_name: &str) -> Result<Option<CellValue>, String> {
+        match (self.find_cell(&line.linetype, cell_name), line.cell_values.get(cell_name)) {
+            (Some(cell), Some(value)) => cell.parse_value(value),
+            _ => Err(format!("Unknown cell {}.{}", line.linetype, cell_name)),
+        }
+    }
+
+    /// Get a cell of a line type by name
+    pub(crate) fn find_cell(&self, linetype: &str, cell_name: &str) -> Option<&Cell> {
+        let line = self.get_binding().lines.iter().find(|schema_line| schema_line.linetype == linetype)?;
+        line.cell.iter().find(|cell| cell.name == cell_name)
+    }
+
+    /// Emit only the given cells in the cell values (projection), all the cells if empty.
+    /// The other cells are still validated, and the cells of the control totals are always kept.
+    pub fn project(&mut self, cell_names: &[String]) -> Result<(), Error> {
+        if cell_names.is_empty() {
+            return Ok(());
+        }
+        for cell_name in cell_names {
+            if !self.get_binding().lines.iter().any(|line| line.cell.iter().any(|cell| &cell.name == cell_name)) {
+                return Err(anyhow!("Unknown cell in projection: {}", cell_name));
+            }
+        }
+
+        let mut control_total_cells: HashSet<(String, String)> = HashSet::new();
+        let mut pending_groups: Vec<&Group> = self.get_groups().iter().collect();
+        let mut control_totals: Vec<&ControlTotal> = self.get_control_totals().iter().collect();
+        while let Some(group) = pending_groups.pop() {
+            control_totals.extend(&group.controltotals);
+            pending_groups.extend(group.items.iter().filter_map(|item| match item {
+                GroupItem::Group(nested_group) => Some(nested_group),
+                GroupItem::Line(_) => None,
+            }));
+        }
+        for control_total in control_totals {
+            control_total_cells.insert((control_total.linetype.to_owned(), control_total.cell.to_owned()));
+            if let Aggregate::Sum { linetype, cell } = &control_total.aggregate {
+                control_total_cells.insert((linetype.to_owned(), cell.to_owned()));
+            }
+        }
+
+        if let Some(fixed_width_schema) = &mut self.fixedwidthschema {
+            for line in &mut fixed_width_schema.lines {
+                for cell in &mut line.cell {
+                    let kept = cell_names.contains(&cell.name)
+                        || control_total_cells.contains(&(line.linetype.to_owned(), cell.name.to_owned()));
+                    cell.ignoreread |= !kept;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the control totals checked at the end of the file
+    pub fn get_control_totals(&self) -> &[ControlTotal] {
+        &self.get_binding().controltotals
+    }
+
+    /// Check if a line is skipped by the parsers: an empty line when "ignoreemptylines" is set
+    pub fn is_ignored_line(&self, line_text: &str) -> bool {
+        line_text.is_empty() && self.get_binding().ignoreemptylines
+    }
+
     /// Get the newline characters
     /// Example: "\n", "\r\n", ...
     pub fn get_newline_characters(&self) -> &str {
@@ -282,8 +1159,47 @@ impl Schema {
         &binding.lineseparator
     }
 
+    /// Get the fixed record length
+    /// Returns the value of the "recordlength" attribute or 0 if it is not set
+    pub fn get_record_length(&self) -> usize {
+        self.get_binding().recordlength
+    }
+
+    /// Check if the records are delimited by length instead of by the line separator
+    /// This is the case when "lineseparator" is empty or "recordlength" is set
+    pub fn is_record_length_delimited(&self) -> bool {
+        self.get_newline_characters().is_empty() || self.get_record_length() > 0
+    }
+
+    /// Check if the line separator is detected from the file
+    /// This is the case when "lineseparator" is "auto" ("\n", "\r\n" or "\r" are accepted)
+    pub fn is_auto_line_separator(&self) -> bool {
+        self.get_newline_characters() == "auto"
+    }
+
+    /// Get the number of leading characters needed to resolve the line type
+    /// Returns the end position of the last cell with a line condition
+    pub fn get_line_condition_length(&self) -> usize {
+        self.get_line_conditions().iter().flat_map(|(_, cells)| cells.iter().map(|cell| cell.end)).max().unwrap_or(0)
+    }
+
+    /// Find the record length for a file without line separators
+    /// The "recordlength" attribute is used if set, otherwise the line type is resolved
+    /// from the leading characters of the record and its "maxlength" is used.
+    /// Returns None if no line type matches or the matched line has no "maxlength"
+    pub fn find_record_length(&self, line_prefix: &str) -> Option<usize> {
+        if self.get_record_length() > 0 {
+            return Some(self.get_record_length());
+        }
+
+        match self.find_line(line_prefix) {
+            Some(line) if line.maxlength > 0 => Some(line.maxlength),
+            _ => None,
+        }
+    }
+
     /// Get binding schema (fixed width or csv)
-    fn get_binding(&self) -> &FixedWidthSchema {
+    pub(crate) fn get_binding(&self) -> &FixedWidthSchema {
         // For now it is only implemented for fixed width scheme.
         match self.fixedwidthschema.as_ref() {
             Some(fixed_width_schema) => fixed_width_schema,
@@ -300,37 +1216,7 @@ impl Schema {
     ) -> Option<(String, Line)> {
         let mut match_line_name = "";
         for (line_name, cell_conditions) in schema_lines_with_condition {
-            let mut line_condition_met = false;
-            for cell_line_condition in cell_conditions {
-                let cell_value: &str = &line_text[cell_line_condition.start..cell_line_condition.end];
-
-                /*
-                Validate the cell value previously to check the line condition
-                When there is a <format> together with a <linecondition>
-
-                    <cell name="Foo" length="5">
-                        <format type="regex" pattern=".*"/>
-                        <linecondition><match type="string" pattern="H"/></linecondition>
-                    </cell>
-                */
-                match Self::validate_cell(cell_line_condition, line_text) {
-                    Ok(_) => {}
-                    Err(_) => {
-                        continue;
-                    }
-                }
-
-                // Check if the line condition is met
-                // TODO: Add support for other linecondition types (e.g. regex, number, ...)
-                if cell_line_condition.linecondition_type.is_none()
-                    || cell_line_condition.linecondition_type == Some("string".to_string())
-                {
-                    line_condition_met = cell_value == cell_line_condition.linecondition_pattern.as_ref().unwrap();
-                } else {
-                    todo!("Line condition type not implemented yet");
-                }
-            }
-            if line_condition_met {
+            if Self::is_line_condition_met(cell_conditions, line_text) {
                 match_line_name = line_name;
                 break;
             }
@@ -347,6 +1233,58 @@ impl Schema {
         line.map(|line| (match_line_name.to_owned(), line))
     }
 
+    /// Find the line that matches the line condition without cloning it
+    /// Same rules as `find_matching_schema_linetype`: the first line whose conditions are met,
+    /// otherwise the only line without conditions.
+    pub fn find_line(&self, line_text: &str) -> Option<&Line> {
+        let binding = self.get_binding();
+
+        let line_with_condition = binding.lines.iter().find(|line| {
+            let mut cell_conditions = line.cell.iter().filter(|cell| cell.linecondition_pattern.is_some()).peekable();
+            cell_conditions.peek().is_some() && Self::is_line_condition_met(cell_conditions, line_text)
+        });
+        if line_with_condition.is_some() {
+            return line_with_condition;
+        }
+
+        // If there is more than one line without conditions, in that case it should return None.
+        let mut lines_without_condition =
+            binding.lines.iter().filter(|line| line.cell.iter().all(|cell| cell.linecondition_pattern.is_none()));
+        match (lines_without_condition.next(), lines_without_condition.next()) {
+            (Some(line), None) => Some(line),
+            _ => None,
+        }
+    }
+
+    /// Check the line conditions of the cells against the line text, all of them must be met
+    fn is_line_condition_met<'c>(cell_conditions: impl IntoIterator<Item = &'c Cell>, line_text: &str) -> bool {
+        cell_conditions.into_iter().all(|cell_line_condition| {
+            let cell_value: &str = match line_text.get(cell_line_condition.start..cell_line_condition.end) {
+                Some(cell_value) => cell_value,
+                None => return false,
+            };
+
+            /*
+            Validate the cell value previously to check the line condition
+            When there is a <format> together with a <linecondition>
+
+                <cell name="Foo" length="5">
+                    <format type="regex" pattern=".*"/>
+                    <linecondition><match type="string" pattern="H"/></linecondition>
+                </cell>
+            */
+            if Self::validate_cell(cell_line_condition, line_text).is_err() {
+                return false;
+            }
+
+            // A "string" condition is compared with the cell value, a regex is matched by the trimmed value
+            match &cell_line_condition.linecondition_regex {
+                Some(re) => re.is_match(Self::trim_cell_value(cell_line_condition, cell_value)),
+                None => Some(cell_value) == cell_line_condition.linecondition_pattern.as_deref(),
+            }
+        })
+    }
+
     /// Compiled regex for line condition
     pub fn compile_line_condition(&self, line_condition: &LineCondition) -> regex::Regex {
         regex::Regex::new(&line_condition.matchpattern).unwrap()
@@ -376,19 +1314,24 @@ impl Schema {
     /// [err:xxx]|cellname|ctype|message -> for cell errors
     ///
     pub fn validate_line(&self, line_number: usize, line_text: String) -> Result<ProcessedLineOk, ProcessedLineError> {
-        let schema_lines_with_condition: Vec<(String, Vec<Cell>)> = self.get_line_conditions().to_owned();
+        self.validate_line_ref(line_number, &line_text).map(ProcessedLine::into_owned)
+    }
 
+    /// Validate a line without copying the cell values
+    /// Same as `validate_line`, but the returned crate::parser::ProcessedLine borrows
+    /// the cell values from `line_text` and the cell names and line type from the schema.
+    pub fn validate_line_ref<'a>(
+        &'a self, line_number: usize, line_text: &'a str,
+    ) -> Result<ProcessedLine<'a>, ProcessedLineError> {
         if self.get_schema_type() == "fixedwidthschema" {
             // Find the line type that matches the line condition (from schema)
-            let match_line: Option<(String, Line)> =
-                self.find_matching_schema_linetype(&line_text, &schema_lines_with_condition);
-
-            let (linetype, match_line) = match match_line {
-                Some(match_line) => (match_line.0, match_line.1),
+            let match_line = match self.find_line(line_text) {
+                Some(match_line) => match_line,
                 None => {
                     return Err(ProcessedLineError {
                         line_number,
                         message: "[err:001]|line|no match found for schema line type".to_string(),
+                        linetype: None,
                     });
                     // TODO: Add optional if the first error should stop processing other lines. (ParserConfig)
                 }
@@ -403,19 +1346,21 @@ impl Schema {
                         line_text.len(),
                         match_line.maxlength
                     ),
+                    linetype: Some(match_line.linetype.to_owned()),
                 });
                 // TODO: Add optional if the first error should stop processing other lines. (ParserConfig)
             }
 
             // Validate each cell in the line
-            let mut cell_values: IndexMap<String, String> = Default::default();
+            let mut cell_values: IndexMap<&str, &str> = IndexMap::with_capacity(match_line.cell.len());
 
             let mut first_error: Option<String> = None;
-            for cell in match_line.cell {
-                match Self::validate_cell(&cell, &line_text) {
-                    Ok(cell_value) => {
-                        cell_values.insert(cell.name, cell_value);
+            for cell in &match_line.cell {
+                match Self::validate_cell(cell, line_text) {
+                    Ok(cell_value) if !cell.ignoreread => {
+                        cell_values.insert(&cell.name, cell_value);
                     }
+                    Ok(_) => (),
                     Err(err) => {
                         first_error = err.to_string().into();
                         break; // TODO: Add optional if the first error should stop processing other cells. (ParserConfig)
@@ -427,11 +1372,12 @@ impl Schema {
                 return Err(ProcessedLineError {
                     line_number,
                     message: first_error.unwrap_or("Unknown error".to_string()),
+                    linetype: Some(match_line.linetype.to_owned()),
                 });
                 // TODO: Add optional if the first error should stop processing other lines. (ParserConfig)
             }
 
-            Ok(ProcessedLineOk { line_number, cell_values, linetype })
+            Ok(ProcessedLine { line_number, cell_values, linetype: &match_line.linetype, warnings: Vec::new() })
         } else if self.get_schema_type() == "csvschema" {
             todo!("CSV schema not implemented yet");
         } else {
@@ -441,14 +1387,18 @@ impl Schema {
 
     /// Validate a cell
     /// Returns:
-    /// - Ok(cell_value) 'cell_value' as String
+    /// - Ok(cell_value) 'cell_value' as a slice of the line text
     /// - Err(message)
     ///
-    fn validate_cell(cell: &Cell, line_text: &str) -> Result<String, String> {
+    /// The value is trimmed of the pad character when the cell has a format.
+    /// Empty values must match the format unless the cell has an "emptypattern" or a "default".
+    /// The constraints of the cell are checked on the trimmed value, and are skipped for empty values:
+    /// [err:014] mandatory, [err:015] minvalue, [err:016] maxvalue, [err:017] values,
+    /// [err:018] minlength and [err:019] maxlength.
+    /// Unnamed cells (fillers) without format must be empty: [err:027].
+    ///
+    fn validate_cell<'a>(cell: &'a Cell, line_text: &'a str) -> Result<&'a str, String> {
         let cell_name = &cell.name;
-        let mut cell_alignment = cell.alignment.to_owned();
-        let cell_padcharacter = &cell.padcharacter;
-
         let cell_value: Option<&str> = line_text.get(cell.start..cell.end);
         let cell_value = match cell_value {
             Some(cell_value) => cell_value,
@@ -456,69 +1406,161 @@ impl Schema {
                 return Err(format!("[err:003]|{}|range|invalid [{}]-[{}]", cell_name, cell.start, cell.end));
             }
         };
-        if let Some(format) = &cell.format {
-            if cell_alignment.is_empty() && format.ctype == "number" {
-                cell_alignment = "right".to_string();
-            } else if cell_alignment.is_empty() {
-                cell_alignment = "left".to_string();
-            }
+        let trimmed_value = Self::trim_cell_value(cell, cell_value);
 
-            let cell_value = match cell_alignment.as_str() {
-                "right" => {
-                    let cell_padcharacter_vec: Vec<char> = cell_padcharacter.chars().collect();
-                    let cell_padcharacter_slice: &[char] = &cell_padcharacter_vec;
-                    cell_value.trim_start_matches(cell_padcharacter_slice)
-                }
-                "left" => {
-                    let cell_padcharacter_vec: Vec<char> = cell_padcharacter.chars().collect();
-                    let cell_padcharacter_slice: &[char] = &cell_padcharacter_vec;
-                    cell_value.trim_end_matches(cell_padcharacter_slice)
+        // Empty cells with an "emptypattern" or a "default" are not validated against the format,
+        // and yield the default value if any. Other empty values must still match the format.
+        if cell.is_empty_value(trimmed_value) {
+            if cell.mandatory {
+                return Err(format!("[err:014]|{}|mandatory|the value is empty", cell_name));
+            }
+            match (&cell.default, &cell.format, &cell.emptypattern) {
+                (Some(default), _, _) => return Ok(default),
+                (None, None, None) => return Ok(cell_value),
+                (None, _, Some(_)) => return Ok(""),
+                (None, Some(_), None) => {
+                    Self::validate_format(cell, trimmed_value)?;
+                    return Ok(trimmed_value);
                 }
-                "center" => {
-                    let cell_padcharacter_vec: Vec<char> = cell_padcharacter.chars().collect();
-                    let cell_padcharacter_slice: &[char] = &cell_padcharacter_vec;
-                    cell_value.trim_matches(cell_padcharacter_slice)
+            }
+        }
+
+        // Fillers without format must be empty
+        if cell.name.is_empty() && cell.format.is_none() {
+            return Err(format!("[err:027]|filler|[{}]-[{}]|the filler is not empty", cell.start, cell.end));
+        }
+
+        Self::validate_format(cell, trimmed_value)?;
+        Self::validate_cell_constraints(cell, trimmed_value)?;
+
+        match cell.format {
+            Some(_) => Ok(trimmed_value),
+            None => Ok(cell_value),
+        }
+    }
+
+    /// Validate a trimmed value against the format of the cell
+    fn validate_format(cell: &Cell, cell_value: &str) -> Result<(), String> {
+        let cell_name = &cell.name;
+        let Some(format) = &cell.format else {
+            return Ok(());
+        };
+
+        if format.ctype == "date" {
+            // validate date format in cell_value
+            let dt = NaiveDate::parse_from_str(cell_value, &format.pattern);
+            if dt.is_err() {
+                return Err(format!("[err:004]|{}|{}|pattern:[{}]", cell_name, format.ctype, format.pattern));
+            }
+        } else if format.ctype == "string" {
+            // Validate regex format in cell_value
+            if let Some(re) = &format.regex_pattern {
+                if !re.is_match(cell_value) {
+                    return Err(format!("[err:005]|{}|{}|pattern:[{}]", cell_name, format.ctype, format.pattern));
                 }
-                _ => cell_value,
+            } else {
+                return Err(format!("[err:006]|{}|{}|pattern:[{}]", cell_name, format.ctype, format.pattern));
+            }
+        } else if format.ctype == "number" {
+            let formatter = decimal_format::DecimalFormat::new(&format.pattern).unwrap();
+            if formatter.validate_number(&format.normalize_number(cell_value)).is_err() {
+                return Err(format!("[err:007]|{}|{}|pattern:[{}]", cell_name, format.ctype, format.pattern));
+            }
+        } else if format.parse_typed(cell_value).is_none() {
+            let code = match format.ctype.as_str() {
+                "boolean" => "021",
+                "integer" => "022",
+                "float" => "023",
+                "implieddecimal" => "028",
+                "time" => "024",
+                "datetime" => "025",
+                _ => "026", // enum
             };
+            return Err(format!("[err:{}]|{}|{}|pattern:[{}]", code, cell_name, format.ctype, format.pattern));
+        }
+        Ok(())
+    }
 
-            // TODO: add more validation for other format types (e.g. number, regex, ...)
-            if format.ctype == "date" {
-                // validate date format in cell_value
-                let dt = NaiveDate::parse_from_str(cell_value, &format.pattern);
-                match dt {
-                    Ok(_) => {
-                        return Ok(cell_value.to_string());
-                    }
-                    Err(_) => {
-                        return Err(format!("[err:004]|{}|{}|pattern:[{}]", cell_name, format.ctype, format.pattern));
-                    }
-                }
-            } else if format.ctype == "string" {
-                // Validate regex format in cell_value
-                if let Some(re) = &format.regex_pattern {
-                    if re.is_match(cell_value) {
-                        return Ok(cell_value.to_string());
-                    } else {
-                        return Err(format!("[err:005]|{}|{}|pattern:[{}]", cell_name, format.ctype, format.pattern));
-                    }
-                } else {
-                    return Err(format!("[err:006]|{}|{}|pattern:[{}]", cell_name, format.ctype, format.pattern));
+    /// Trim the pad character according to the alignment of the cell, see `Cell::get_alignment`
+    fn trim_cell_value<'a>(cell: &Cell, cell_value: &'a str) -> &'a str {
+        let cell_alignment = cell.get_alignment();
+
+        let cell_padcharacter_vec: Vec<char> = cell.padcharacter.chars().collect();
+        let cell_padcharacter_slice: &[char] = &cell_padcharacter_vec;
+        match cell_alignment {
+            "right" => cell_value.trim_start_matches(cell_padcharacter_slice),
+            "left" => cell_value.trim_end_matches(cell_padcharacter_slice),
+            "center" => cell_value.trim_matches(cell_padcharacter_slice),
+            _ => cell_value,
+        }
+    }
+
+    /// Validate the constraints of a cell on its trimmed value, not empty
+    fn validate_cell_constraints(cell: &Cell, value: &str) -> Result<(), String> {
+        let cell_name = &cell.name;
+
+        let length = value.chars().count();
+        if cell.minlength > 0 && length < cell.minlength {
+            return Err(format!(
+                "[err:018]|{}|minlength|the value has length {} but was expected at least {}",
+                cell_name, length, cell.minlength
+            ));
+        }
+        if cell.maxlength > 0 && length > cell.maxlength {
+            return Err(format!(
+                "[err:019]|{}|maxlength|the value has length {} but was expected at most {}",
+                cell_name, length, cell.maxlength
+            ));
+        }
+
+        if !cell.values.is_empty() && !cell.values.iter().any(|allowed_value| allowed_value == value) {
+            return Err(format!("[err:017]|{}|values|{} is not one of [{}]", cell_name, value, cell.values.join("|")));
+        }
+
+        if let Some(format) = &cell.format {
+            // The bounds are checked against the format when the schema is loaded
+            let compare = |bound: &str| match format.ctype.as_str() {
+                "number" => Decimal::parse(&format.normalize_number(value).replace(',', ""))?
+                    .compare(Decimal::parse(&format.normalize_number(bound).replace(',', ""))?),
+                _ => format.parse_typed(value)?.compare(&format.parse_typed(bound)?),
+            };
+            if let Some(minvalue) = &cell.minvalue {
+                if compare(minvalue) == Some(std::cmp::Ordering::Less) {
+                    return Err(format!("[err:015]|{}|minvalue|{} is less than {}", cell_name, value, minvalue));
                 }
-                
-            } else if format.ctype == "number" {
-                let formatter = decimal_format::DecimalFormat::new(&format.pattern).unwrap();
-                match formatter.validate_number(cell_value) {
-                    Ok(_) => {
-                        return Ok(cell_value.to_string());
-                    }
-                    Err(_) => {
-                        return Err(format!("[err:007]|{}|{}|pattern:[{}]", cell_name, format.ctype, format.pattern));
-                    }
+            }
+            if let Some(maxvalue) = &cell.maxvalue {
+                if compare(maxvalue) == Some(std::cmp::Ordering::Greater) {
+                    return Err(format!("[err:016]|{}|maxvalue|{} is greater than {}", cell_name, value, maxvalue));
                 }
             }
         }
-        Ok(cell_value.to_string())
+
+        Ok(())
+    }
+}
+
+/// Schema written to a temporary file by a test, the file is removed when dropped
+#[cfg(test)]
+pub(crate) struct TestSchemaFile(std::path::PathBuf);
+
+#[cfg(test)]
+impl TestSchemaFile {
+    pub(crate) fn new(name: &str, xml: &str) -> Self {
+        let file_path = std::env::temp_dir().join(format!("rsapar_schema_{}_{}.xml", name, std::process::id()));
+        std::fs::write(&file_path, xml).unwrap();
+        Self(file_path)
+    }
+
+    pub(crate) fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestSchemaFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
     }
 }
 
@@ -532,4 +1574,351 @@ mod tests {
         let schema: Schema = Schema::new("./example/fixedwidth_schema.xml").expect("Failed to load schema");
         assert!(schema.fixedwidthschema.is_some());
     }
+
+    #[test]
+    fn test_cell_constraints() {
+        let format = |ctype: &str, pattern: &str| {
+            Some(Format {
+                ctype: ctype.to_string(),
+                pattern: pattern.to_string(),
+                regex_pattern: None,
+                ..Default::default()
+            })
+        };
+        let cell = |length: usize, format: Option<Format>| Cell {
+            name: "Foo".to_string(),
+            length,
+            end: length,
+            format,
+            padcharacter: " ".to_string(),
+            ..Default::default()
+        };
+        let error_code = |cell: &Cell, line_text: &str| match Schema::validate_cell(cell, line_text) {
+            Ok(_) => "ok".to_string(),
+            Err(message) => message[..9].to_string(),
+        };
+
+        let mandatory = Cell { mandatory: true, ..cell(5, None) };
+        assert_eq!(error_code(&mandatory, "     "), "[err:014]");
+        assert_eq!(error_code(&mandatory, "A    "), "ok");
+
+        let amount = Cell {
+            minvalue: Some("1.00".to_string()),
+            maxvalue: Some("100.00".to_string()),
+            ..cell(6, format("number", "##0.00"))
+        };
+        assert!(Schema::check_cell_value_range(&amount).is_ok());
+        assert_eq!(error_code(&amount, "  0.50"), "[err:015]");
+        assert_eq!(error_code(&amount, "100.50"), "[err:016]");
+        assert_eq!(error_code(&amount, "100.00"), "ok");
+
+        let date = Cell {
+            minvalue: Some("20240101".to_string()),
+            maxvalue: Some("20241231".to_string()),
+            ..cell(8, format("date", "%Y%m%d"))
+        };
+        assert_eq!(error_code(&date, "20231231"), "[err:015]");
+        assert_eq!(error_code(&date, "20250101"), "[err:016]");
+        assert_eq!(error_code(&date, "20240524"), "ok");
+
+        let implied = Cell {
+            minvalue: Some("-10000".to_string()),
+            maxvalue: Some("10000".to_string()),
+            ..cell(6, format("implieddecimal", "2"))
+        };
+        assert!(Schema::check_cell_value_range(&implied).is_ok());
+        assert_eq!(error_code(&implied, "001000"), "ok");
+        assert_eq!(error_code(&implied, "01000J"), "[err:015]");
+        assert_eq!(error_code(&implied, "01.000"), "[err:028]");
+        let implied_format = implied.format.as_ref().unwrap();
+        assert_eq!(implied_format.parse_typed("01234E"), Some(CellValue::Number(123.45)));
+        assert_eq!(implied_format.parse_typed("01234N"), Some(CellValue::Number(-123.45)));
+        assert_eq!(implied_format.parse_typed("-12345"), Some(CellValue::Number(-123.45)));
+        assert_eq!(implied_format.parse_typed("12345+"), Some(CellValue::Number(123.45)));
+        assert_eq!(implied_format.parse_typed("-"), None);
+
+        let currency = Cell { values: vec!["USD".to_string(), "EUR".to_string()], ..cell(3, None) };
+        assert_eq!(error_code(&currency, "GBP"), "[err:017]");
+        assert_eq!(error_code(&currency, "EUR"), "ok");
+
+        let name = Cell { minlength: 2, maxlength: 4, ..cell(6, None) };
+        assert_eq!(error_code(&name, "A     "), "[err:018]");
+        assert_eq!(error_code(&name, "ABCDE "), "[err:019]");
+        assert_eq!(error_code(&name, "ABCD  "), "ok");
+        // Empty values are only checked by "mandatory"
+        assert_eq!(error_code(&name, "      "), "ok");
+
+        // The bounds need a number or date format
+        assert!(Schema::check_cell_value_range(&Cell { minvalue: Some("1".to_string()), ..cell(1, None) }).is_err());
+        assert!(Schema::check_cell_value_range(&Cell {
+            maxvalue: Some("2024-12-31".to_string()),
+            ..cell(8, format("date", "%Y%m%d"))
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_empty_cells() {
+        let date_cell = |emptypattern: Option<&str>, default: Option<&str>| Cell {
+            name: "Date".to_string(),
+            length: 8,
+            end: 8,
+            format: Some(Format {
+                ctype: "date".to_string(),
+                pattern: "%Y%m%d".to_string(),
+                regex_pattern: None,
+                ..Default::default()
+            }),
+            padcharacter: " ".to_string(),
+            emptypattern: emptypattern.map(str::to_owned),
+            emptypattern_regex: emptypattern.map(|pattern| regex::Regex::new(&format!("^(?:{})$", pattern)).unwrap()),
+            default: default.map(str::to_owned),
+            ..Default::default()
+        };
+
+        // Empty cells are allowed with an "emptypattern" or a "default", yielding "" or the default value
+        let cell = date_cell(Some("0+"), None);
+        assert_eq!(Schema::validate_cell(&cell, "        "), Ok(""));
+        assert_eq!(Schema::validate_cell(&cell, "00000000"), Ok(""));
+        assert_eq!(cell.parse_value(""), Ok(None));
+        assert_eq!(
+            cell.parse_value("20240524"),
+            Ok(Some(CellValue::Date(NaiveDate::from_ymd_opt(2024, 5, 24).unwrap())))
+        );
+        assert!(cell.parse_value("2024").is_err());
+
+        let cell = date_cell(Some("0+"), Some("19700101"));
+        assert_eq!(Schema::validate_cell(&cell, "00000000"), Ok("19700101"));
+        assert_eq!(Schema::validate_cell(&cell, "        "), Ok("19700101"));
+        assert_eq!(
+            cell.parse_value("00000000"),
+            Ok(Some(CellValue::Date(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())))
+        );
+
+        // Or rejected
+        let cell = Cell { mandatory: true, ..date_cell(Some("0+"), None) };
+        assert!(Schema::validate_cell(&cell, "00000000").unwrap_err().starts_with("[err:014]"));
+
+        // Without "emptypattern" nor "default", an empty value must match the format
+        let cell = date_cell(None, None);
+        assert!(Schema::validate_cell(&cell, "        ").unwrap_err().starts_with("[err:004]"));
+        let schema = Schema::new("./example/fixedwidth_schema.xml").unwrap();
+        let error = |line: &str| schema.validate_line(2, line.to_string()).unwrap_err().message;
+        assert!(error("000000000002.00              ").starts_with("[err:005]|email|"));
+        assert!(error("0000           jhl01@6u24.com").starts_with("[err:007]|Amount|"));
+    }
+
+    #[test]
+    fn test_typed_value() {
+        let schema = Schema::new("./example/fixedwidth_schema.xml").unwrap();
+        let line = schema.validate_line(2, "000000000002.00jhl01@6u24.com".to_string()).unwrap();
+        assert_eq!(schema.get_typed_value(&line, "Amount"), Ok(Some(CellValue::Number(2.0))));
+        assert_eq!(schema.get_typed_value(&line, "UserID"), Ok(Some(CellValue::String("0000".to_string()))));
+        assert!(schema.get_typed_value(&line, "Unknown").is_err());
+    }
+
+    #[test]
+    fn test_typed_formats() {
+        let schema = Schema::new("./example/fixedwidth_schema_types.xml").unwrap();
+        let line = schema.validate_line(1, "y   42  -1.5e312300020240524123000OPEN".to_string()).unwrap();
+        let typed_value = |cell_name: &str| schema.get_typed_value(&line, cell_name).unwrap().unwrap();
+        assert_eq!(typed_value("Active"), CellValue::Boolean(true));
+        assert_eq!(typed_value("Count"), CellValue::Integer(42));
+        assert_eq!(typed_value("Rate"), CellValue::Float(-1500.0));
+        assert_eq!(typed_value("Time"), CellValue::Time(NaiveTime::from_hms_opt(12, 30, 0).unwrap()));
+        let stamp = FixedOffset::east_opt(2 * 3600).unwrap().with_ymd_and_hms(2024, 5, 24, 12, 30, 0).unwrap();
+        assert_eq!(typed_value("Stamp"), CellValue::ZonedDateTime(stamp));
+        assert_eq!(typed_value("Status"), CellValue::Enum("OPEN".to_string()));
+
+        // The Java pattern of the datetime is translated when the schema is loaded
+        let stamp_format = schema.fixedwidthschema.as_ref().unwrap().lines[0].cell[4].format.as_ref().unwrap();
+        assert_eq!(stamp_format.pattern, "%Y%m%d%H%M%S");
+
+        let error_code = |line_text: &str| match schema.validate_line(1, line_text.to_string()) {
+            Ok(_) => "ok".to_string(),
+            Err(err) => err.message[..9].to_string(),
+        };
+        assert_eq!(error_code("N    0       000000020240101000000DONE"), "ok");
+        assert_eq!(error_code("X    0       000000020240101000000DONE"), "[err:021]");
+        assert_eq!(error_code("N  4.2       000000020240101000000DONE"), "[err:022]");
+        assert_eq!(error_code("N20000       000000020240101000000DONE"), "[err:016]");
+        assert_eq!(error_code("N    0     inf00000020240101000000DONE"), "[err:023]");
+        assert_eq!(error_code("N    0       025000020240101000000DONE"), "[err:024]");
+        assert_eq!(error_code("N    0       000000020241301000000DONE"), "[err:025]");
+        assert_eq!(error_code("N    0       000000020240101000000NONE"), "[err:026]");
+
+        // Unknown format types are rejected when the schema is loaded
+        let xml = std::fs::read_to_string("./example/fixedwidth_schema_types.xml").unwrap();
+        let result = load_schema("unknown_format", &xml.replace("type=\"float\"", "type=\"money\""));
+        assert_eq!(result.unwrap_err().to_string(), "line 12, column 9: Unknown format type: money");
+    }
+
+    #[test]
+    fn test_locale() {
+        let format = |language: &str, country: &str| Format {
+            ctype: "number".to_string(),
+            locale: Some(Locale::new(language, country).unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(format("de", "DE").normalize_number("1.234,5"), "1,234.5");
+        assert_eq!(format("fr", "FR").normalize_number("1\u{a0}234,5"), "1,234.5");
+        assert_eq!(format("sv", "SE").normalize_number("1 234,5"), "1,234.5");
+        assert_eq!(format("de", "CH").normalize_number("1'234.5"), "1,234.5");
+        assert_eq!(format("en", "US").normalize_number("1,234.5"), "1,234.5");
+        assert_eq!(format("de", "DE").parse_typed("1.234,5"), Some(CellValue::Number(1234.5)));
+        assert_eq!(Locale::new("xx", "").unwrap_err().to_string(), "Unsupported locale: xx_");
+    }
+
+    #[test]
+    fn test_line_conditions() {
+        let xml = r#"<schema>
+  <fixedwidthschema>
+    <line linetype="Sale">
+      <cell name="Type" length="1"><linecondition><match type="string" pattern="S"/></linecondition></cell>
+      <cell name="Channel" length="4"><linecondition><match pattern="WEB|SHOP"/></linecondition></cell>
+    </line>
+  </fixedwidthschema>
+</schema>"#;
+        let schema = load_schema("line_conditions", xml).unwrap();
+        // All the conditions of a line must be met, the regex matches the trimmed value
+        assert!(schema.find_line("SWEB ").is_some());
+        assert!(schema.find_line("SSHOP").is_some());
+        assert!(schema.find_line("SMAIL").is_none());
+        assert!(schema.find_line("XWEB ").is_none());
+
+        let result = load_schema("line_condition_type", &xml.replace("type=\"string\"", "type=\"number\""));
+        assert_eq!(result.unwrap_err().to_string(), "line 4, column 51: Unknown linecondition type: number");
+    }
+
+    /// Load a schema from a temporary file
+    fn load_schema(name: &str, xml: &str) -> Result<Schema, Error> {
+        Schema::new(TestSchemaFile::new(name, xml).path())
+    }
+
+    #[test]
+    fn test_cell_positions() {
+        let schema = Schema::new("./example/fixedwidth_schema_redefines.xml").unwrap();
+        let positions: Vec<(&str, usize, usize)> = schema.fixedwidthschema.as_ref().unwrap().lines[0]
+            .cell
+            .iter()
+            .map(|cell| (cell.name.as_str(), cell.start, cell.end))
+            .collect();
+        assert_eq!(
+            positions,
+            [("Code", 0, 4), ("Date", 4, 12), ("Year", 4, 8), ("Month", 8, 10), ("Day", 10, 12), ("Amount", 12, 18)]
+        );
+        let line = schema.validate_line(1, "A00120240524001.50".to_string()).unwrap();
+        assert_eq!(line.cell_values["Date"], "20240524");
+        assert_eq!(line.cell_values["Month"], "05");
+        assert_eq!(line.cell_values["Amount"], "001.50");
+
+        let xml = std::fs::read_to_string("./example/fixedwidth_schema_redefines.xml").unwrap();
+        let result = load_schema("overlap", &xml.replace(" allowoverlaps=\"true\"", ""));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "line 10, column 7: Overlapping cell Year of line Record at position 5, set allowoverlaps=\"true\" to allow it"
+        );
+        let result = load_schema("gap", &xml.replace("start=\"13\"", "start=\"15\""));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "line 13, column 7: Gap before cell Amount of line Record at position 13, add a filler cell"
+        );
+        assert!(load_schema("start", &xml.replace("start=\"13\"", "start=\"0\"")).is_err());
+    }
+
+    #[test]
+    fn test_filler_cells() {
+        let schema = Schema::new("./example/fixedwidth_schema_filler.xml").unwrap();
+        let line = schema.validate_line(1, "A001    001.50note  ".to_string()).unwrap();
+        let cell_names: Vec<&str> = line.cell_values.keys().map(String::as_str).collect();
+        assert_eq!(cell_names, ["Code", "Amount"]);
+
+        let err = schema.validate_line(1, "A001 X  001.50note  ".to_string()).unwrap_err();
+        assert_eq!(err.message, "[err:027]|filler|[4]-[8]|the filler is not empty");
+        // The cells with ignoreread are still validated
+        assert!(schema.validate_line(1, "A001    001.50note  X".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_lint() {
+        for entry in std::fs::read_dir("./example").unwrap() {
+            let file_path = entry.unwrap().path();
+            let file_name = file_path.file_name().unwrap().to_str().unwrap();
+            if file_name.starts_with("fixedwidth_schema") && file_name.ends_with(".xml") {
+                assert!(Schema::new_strict(file_path.to_str().unwrap()).is_ok(), "{}", file_name);
+            }
+        }
+
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<schema>
+  <fixedwidthschema lineseparator="\n">
+    <line linetype="Header" maxlength="10">
+      <cell name="Code" length="abc"/>
+      <cell name="Code" length="4"/>
+      <format type="string" pattern=".*"/>
+    </line>
+    <line linetype="Body">
+      <cell name="Text" length="4"/>
+    </line>
+  </fixedwidthschema>
+</schema>"#;
+        let schema = load_schema("lint", xml).unwrap();
+        let issues: Vec<String> = schema.lint().iter().map(LintIssue::to_string).collect();
+        assert_eq!(
+            issues,
+            [
+                "line 5, column 7: Invalid length=\"abc\", expected a number",
+                "line 6, column 7: Duplicate cell Code in line Header",
+                "line 7, column 7: <format> outside a cell is ignored",
+                "line 4, column 5: The cells of line Header end at 4 but the maxlength is 10",
+                "line 9, column 5: Line Body is never matched, there is more than one line without conditions: Header, Body",
+            ]
+        );
+        let result = Schema::new_strict(TestSchemaFile::new("lint", xml).path());
+        assert!(result.unwrap_err().to_string().ends_with(&issues.join("\n")));
+    }
+
+    #[test]
+    fn test_occurs() {
+        assert_eq!(Occurs::parse("1").unwrap(), Occurs { min: 1, max: Some(1) });
+        assert_eq!(Occurs::parse("*").unwrap(), Occurs { min: 0, max: None });
+        assert_eq!(Occurs::parse("0..1").unwrap(), Occurs { min: 0, max: Some(1) });
+        assert_eq!(Occurs::parse("1..*").unwrap(), Occurs { min: 1, max: None });
+        assert!(Occurs::parse("2..1").is_err());
+        assert!(Occurs::parse("0").is_err());
+        assert!(Occurs::parse("x").is_err());
+        for occurs in ["1", "*", "0..1", "1..*", "2..5"] {
+            assert_eq!(Occurs::parse(occurs).unwrap().to_string(), occurs);
+        }
+    }
+
+    #[test]
+    fn test_control_total_rule() {
+        let control_total = ControlTotal::parse("Footer.TotalAmount == sum(Body.Amount)").unwrap();
+        assert_eq!((control_total.linetype.as_str(), control_total.cell.as_str()), ("Footer", "TotalAmount"));
+        assert_eq!(
+            control_total.aggregate,
+            Aggregate::Sum { linetype: "Body".to_string(), cell: "Amount".to_string() }
+        );
+        let control_total = ControlTotal::parse("Footer.Count==count( Body )").unwrap();
+        assert_eq!(control_total.aggregate, Aggregate::Count { linetype: "Body".to_string() });
+        assert!(ControlTotal::parse("Footer.Count == avg(Body.Amount)").is_err());
+        assert!(ControlTotal::parse("Footer.Count == count(Body.Amount)").is_err());
+        assert!(ControlTotal::parse("Footer == count(Body)").is_err());
+    }
+
+    #[test]
+    fn test_groups() {
+        let schema = Schema::new("./example/fixedwidth_schema_groups.xml").expect("Failed to load schema");
+        let groups = schema.get_groups();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].name, "Batch");
+        assert_eq!(groups[0].occurs, Occurs { min: 1, max: None });
+        let names: Vec<&str> = groups[0].items.iter().map(GroupItem::name).collect();
+        assert_eq!(names, ["BatchHeader", "Entry", "BatchTrailer"]);
+        match &groups[0].items[1] {
+            GroupItem::Group(entry) => assert_eq!(entry.items[1].occurs(), Occurs { min: 0, max: Some(1) }),
+            GroupItem::Line(_) => panic!("Entry should be a nested group"),
+        }
+    }
 }
@@ -0,0 +1,392 @@
+//! Differences between two versions of a schema, see `Schema::diff`.
+
+use crate::{Cell, Format, GroupItem, Line, Schema};
+
+/// Kind of a `SchemaChange`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    FileChanged, // Type of schema or attribute of the file, e.g. "lineseparator" or "recordlength"
+    LineAdded,
+    LineRemoved,
+    LineRenamed, // Same line conditions with another line type
+    LineConditionChanged,
+    LineLengthChanged,
+    CellAdded,
+    CellRemoved,
+    CellMoved,
+    CellLengthChanged,
+    FormatChanged,
+    ConstraintChanged, // mandatory, minvalue, maxvalue, values, minlength, maxlength, emptypattern or default
+    IgnoreReadChanged, // The cell is emitted in the cell values or not
+    ControlTotalAdded,
+    ControlTotalRemoved,
+}
+
+/// A difference between two schemas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaChange {
+    pub kind: ChangeKind,
+    pub linetype: String,     // Empty for the changes of the file
+    pub cell: Option<String>, // The changed cell, None for the changes of lines
+    pub description: String,
+    pub compatible: bool, // The files valid for the old schema are valid for the new one, with the same values
+}
+
+/// Differences from an old schema to a new one, see `Schema::diff`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    pub changes: Vec<SchemaChange>,
+}
+
+impl SchemaDiff {
+    /// Check if the files valid for the old schema are valid for the new one, with the same values
+    pub fn is_compatible(&self) -> bool {
+        self.changes.iter().all(|change| change.compatible)
+    }
+
+    /// The changes that break the files or the readers of the old schema
+    pub fn incompatible_changes(&self) -> impl Iterator<Item = &SchemaChange> {
+        self.changes.iter().filter(|change| !change.compatible)
+    }
+
+    fn push(&mut self, kind: ChangeKind, line: &str, cell: Option<&str>, description: String, compatible: bool) {
+        let (linetype, cell) = (line.to_owned(), cell.map(str::to_owned));
+        self.changes.push(SchemaChange { kind, linetype, cell, description, compatible });
+    }
+}
+
+/// Format as "Body.Amount: length 11 -> 12 (incompatible)"
+impl std::fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.linetype.as_str(), &self.cell) {
+            ("", _) => write!(f, "File: ")?,
+            (linetype, Some(cell)) => write!(f, "{}.{}: ", linetype, cell)?,
+            (linetype, None) => write!(f, "{}: ", linetype)?,
+        }
+        let compatibility = if self.compatible { "compatible" } else { "incompatible" };
+        write!(f, "{} ({})", self.description, compatibility)
+    }
+}
+
+/// Cells with line conditions: (start, end, type, pattern)
+fn line_conditions(line: &Line) -> Vec<(usize, usize, Option<&str>, Option<&str>)> {
+    line.cell
+        .iter()
+        .filter(|cell| cell.linecondition_pattern.is_some())
+        .map(|cell| {
+            let (ctype, pattern) = (cell.linecondition_type.as_deref(), cell.linecondition_pattern.as_deref());
+            (cell.start, cell.end, ctype, pattern)
+        })
+        .collect()
+}
+
+/// Attributes of the file and lines of a fixed width or CSV schema
+fn file_layout(schema: &Schema) -> (Vec<(&'static str, String)>, &[Line]) {
+    match (&schema.fixedwidthschema, &schema.csvschema) {
+        (Some(fixed), _) => {
+            let attributes = vec![
+                ("schema", "fixedwidthschema".to_string()),
+                ("lineseparator", fixed.lineseparator.to_owned()),
+                ("recordlength", fixed.recordlength.to_string()),
+                ("ignoreemptylines", fixed.ignoreemptylines.to_string()),
+            ];
+            (attributes, &fixed.lines)
+        }
+        (None, Some(csv)) => {
+            let attributes = vec![
+                ("schema", "csvschema".to_string()),
+                ("lineseparator", csv.lineseparator.to_owned()),
+                ("cellseparator", csv.cellseparator.to_owned()),
+                ("firstlineasschema", csv.firstlineasschema.to_string()),
+            ];
+            (attributes, &csv.lines)
+        }
+        (None, None) => (Vec::new(), &[]),
+    }
+}
+
+/// Rules of the control totals of the file and of its groups
+fn control_total_rules(schema: &Schema) -> Vec<&str> {
+    let Some(fixed) = &schema.fixedwidthschema else {
+        return Vec::new();
+    };
+    let mut rules: Vec<&str> = fixed.controltotals.iter().map(|total| total.rule.as_str()).collect();
+    let mut pending_groups: Vec<_> = fixed.groups.iter().collect();
+    while let Some(group) = pending_groups.pop() {
+        rules.extend(group.controltotals.iter().map(|total| total.rule.as_str()));
+        pending_groups.extend(group.items.iter().filter_map(|item| match item {
+            GroupItem::Group(nested_group) => Some(nested_group),
+            GroupItem::Line(_) => None,
+        }));
+    }
+    rules
+}
+
+/// Cells of a line except the fillers
+fn named_cells(line: &Line) -> Vec<&Cell> {
+    line.cell.iter().filter(|cell| !cell.name.is_empty()).collect()
+}
+
+/// Describe a format as in the schema, e.g. `number "#0.00"`
+fn describe_format(format: Option<&Format>) -> String {
+    match format {
+        Some(format) if format.pattern.is_empty() => format.ctype.to_owned(),
+        Some(format) => format!("{} \"{}\"", format.ctype, format.pattern),
+        None => "none".to_string(),
+    }
+}
+
+fn same_format(old: Option<&Format>, new: Option<&Format>) -> bool {
+    match (old, new) {
+        (Some(old), Some(new)) => {
+            old.ctype == new.ctype
+                && old.pattern == new.pattern
+                && old.true_values == new.true_values
+                && old.false_values == new.false_values
+                && old.values == new.values
+                && old.timezone == new.timezone
+                && old.locale == new.locale
+        }
+        (old, new) => old.is_none() && new.is_none(),
+    }
+}
+
+impl Schema {
+    /// Differences from this schema to a new version of it, with each change classified as backward compatible
+    /// (the files valid for this schema are valid for the new one, with the same values) or not.
+    ///
+    /// The lines are matched by line type, or by their line conditions when they are renamed,
+    /// and the cells by name. Unnamed cells (fillers) are compared by the positions of the other cells.
+    pub fn diff(&self, other: &Schema) -> SchemaDiff {
+        let ((old_attributes, old_lines), (new_attributes, new_lines)) = (file_layout(self), file_layout(other));
+        let mut diff = SchemaDiff::default();
+
+        for (attribute, old_value) in &old_attributes {
+            let new_value = new_attributes.iter().find(|(name, _)| name == attribute).map(|(_, value)| value);
+            if new_value == Some(old_value) {
+                continue;
+            }
+            let compatible = match (*attribute, new_value.map(String::as_str)) {
+                // The lines of the old files are still split with "auto"
+                ("lineseparator", Some("auto")) => {
+                    ["\\n", "\\r\\n", "\\r", "\n", "\r\n", "\r"].contains(&old_value.as_str())
+                }
+                // The old files have no empty lines
+                ("ignoreemptylines", Some("true")) => true,
+                _ => false,
+            };
+            let description = format!("{} {} -> {}", attribute, old_value, new_value.map_or("none", String::as_str));
+            diff.push(ChangeKind::FileChanged, "", None, description, compatible);
+        }
+
+        let mut matched_new_lines = Vec::new();
+        for old_line in old_lines {
+            let new_line = new_lines.iter().find(|new_line| new_line.linetype == old_line.linetype).or_else(|| {
+                // A line with the same conditions under another line type is renamed
+                let conditions = line_conditions(old_line);
+                new_lines.iter().find(|new_line| {
+                    !conditions.is_empty()
+                        && line_conditions(new_line) == conditions
+                        && !old_lines.iter().any(|line| line.linetype == new_line.linetype)
+                })
+            });
+            let Some(new_line) = new_line else {
+                diff.push(ChangeKind::LineRemoved, &old_line.linetype, None, "line removed".to_string(), false);
+                continue;
+            };
+            matched_new_lines.push(&new_line.linetype);
+
+            if new_line.linetype != old_line.linetype {
+                let description = format!("line renamed to {}", new_line.linetype);
+                diff.push(ChangeKind::LineRenamed, &old_line.linetype, None, description, false);
+            }
+            Self::diff_line(old_line, new_line, &mut diff);
+        }
+        for new_line in new_lines.iter().filter(|new_line| !matched_new_lines.contains(&&new_line.linetype)) {
+            // The old files have no lines of this type
+            diff.push(ChangeKind::LineAdded, &new_line.linetype, None, "line added".to_string(), true);
+        }
+
+        let (old_rules, new_rules) = (control_total_rules(self), control_total_rules(other));
+        for rule in old_rules.iter().filter(|rule| !new_rules.contains(rule)) {
+            let description = format!("control total removed: {}", rule);
+            diff.push(ChangeKind::ControlTotalRemoved, "", None, description, true);
+        }
+        for rule in new_rules.iter().filter(|rule| !old_rules.contains(rule)) {
+            let description = format!("control total added: {}", rule);
+            diff.push(ChangeKind::ControlTotalAdded, "", None, description, false);
+        }
+
+        diff
+    }
+
+    fn diff_line(old_line: &Line, new_line: &Line, diff: &mut SchemaDiff) {
+        let linetype = old_line.linetype.as_str();
+        if line_conditions(old_line) != line_conditions(new_line) {
+            diff.push(ChangeKind::LineConditionChanged, linetype, None, "line conditions changed".to_string(), false);
+        }
+        if old_line.maxlength != new_line.maxlength {
+            let description = format!("maxlength {} -> {}", old_line.maxlength, new_line.maxlength);
+            diff.push(ChangeKind::LineLengthChanged, linetype, None, description, new_line.maxlength == 0);
+        }
+
+        let (old_cells, new_cells) = (named_cells(old_line), named_cells(new_line));
+        for old_cell in &old_cells {
+            match new_cells.iter().find(|new_cell| new_cell.name == old_cell.name) {
+                Some(new_cell) => Self::diff_cell(linetype, old_cell, new_cell, diff),
+                None => {
+                    let description = format!("cell removed from {}-{}", old_cell.start + 1, old_cell.end);
+                    diff.push(ChangeKind::CellRemoved, linetype, Some(&old_cell.name), description, false);
+                }
+            }
+        }
+
+        let fillers: Vec<&Cell> = old_line.cell.iter().filter(|cell| cell.name.is_empty()).collect();
+        let old_end = old_line.cell.iter().map(|cell| cell.end).max().unwrap_or(0);
+        for new_cell in new_cells.iter().filter(|new_cell| !old_cells.iter().any(|cell| cell.name == new_cell.name)) {
+            // The old files have spaces in the fillers, and any value in the cells overlapped without format
+            let in_filler = fillers.iter().any(|filler| filler.start <= new_cell.start && new_cell.end <= filler.end);
+            let compatible =
+                !new_cell.mandatory && (in_filler || (new_cell.end <= old_end && new_cell.format.is_none()));
+            let description = format!("cell added at {}-{}", new_cell.start + 1, new_cell.end);
+            diff.push(ChangeKind::CellAdded, linetype, Some(&new_cell.name), description, compatible);
+        }
+    }
+
+    fn diff_cell(linetype: &str, old: &Cell, new: &Cell, diff: &mut SchemaDiff) {
+        let cell = Some(old.name.as_str());
+        if old.start != new.start {
+            let description = format!("moved from {} to {}", old.start + 1, new.start + 1);
+            diff.push(ChangeKind::CellMoved, linetype, cell, description, false);
+        }
+        if old.length != new.length {
+            let description = format!("length {} -> {}", old.length, new.length);
+            diff.push(ChangeKind::CellLengthChanged, linetype, cell, description, false);
+        }
+        if !same_format(old.format.as_ref(), new.format.as_ref())
+            || old.alignment != new.alignment
+            || old.padcharacter != new.padcharacter
+        {
+            let description =
+                format!("format {} -> {}", describe_format(old.format.as_ref()), describe_format(new.format.as_ref()));
+            diff.push(ChangeKind::FormatChanged, linetype, cell, description, false);
+        }
+        if old.ignoreread != new.ignoreread {
+            // The readers of the old schema miss the cells that are not emitted anymore
+            let description = format!("ignoreread {} -> {}", old.ignoreread, new.ignoreread);
+            diff.push(ChangeKind::IgnoreReadChanged, linetype, cell, description, !new.ignoreread);
+        }
+
+        // Constraints: (attribute, old value, new value, compatible)
+        let describe = |value: &Option<String>| value.as_deref().unwrap_or("none").to_owned();
+        let describe_length = |length: usize| if length == 0 { "none".to_string() } else { length.to_string() };
+        let constraints = [
+            ("mandatory", old.mandatory.to_string(), new.mandatory.to_string(), !new.mandatory),
+            ("minvalue", describe(&old.minvalue), describe(&new.minvalue), new.minvalue.is_none()),
+            ("maxvalue", describe(&old.maxvalue), describe(&new.maxvalue), new.maxvalue.is_none()),
+            (
+                "values",
+                old.values.join("|"),
+                new.values.join("|"),
+                new.values.is_empty() || (!old.values.is_empty() && old.values.iter().all(|v| new.values.contains(v))),
+            ),
+            (
+                "minlength",
+                describe_length(old.minlength),
+                describe_length(new.minlength),
+                new.minlength <= old.minlength,
+            ),
+            (
+                "maxlength",
+                describe_length(old.maxlength),
+                describe_length(new.maxlength),
+                new.maxlength == 0 || (old.maxlength > 0 && new.maxlength >= old.maxlength),
+            ),
+            ("emptypattern", describe(&old.emptypattern), describe(&new.emptypattern), false),
+            ("default", describe(&old.default), describe(&new.default), false),
+        ];
+        for (attribute, old_value, new_value, compatible) in constraints {
+            if old_value != new_value {
+                let description = format!("{} {} -> {}", attribute, old_value, new_value);
+                diff.push(ChangeKind::ConstraintChanged, linetype, cell, description, compatible);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_diff() {
+        let old = Schema::new("./example/fixedwidth_schema.xml").unwrap();
+        assert_eq!(old.diff(&old), SchemaDiff::default());
+
+        let mut new = old.clone();
+        let lines = &mut new.fixedwidthschema.as_mut().unwrap().lines;
+        lines[0].linetype = "FileHeader".to_string(); // Same condition
+        lines[1].cell[1].length = 12;
+        lines[1].cell[2].start = 16;
+        lines[1].cell[2].mandatory = true;
+        lines[1].maxlength = 0;
+        lines[2].cell[2].format.as_mut().unwrap().pattern = "##########0.00".to_string();
+        lines[2].cell[0].linecondition_pattern = Some("T".to_string());
+        lines[2].cell.remove(1);
+        lines.push(Line { linetype: "Comment".to_string(), ..Default::default() });
+
+        let diff = old.diff(&new);
+        let changes: Vec<String> = diff.changes.iter().map(SchemaChange::to_string).collect();
+        assert_eq!(
+            changes,
+            [
+                "Header: line renamed to FileHeader (incompatible)",
+                "Body: maxlength 29 -> 0 (compatible)",
+                "Body.Amount: length 11 -> 12 (incompatible)",
+                "Body.email: moved from 16 to 17 (incompatible)",
+                "Body.email: mandatory false -> true (incompatible)",
+                "Footer: line conditions changed (incompatible)",
+                "Footer.FileName: cell removed from 2-8 (incompatible)",
+                "Footer.TotalAmount: format number \"#########0.00\" -> number \"##########0.00\" (incompatible)",
+                "Comment: line added (compatible)",
+            ]
+        );
+        assert!(!diff.is_compatible());
+        assert_eq!(diff.incompatible_changes().count(), 7);
+        assert_eq!(diff.changes[0].kind, ChangeKind::LineRenamed);
+
+        // Cells added in a filler and relaxed constraints are compatible
+        let mut old = Schema::new("./example/fixedwidth_schema_filler.xml").unwrap();
+        old.fixedwidthschema.as_mut().unwrap().lines[0].cell[0].mandatory = true;
+        let mut new = old.clone();
+        let fixed_width_schema = new.fixedwidthschema.as_mut().unwrap();
+        fixed_width_schema.lineseparator = "auto".to_string();
+        fixed_width_schema.lines[0].cell[0].mandatory = false;
+        fixed_width_schema.lines[0].cell[1].name = "Branch".to_string();
+        let diff = old.diff(&new);
+        let changes: Vec<String> = diff.changes.iter().map(SchemaChange::to_string).collect();
+        assert_eq!(
+            changes,
+            [
+                "File: lineseparator \\n -> auto (compatible)",
+                "Record.Code: mandatory true -> false (compatible)",
+                "Record.Branch: cell added at 5-8 (compatible)",
+            ]
+        );
+        assert!(diff.is_compatible());
+
+        // Cells added after the end of the old lines are incompatible
+        let mut new = old.clone();
+        let record = &mut new.fixedwidthschema.as_mut().unwrap().lines[0];
+        record.cell.push(Cell { name: "Extra".to_string(), length: 2, start: 20, end: 22, ..Default::default() });
+        record.maxlength = 22;
+        assert!(!old.diff(&new).is_compatible());
+
+        // Cells not emitted anymore are incompatible, and emitted again compatible
+        let mut new = old.clone();
+        new.fixedwidthschema.as_mut().unwrap().lines[0].cell[0].ignoreread = true;
+        let changes: Vec<String> = old.diff(&new).changes.iter().map(SchemaChange::to_string).collect();
+        assert_eq!(changes, ["Record.Code: ignoreread false -> true (incompatible)"]);
+        assert!(new.diff(&old).is_compatible());
+    }
+}
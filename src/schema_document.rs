@@ -0,0 +1,507 @@
+//! JSON and YAML schemas (features "json" and "yaml").
+//!
+//! The documents have the same structure as the XML schema: the attributes of an element are fields,
+//! and its child elements are lists named in plural ("lines", "cells", "groups", "items", "controltotals").
+//! They are loaded by the same code as the XML schema, so they are checked in the same way.
+//!
+//! ```yaml
+//! fixedwidthschema:
+//!   lineseparator: '\n' # Escaped as in the XML schema
+//!   lines:
+//!     - linetype: Header
+//!       maxlength: 9
+//!       cells:
+//!         - name: RecordType
+//!           length: 1
+//!           linecondition:
+//!             match: { type: string, pattern: H }
+//!         - name: RecordDate
+//!           length: 8
+//!           format: { type: date, pattern: yyyyMMdd }
+//!   controltotals:
+//!     - rule: Footer.Count == count(Body)
+//! ```
+
+use anyhow::{anyhow, Error, Result};
+use serde::{Deserialize, Serialize};
+use xml::attribute::OwnedAttribute;
+use xml::name::OwnedName;
+use xml::namespace::Namespace;
+use xml::reader::XmlEvent;
+
+use crate::{Cell, ControlTotal, Group, GroupItem, Line, Schema};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SchemaDocument {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fixedwidthschema: Option<FixedWidthSchemaDocument>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixedWidthSchemaDocument {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lineseparator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recordlength: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ignoreemptylines: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    locale: Option<LocaleDocument>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    lines: Vec<LineDocument>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<GroupDocument>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    controltotals: Vec<ControlTotalDocument>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LocaleDocument {
+    language: String,
+    #[serde(default)]
+    country: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LineDocument {
+    linetype: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    occurs: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    maxlength: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    minlength: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    padcharacter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allowoverlaps: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cells: Vec<CellDocument>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CellDocument {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>, // Fillers have no name
+    length: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alignment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    padcharacter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mandatory: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    minvalue: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    maxvalue: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    values: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    minlength: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    maxlength: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    emptypattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ignoreread: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    linecondition: Option<LineConditionDocument>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    format: Option<FormatDocument>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LineConditionDocument {
+    #[serde(rename = "match")]
+    match_: MatchDocument,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MatchDocument {
+    #[serde(rename = "type")]
+    mtype: String,
+    pattern: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FormatDocument {
+    #[serde(rename = "type")]
+    ctype: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timezone: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GroupDocument {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    occurs: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    items: Vec<GroupItemDocument>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    controltotals: Vec<ControlTotalDocument>,
+}
+
+/// `lineref: {...}` or `group: {...}`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum GroupItemDocument {
+    Lineref(LineRefDocument),
+    Group(GroupDocument),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LineRefDocument {
+    linetype: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    occurs: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ControlTotalDocument {
+    rule: String,
+}
+
+/// The XML events of a document
+#[derive(Default)]
+struct Events(Vec<XmlEvent>);
+
+impl Events {
+    /// Start an element with the attributes that are set
+    fn start(&mut self, name: &str, attributes: &[(&str, Option<String>)]) {
+        let attributes = attributes
+            .iter()
+            .filter_map(|(name, value)| Some(OwnedAttribute::new(OwnedName::local(*name), value.as_ref()?)))
+            .collect();
+        self.0.push(XmlEvent::StartElement { name: OwnedName::local(name), attributes, namespace: Namespace::empty() });
+    }
+
+    fn end(&mut self, name: &str) {
+        self.0.push(XmlEvent::EndElement { name: OwnedName::local(name) });
+    }
+
+    fn control_totals(&mut self, control_totals: &[ControlTotalDocument]) {
+        for control_total in control_totals {
+            self.start("controltotal", &[("rule", Some(control_total.rule.to_owned()))]);
+            self.end("controltotal");
+        }
+    }
+
+    fn group(&mut self, group: &GroupDocument) {
+        self.start("group", &[("name", Some(group.name.to_owned())), ("occurs", group.occurs.to_owned())]);
+        for item in &group.items {
+            match item {
+                GroupItemDocument::Lineref(line_ref) => {
+                    let attributes =
+                        [("linetype", Some(line_ref.linetype.to_owned())), ("occurs", line_ref.occurs.to_owned())];
+                    self.start("lineref", &attributes);
+                    self.end("lineref");
+                }
+                GroupItemDocument::Group(nested_group) => self.group(nested_group),
+            }
+        }
+        self.control_totals(&group.controltotals);
+        self.end("group");
+    }
+}
+
+impl SchemaDocument {
+    /// The events of the equivalent XML schema
+    fn events(&self) -> Vec<XmlEvent> {
+        let to_string = |value: Option<usize>| value.map(|value| value.to_string());
+        let bool_to_string = |value: Option<bool>| value.map(|value| value.to_string());
+
+        let mut events = Events::default();
+        events.start("schema", &[]);
+        if let Some(fixed_width_schema) = &self.fixedwidthschema {
+            events.start(
+                "fixedwidthschema",
+                &[
+                    ("lineseparator", fixed_width_schema.lineseparator.to_owned()),
+                    ("recordlength", to_string(fixed_width_schema.recordlength)),
+                    ("ignoreemptylines", bool_to_string(fixed_width_schema.ignoreemptylines)),
+                ],
+            );
+            if let Some(locale) = &fixed_width_schema.locale {
+                events.start(
+                    "locale",
+                    &[("language", Some(locale.language.to_owned())), ("country", Some(locale.country.to_owned()))],
+                );
+                events.end("locale");
+            }
+            for line in &fixed_width_schema.lines {
+                events.start(
+                    "line",
+                    &[
+                        ("linetype", Some(line.linetype.to_owned())),
+                        ("occurs", line.occurs.to_owned()),
+                        ("maxlength", to_string(line.maxlength)),
+                        ("minlength", to_string(line.minlength)),
+                        ("padcharacter", line.padcharacter.to_owned()),
+                        ("allowoverlaps", bool_to_string(line.allowoverlaps)),
+                    ],
+                );
+                for cell in &line.cells {
+                    events.start(
+                        "cell",
+                        &[
+                            ("name", cell.name.to_owned()),
+                            ("length", Some(cell.length.to_string())),
+                            ("start", to_string(cell.start)),
+                            ("alignment", cell.alignment.to_owned()),
+                            ("padcharacter", cell.padcharacter.to_owned()),
+                            ("mandatory", bool_to_string(cell.mandatory)),
+                            ("minvalue", cell.minvalue.to_owned()),
+                            ("maxvalue", cell.maxvalue.to_owned()),
+                            ("values", cell.values.to_owned()),
+                            ("minlength", to_string(cell.minlength)),
+                            ("maxlength", to_string(cell.maxlength)),
+                            ("emptypattern", cell.emptypattern.to_owned()),
+                            ("default", cell.default.to_owned()),
+                            ("ignoreread", bool_to_string(cell.ignoreread)),
+                        ],
+                    );
+                    if let Some(linecondition) = &cell.linecondition {
+                        events.start("linecondition", &[]);
+                        let match_ = &linecondition.match_;
+                        events.start(
+                            "match",
+                            &[("type", Some(match_.mtype.to_owned())), ("pattern", Some(match_.pattern.to_owned()))],
+                        );
+                        events.end("match");
+                        events.end("linecondition");
+                    }
+                    if let Some(format) = &cell.format {
+                        events.start(
+                            "format",
+                            &[
+                                ("type", Some(format.ctype.to_owned())),
+                                ("pattern", format.pattern.to_owned()),
+                                ("timezone", format.timezone.to_owned()),
+                            ],
+                        );
+                        events.end("format");
+                    }
+                    events.end("cell");
+                }
+                events.end("line");
+            }
+            for group in &fixed_width_schema.groups {
+                events.group(group);
+            }
+            events.control_totals(&fixed_width_schema.controltotals);
+            events.end("fixedwidthschema");
+        }
+        events.end("schema");
+        events.0
+    }
+
+    /// The document of a loaded schema, only fixed width schemas have one
+    fn from_schema(schema: &Schema) -> Result<Self, Error> {
+        let Some(fixed_width_schema) = &schema.fixedwidthschema else {
+            return Err(anyhow!("Only fixed width schemas can be exported"));
+        };
+        let non_zero = |value: usize| (value > 0).then_some(value);
+        let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_owned());
+
+        let lines = fixed_width_schema
+            .lines
+            .iter()
+            .map(|line| {
+                let mut end_cell = 0; // End of the previous cell
+                let cells = line
+                    .cell
+                    .iter()
+                    .map(|cell| {
+                        let start = (cell.start != end_cell).then_some(cell.start + 1);
+                        end_cell = cell.end;
+                        CellDocument { start, ..cell_document(cell, line) }
+                    })
+                    .collect();
+                LineDocument {
+                    linetype: line.linetype.to_owned(),
+                    occurs: non_empty(&line.occurs),
+                    maxlength: non_zero(line.maxlength),
+                    minlength: non_zero(line.minlength),
+                    padcharacter: (line.padcharacter != " ").then(|| line.padcharacter.to_owned()),
+                    allowoverlaps: line.allowoverlaps.then_some(true),
+                    cells,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            fixedwidthschema: Some(FixedWidthSchemaDocument {
+                lineseparator: Some(fixed_width_schema.lineseparator.to_owned()),
+                recordlength: non_zero(fixed_width_schema.recordlength),
+                ignoreemptylines: fixed_width_schema.ignoreemptylines.then_some(true),
+                locale: fixed_width_schema.locale.as_ref().map(|locale| LocaleDocument {
+                    language: locale.language.to_owned(),
+                    country: locale.country.to_owned(),
+                }),
+                lines,
+                groups: fixed_width_schema.groups.iter().map(group_document).collect(),
+                controltotals: control_total_documents(&fixed_width_schema.controltotals),
+            }),
+        })
+    }
+}
+
+fn cell_document(cell: &Cell, line: &Line) -> CellDocument {
+    let non_zero = |value: usize| (value > 0).then_some(value);
+    let linecondition = match (&cell.linecondition_type, &cell.linecondition_pattern) {
+        (Some(mtype), Some(pattern)) => Some(LineConditionDocument {
+            match_: MatchDocument { mtype: mtype.to_owned(), pattern: pattern.to_owned() },
+        }),
+        _ => None,
+    };
+    let format = cell.format.as_ref().map(|format| FormatDocument {
+        ctype: format.ctype.to_owned(),
+        pattern: Some(format.pattern.to_owned()),
+        timezone: format.timezone.map(|timezone| timezone.to_string()),
+    });
+
+    CellDocument {
+        name: (!cell.name.is_empty()).then(|| cell.name.to_owned()),
+        length: cell.length,
+        start: None,
+        alignment: (!cell.alignment.is_empty()).then(|| cell.alignment.to_owned()),
+        padcharacter: (cell.padcharacter != line.padcharacter).then(|| cell.padcharacter.to_owned()),
+        mandatory: cell.mandatory.then_some(true),
+        minvalue: cell.minvalue.to_owned(),
+        maxvalue: cell.maxvalue.to_owned(),
+        values: (!cell.values.is_empty()).then(|| cell.values.join("|")),
+        minlength: non_zero(cell.minlength),
+        maxlength: non_zero(cell.maxlength),
+        emptypattern: cell.emptypattern.to_owned(),
+        default: cell.default.to_owned(),
+        // Unnamed cells are fillers without the attribute
+        ignoreread: (cell.ignoreread && !cell.name.is_empty()).then_some(true),
+        linecondition,
+        format,
+    }
+}
+
+fn group_document(group: &Group) -> GroupDocument {
+    let items = group
+        .items
+        .iter()
+        .map(|item| match item {
+            GroupItem::Line(line_ref) => GroupItemDocument::Lineref(LineRefDocument {
+                linetype: line_ref.linetype.to_owned(),
+                occurs: Some(line_ref.occurs.to_string()),
+            }),
+            GroupItem::Group(nested_group) => GroupItemDocument::Group(group_document(nested_group)),
+        })
+        .collect();
+    GroupDocument {
+        name: group.name.to_owned(),
+        occurs: Some(group.occurs.to_string()),
+        items,
+        controltotals: control_total_documents(&group.controltotals),
+    }
+}
+
+fn control_total_documents(control_totals: &[ControlTotal]) -> Vec<ControlTotalDocument> {
+    control_totals.iter().map(|control_total| ControlTotalDocument { rule: control_total.rule.to_owned() }).collect()
+}
+
+impl Schema {
+    fn from_document(document: SchemaDocument) -> Result<Self, Error> {
+        Self::from_events(document.events().into_iter().map(|event| Ok((event, None))))
+    }
+
+    /// Load schema from a JSON document (feature "json")
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Self::from_document(serde_json::from_str(json)?)
+    }
+
+    /// Export the schema to a JSON document (feature "json")
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(&SchemaDocument::from_schema(self)?)?)
+    }
+
+    /// Load schema from a YAML document (feature "yaml")
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(yaml: &str) -> Result<Self, Error> {
+        Self::from_document(serde_yaml::from_str(yaml)?)
+    }
+
+    /// Export the schema to a YAML document (feature "yaml")
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String, Error> {
+        Ok(serde_yaml::to_string(&SchemaDocument::from_schema(self)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example schemas are the same after exporting them and loading them again.
+    #[test]
+    fn test_schema_document_round_trip() {
+        for file_path in [
+            "./example/fixedwidth_schema.xml",
+            "./example/fixedwidth_schema_groups.xml",
+            "./example/fixedwidth_schema_types.xml",
+            "./example/fixedwidth_schema_filler.xml",
+            "./example/fixedwidth_schema_redefines.xml",
+            "./example/jsapar/persons_schema.xml",
+            "./example/jsapar/orders_schema.xml",
+        ] {
+            let schema = Schema::new(file_path).unwrap();
+            let expected = format!("{:?}", schema.fixedwidthschema);
+            #[cfg(feature = "json")]
+            {
+                let json = schema.to_json().unwrap();
+                assert_eq!(format!("{:?}", Schema::from_json(&json).unwrap().fixedwidthschema), expected, "{}", json);
+            }
+            #[cfg(feature = "yaml")]
+            {
+                let yaml = schema.to_yaml().unwrap();
+                assert_eq!(format!("{:?}", Schema::from_yaml(&yaml).unwrap().fixedwidthschema), expected, "{}", yaml);
+            }
+        }
+
+        // Other schemas are not exported as an empty document
+        let schema = Schema { fixedwidthschema: None, csvschema: None, lint_issues: Vec::new() };
+        #[cfg(feature = "json")]
+        assert_eq!(schema.to_json().unwrap_err().to_string(), "Only fixed width schemas can be exported");
+        #[cfg(feature = "yaml")]
+        assert_eq!(schema.to_yaml().unwrap_err().to_string(), "Only fixed width schemas can be exported");
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_yaml_schema() {
+        let schema = Schema::new("./example/fixedwidth_schema.yaml").unwrap();
+        let xml_schema = Schema::new("./example/fixedwidth_schema.xml").unwrap();
+        assert_eq!(format!("{:?}", schema.fixedwidthschema), format!("{:?}", xml_schema.fixedwidthschema));
+
+        // The errors are the same as for the XML schema
+        let yaml = std::fs::read_to_string("./example/fixedwidth_schema.yaml").unwrap();
+        let result = Schema::from_yaml(&yaml.replace("type: number", "type: money"));
+        assert_eq!(result.unwrap_err().to_string(), "Unknown format type: money");
+        assert!(Schema::from_yaml("fixedwidthschema:\n  lines:\n    - linetype: A\n      unknown: 1\n").is_err());
+    }
+}
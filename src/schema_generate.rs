@@ -0,0 +1,1048 @@
+//! Synthetic data files generated from a schema, see `Schema::generate`.
+
+use anyhow::{anyhow, Error, Result};
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta, TimeZone};
+use regex_syntax::hir::{Class, Hir, HirKind};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io::Write;
+use std::ops::Range;
+
+use crate::decimal_format::Decimal;
+use crate::parser::LineSplit;
+use crate::{
+    Aggregate, Cell, CellValue, ControlTotal, FixedWidthSchema, Format, Group, GroupItem, Line, Locale, Occurs, Schema,
+};
+
+/// Attempts to generate a valid line, or to inject a fault in a line
+const MAX_ATTEMPTS: usize = 100;
+/// Attempts to generate a string of the length of its cell from a regex
+const STRING_ATTEMPTS: usize = 10;
+/// Digits of the integer part of the summed values, so the control totals fit in their cells
+const SUMMED_INTEGER_DIGITS: u32 = 2;
+/// Characters of the values injected for `FaultKind::Format`
+const FAULT_CHARACTERS: &[u8] = b"#?!*";
+const ALPHANUMERIC: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Kind of error injected in a line by `Schema::generate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaultKind {
+    UnknownLineType, // The line conditions of no line type are met
+    LineLength,      // The line is one character longer or shorter than its "maxlength"
+    Format,          // A value not matching the format of its cell
+    Mandatory,       // A mandatory cell left empty
+    ValueRange,      // A value below the "minvalue" or above the "maxvalue" of its cell
+    AllowedValues,   // A value that is not one of the "values" of its cell
+    ValueLength,     // A value shorter than the "minlength" or longer than the "maxlength" of its cell
+}
+
+impl FaultKind {
+    /// Codes of the errors reported by the parser for the lines with this fault
+    pub fn error_codes(&self) -> &'static [&'static str] {
+        match self {
+            FaultKind::UnknownLineType => &["001"],
+            FaultKind::LineLength => &["002"],
+            FaultKind::Format => &["004", "005", "006", "007", "021", "022", "023", "024", "025", "026", "028"],
+            FaultKind::Mandatory => &["014"],
+            FaultKind::ValueRange => &["015", "016"],
+            FaultKind::AllowedValues => &["017"],
+            FaultKind::ValueLength => &["018", "019"],
+        }
+    }
+}
+
+/// Options of `Schema::generate`
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    pub seed: u64,                       // The same schema, options and seed generate the same file
+    pub repeat: usize, // Occurrences of the line types and groups without an upper bound in "occurs" (default 10)
+    pub occurs: HashMap<String, usize>, // Occurrences of line types and groups by name, instead of their "occurs"
+    pub faults: Vec<(FaultKind, usize)>, // Number of lines with each kind of fault
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self { seed: 0, repeat: 10, occurs: HashMap::new(), faults: Vec::new() }
+    }
+}
+
+/// A fault injected in a line by `Schema::generate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InjectedFault {
+    pub line_number: usize,
+    pub kind: FaultKind,
+    pub message: String, // Error reported by the parser for the line
+}
+
+/// Lines written by `Schema::generate`
+#[derive(Debug, Clone, Default)]
+pub struct GenerateReport {
+    pub lines: usize,
+    pub faults: Vec<InjectedFault>, // By line number
+}
+
+/// Values requested from the generators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bounds {
+    Valid,
+    Summed, // Valid and below 100, for the cells summed by a control total
+    BelowMin,
+    AboveMax,
+}
+
+impl Bounds {
+    fn is_valid(self) -> bool {
+        matches!(self, Bounds::Valid | Bounds::Summed)
+    }
+}
+
+/// Pseudo-random numbers (SplitMix64), the same for the same seed
+#[derive(Debug)]
+struct Random(u64);
+
+impl Random {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Number between low and high, both included, or low if high is lower
+    fn between(&mut self, low: i128, high: i128) -> i128 {
+        if high <= low {
+            return low;
+        }
+        let span = high.abs_diff(low).saturating_add(1);
+        let random = (u128::from(self.next_u64()) << 64) | u128::from(self.next_u64());
+        low + (random % span) as i128
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        match items.len() {
+            0 => None,
+            len => items.get(self.between(0, len as i128 - 1) as usize),
+        }
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for index in (1..items.len()).rev() {
+            items.swap(index, self.between(0, index as i128) as usize);
+        }
+    }
+}
+
+/// A DecimalFormat pattern split at the decimal separator, see `DecimalFormat`
+#[derive(Debug)]
+struct NumberPattern {
+    integer: Vec<PatternToken>,
+    fraction: Option<Vec<PatternToken>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PatternToken {
+    Digit { required: bool }, // "0" or "#"
+    Grouping,
+    Literal(char), // Quoted characters and the other symbols
+}
+
+impl NumberPattern {
+    /// Parse the positive pattern, the negative values are written with a leading "-"
+    fn parse(pattern: &str) -> Self {
+        let mut integer = Vec::new();
+        let mut fraction: Option<Vec<PatternToken>> = None;
+        let mut in_quotes = false;
+        for c in pattern.chars() {
+            let token = match c {
+                '\'' => {
+                    in_quotes = !in_quotes;
+                    continue;
+                }
+                c if in_quotes => PatternToken::Literal(c),
+                ';' => break,
+                '.' if fraction.is_none() => {
+                    fraction = Some(Vec::new());
+                    continue;
+                }
+                '0' => PatternToken::Digit { required: true },
+                '#' => PatternToken::Digit { required: false },
+                ',' => PatternToken::Grouping,
+                '¤' => PatternToken::Literal('$'),
+                c => PatternToken::Literal(c),
+            };
+            fraction.as_mut().unwrap_or(&mut integer).push(token);
+        }
+        Self { integer, fraction }
+    }
+
+    fn digits(tokens: &[PatternToken]) -> u32 {
+        tokens.iter().filter(|token| matches!(token, PatternToken::Digit { .. })).count() as u32
+    }
+
+    fn decimals(&self) -> u32 {
+        self.fraction.as_deref().map_or(0, Self::digits)
+    }
+
+    /// Write a number given in units of its last decimal, e.g. 1250 is 12.50 with 2 decimals
+    /// Returns None if the integer part has more digits than the pattern
+    fn format(&self, value: i128, locale: Option<&Locale>) -> Option<String> {
+        let decimal_separator = locale.map_or('.', |locale| locale.decimal_separator);
+        // The no-break space of the grouping separator is also read as a space
+        let grouping_separator = match locale.map_or(',', |locale| locale.grouping_separator) {
+            '\u{a0}' => ' ',
+            grouping_separator => grouping_separator,
+        };
+        let (negative, integer, fraction) = split_scaled(value, self.decimals());
+
+        let mut integer_digits = integer.trim_start_matches('0').chars().rev();
+        let mut reversed_integer = Vec::new();
+        for token in self.integer.iter().rev() {
+            match token {
+                PatternToken::Digit { required } => match integer_digits.next() {
+                    Some(digit) => reversed_integer.push(digit),
+                    None if *required => reversed_integer.push('0'),
+                    None => (),
+                },
+                PatternToken::Grouping => reversed_integer.push(grouping_separator),
+                PatternToken::Literal(c) => reversed_integer.push(*c),
+            }
+        }
+        if integer_digits.next().is_some() {
+            return None;
+        }
+        let mut text: String = reversed_integer.into_iter().rev().collect();
+
+        if let Some(fraction_tokens) = &self.fraction {
+            text.push(decimal_separator);
+            let mut fraction_digits = fraction.chars();
+            for token in fraction_tokens {
+                match token {
+                    PatternToken::Digit { .. } => text.push(fraction_digits.next().unwrap_or('0')),
+                    PatternToken::Grouping => text.push(grouping_separator),
+                    PatternToken::Literal(c) => text.push(*c),
+                }
+            }
+        }
+        Some(if negative { format!("-{}", text) } else { text })
+    }
+}
+
+/// Split a number given in units of its last decimal into its sign, integer and fraction digits
+fn split_scaled(value: i128, decimals: u32) -> (bool, String, String) {
+    let digits = format!("{:0>width$}", value.unsigned_abs(), width = decimals as usize + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals as usize);
+    (value < 0, integer.to_string(), fraction.to_string())
+}
+
+/// Pad a value to the length of its cell according to its alignment
+/// Returns None if the value is longer than the cell
+fn render(cell: &Cell, value: &str) -> Option<String> {
+    let padding = cell.length.checked_sub(value.len())?;
+    let pad = cell.padcharacter.chars().next().unwrap_or(' ');
+    let pads = |count: usize| std::iter::repeat(pad).take(count).collect::<String>();
+    Some(match cell.get_alignment() {
+        "right" => format!("{}{}", pads(padding), value),
+        "center" => format!("{}{}{}", pads(padding / 2), value, pads(padding - padding / 2)),
+        _ => format!("{}{}", value, pads(padding)),
+    })
+}
+
+/// Range of the length of a trimmed value: the "minlength" and "maxlength" of the cell, at least one character
+fn length_range(cell: &Cell) -> (usize, usize) {
+    let max_length = match cell.maxlength {
+        0 => cell.length,
+        maxlength => maxlength.min(cell.length),
+    };
+    (cell.minlength.max(1).min(max_length), max_length)
+}
+
+/// A line of the generated file with the text of each of its cells
+#[derive(Debug, Clone)]
+struct GeneratedLine {
+    cells: Vec<String>,
+    text: String,
+}
+
+impl GeneratedLine {
+    /// Join the cells, except those overlapping the previous ones (redefined fields), up to the "maxlength"
+    fn new(line: &Line, cells: Vec<String>) -> Self {
+        let mut text = String::with_capacity(line.maxlength);
+        let mut end = 0;
+        for (cell, cell_text) in line.cell.iter().zip(&cells) {
+            if cell.start >= end {
+                text.push_str(cell_text);
+                end = cell.end;
+            }
+        }
+        let pad = line.padcharacter.chars().next().unwrap_or(' ');
+        while text.len() < line.maxlength {
+            text.push(pad);
+        }
+        Self { cells, text }
+    }
+}
+
+/// Lines of the file in order, with the line ranges of the groups and file checked by control totals
+type Plan<'s> = (Vec<&'s Line>, Vec<(Range<usize>, &'s [ControlTotal])>);
+
+struct Generator<'s> {
+    schema: &'s Schema,
+    binding: &'s FixedWidthSchema,
+    options: &'s GenerateOptions,
+    random: Random,
+    summed_cells: HashSet<(&'s str, &'s str)>, // Line type and cell summed by a control total
+    regexes: HashMap<String, Option<Hir>>,     // Parsed patterns of the string formats and line conditions
+}
+
+impl Schema {
+    /// Write a file that is valid for the schema, with the `faults` of the options injected in some of its lines.
+    ///
+    /// The lines not referenced by a group follow the order of the schema, and each top level group is written
+    /// where its first line type is declared. Line types and groups occur as their "occurs" allows, `repeat` times
+    /// when it has no upper bound, unless `occurs` sets their occurrences by name.
+    ///
+    /// The values follow the format, alignment, padding, line conditions and constraints of their cells.
+    /// The cells of the control totals are computed from the lines, and the summed values are kept below 100
+    /// so that the totals fit in their cells.
+    ///
+    /// Each fault is injected in a different line and reported with the error the parser gives for that line.
+    /// Faulty lines are left out of the groups and control totals by the parser, which may report further errors.
+    pub fn generate<W: Write>(&self, mut writer: W, options: &GenerateOptions) -> Result<GenerateReport, Error> {
+        let binding =
+            self.fixedwidthschema.as_ref().ok_or_else(|| anyhow!("Only fixed width schemas can be generated"))?;
+        let mut generator = Generator::new(self, binding, options);
+
+        let (plan, mut scopes) = generator.plan();
+        scopes.push((0..plan.len(), binding.controltotals.as_slice()));
+
+        let mut lines = plan.iter().map(|line| generator.generate_line(line)).collect::<Result<Vec<_>, Error>>()?;
+        // The nested groups come before the groups containing them, and the file is last
+        for (range, control_totals) in scopes {
+            generator.set_control_totals(&plan, &mut lines, range, control_totals)?;
+        }
+        let faults = generator.inject_faults(&plan, &mut lines)?;
+
+        let line_separator = match binding.lineseparator.as_str() {
+            "auto" => b"\n".to_vec(),
+            lineseparator => LineSplit::unescape(lineseparator),
+        };
+        for (index, line) in lines.iter().enumerate() {
+            if index > 0 {
+                writer.write_all(&line_separator)?;
+            }
+            writer.write_all(line.text.as_bytes())?;
+        }
+        writer.flush()?;
+
+        Ok(GenerateReport { lines: lines.len(), faults })
+    }
+}
+
+impl<'s> Generator<'s> {
+    fn new(schema: &'s Schema, binding: &'s FixedWidthSchema, options: &'s GenerateOptions) -> Self {
+        let mut control_totals: Vec<&ControlTotal> = binding.controltotals.iter().collect();
+        let mut pending_groups: Vec<&Group> = binding.groups.iter().collect();
+        while let Some(group) = pending_groups.pop() {
+            control_totals.extend(&group.controltotals);
+            pending_groups.extend(group.items.iter().filter_map(|item| match item {
+                GroupItem::Group(nested_group) => Some(nested_group),
+                GroupItem::Line(_) => None,
+            }));
+        }
+        let summed_cells = control_totals
+            .into_iter()
+            .filter_map(|control_total| match &control_total.aggregate {
+                Aggregate::Sum { linetype, cell } => Some((linetype.as_str(), cell.as_str())),
+                Aggregate::Count { .. } => None,
+            })
+            .collect();
+
+        Self { schema, binding, options, random: Random(options.seed), summed_cells, regexes: HashMap::new() }
+    }
+
+    /// Occurrences of a line type or group
+    fn occurrences(&mut self, name: &str, occurs: Occurs) -> usize {
+        if let Some(&occurrences) = self.options.occurs.get(name) {
+            return occurrences;
+        }
+        match occurs.max {
+            Some(max) => self.random.between(occurs.min as i128, max as i128) as usize,
+            None => occurs.min.max(self.options.repeat),
+        }
+    }
+
+    fn plan(&mut self) -> Plan<'s> {
+        let binding = self.binding;
+        let mut plan = (Vec::new(), Vec::new());
+        let mut planned_groups = HashSet::new();
+        for line in &binding.lines {
+            match binding.groups.iter().position(|group| Self::references(group, &line.linetype)) {
+                Some(group_index) => {
+                    if planned_groups.insert(group_index) {
+                        self.plan_group(&binding.groups[group_index], &mut plan);
+                    }
+                }
+                None => {
+                    // Lines without "occurs" occur any number of times
+                    let occurs = Occurs::parse(&line.occurs).unwrap_or(Occurs { min: 0, max: None });
+                    for _ in 0..self.occurrences(&line.linetype, occurs) {
+                        plan.0.push(line);
+                    }
+                }
+            }
+        }
+        plan
+    }
+
+    fn plan_group(&mut self, group: &'s Group, plan: &mut Plan<'s>) {
+        for _ in 0..self.occurrences(&group.name, group.occurs) {
+            let start = plan.0.len();
+            for item in &group.items {
+                match item {
+                    GroupItem::Line(line_ref) => {
+                        // The line references are checked when the schema is loaded
+                        let Some(line) = self.binding.lines.iter().find(|line| line.linetype == line_ref.linetype)
+                        else {
+                            continue;
+                        };
+                        for _ in 0..self.occurrences(&line_ref.linetype, line_ref.occurs) {
+                            plan.0.push(line);
+                        }
+                    }
+                    GroupItem::Group(nested_group) => self.plan_group(nested_group, plan),
+                }
+            }
+            if !group.controltotals.is_empty() {
+                plan.1.push((start..plan.0.len(), group.controltotals.as_slice()));
+            }
+        }
+    }
+
+    /// Check if a group or its nested groups reference a line type
+    fn references(group: &Group, linetype: &str) -> bool {
+        group.items.iter().any(|item| match item {
+            GroupItem::Line(line_ref) => line_ref.linetype == linetype,
+            GroupItem::Group(nested_group) => Self::references(nested_group, linetype),
+        })
+    }
+
+    /// Generate the cells of a line until the line is valid and resolves to its line type
+    fn generate_line(&mut self, line: &Line) -> Result<GeneratedLine, Error> {
+        let mut last_error = String::from("a value does not fit in its cell");
+        for _ in 0..MAX_ATTEMPTS {
+            let cells = line
+                .cell
+                .iter()
+                .map(|cell| {
+                    let bounds = match self.summed_cells.contains(&(line.linetype.as_str(), cell.name.as_str())) {
+                        true => Bounds::Summed,
+                        false => Bounds::Valid,
+                    };
+                    self.generate_value(cell, bounds).and_then(|value| render(cell, &value))
+                })
+                .collect::<Option<Vec<_>>>();
+            let Some(cells) = cells else {
+                continue;
+            };
+
+            let generated_line = GeneratedLine::new(line, cells);
+            match self.schema.validate_line_ref(0, &generated_line.text) {
+                Ok(processed_line) if processed_line.linetype == line.linetype => return Ok(generated_line),
+                Ok(processed_line) => last_error = format!("the line matches line type {}", processed_line.linetype),
+                Err(error) => last_error = error.message,
+            }
+        }
+        Err(anyhow!("Cannot generate a valid line {}: {}", line.linetype, last_error))
+    }
+
+    /// Generate the trimmed value of a cell
+    /// Returns None if the cell has no value for the bounds, e.g. `Bounds::BelowMin` without "minvalue"
+    fn generate_value(&mut self, cell: &Cell, bounds: Bounds) -> Option<String> {
+        if bounds.is_valid() {
+            match (&cell.linecondition_pattern, &cell.linecondition_regex) {
+                (Some(pattern), None) => return Some(pattern.to_owned()),
+                (Some(pattern), Some(re)) => {
+                    return self.generate_matching(pattern, 1, cell.length).filter(|value| re.is_match(value))
+                }
+                _ => (),
+            }
+            if let Some(value) = self.random.pick(&cell.values) {
+                return Some(value.to_owned());
+            }
+        }
+
+        let Some(format) = &cell.format else {
+            // Fillers are empty, the other cells without format are read as they are
+            return match bounds {
+                _ if cell.name.is_empty() => Some(String::new()),
+                Bounds::Valid => {
+                    let (min_length, max_length) = length_range(cell);
+                    Some(self.alphanumeric(min_length, max_length))
+                }
+                Bounds::Summed => Some(self.random.between(0, 10i128.pow(SUMMED_INTEGER_DIGITS) - 1).to_string()),
+                Bounds::BelowMin | Bounds::AboveMax => None,
+            };
+        };
+
+        let decimal_separator = format.locale.as_ref().map_or('.', |locale| locale.decimal_separator);
+        let max_digits = (cell.length as u32).min(18);
+        match format.ctype.as_str() {
+            "string" if bounds.is_valid() => {
+                let (min_length, max_length) = length_range(cell);
+                self.generate_matching(&format.pattern, min_length, max_length)
+            }
+            "number" => {
+                let pattern = NumberPattern::parse(&format.pattern);
+                let decimals = pattern.decimals();
+                let digits = (NumberPattern::digits(&pattern.integer) + decimals).min(18);
+                let value = self.generate_scaled(cell, format, bounds, decimals, digits)?;
+                pattern.format(value, format.locale.as_ref())
+            }
+            "integer" => self.generate_scaled(cell, format, bounds, 0, max_digits).map(|value| value.to_string()),
+            "float" => {
+                let value = self.generate_scaled(cell, format, bounds, 2, max_digits.saturating_sub(1).max(1))?;
+                let (negative, integer, fraction) = split_scaled(value, 2);
+                Some(format!("{}{}{}{}", if negative { "-" } else { "" }, integer, decimal_separator, fraction))
+            }
+            "implieddecimal" => {
+                let decimals = format.pattern.parse().unwrap_or(0);
+                self.generate_scaled(cell, format, bounds, decimals, max_digits).map(|value| value.to_string())
+            }
+            "date" | "time" | "datetime" => self.generate_date(cell, format, bounds),
+            "boolean" if bounds.is_valid() => {
+                let literals = match self.random.between(0, 1) {
+                    0 => &format.false_values,
+                    _ => &format.true_values,
+                };
+                self.random.pick(literals).cloned()
+            }
+            "enum" if bounds.is_valid() => self.random.pick(&format.values).cloned(),
+            _ => None,
+        }
+    }
+
+    fn alphanumeric(&mut self, min_length: usize, max_length: usize) -> String {
+        let length = self.random.between(min_length as i128, max_length as i128) as usize;
+        (0..length).filter_map(|_| self.random.pick(ALPHANUMERIC).map(|&c| c as char)).collect()
+    }
+
+    /// Generate a number in units of its last decimal, e.g. 1250 is 12.50 with 2 decimals,
+    /// within the "minvalue" and "maxvalue" of the cell and the number of digits
+    fn generate_scaled(
+        &mut self, cell: &Cell, format: &Format, bounds: Bounds, decimals: u32, max_digits: u32,
+    ) -> Option<i128> {
+        let scale = 10f64.powi(decimals as i32);
+        let parse_bound = |bound: &String, round: fn(f64) -> f64| match format.parse_typed(bound)? {
+            CellValue::Integer(integer) => Some(integer as i128 * 10i128.pow(decimals)),
+            CellValue::Number(number) | CellValue::Float(number) => Some(round(number * scale) as i128),
+            _ => None,
+        };
+        let min = cell.minvalue.as_ref().and_then(|minvalue| parse_bound(minvalue, f64::ceil));
+        let max = cell.maxvalue.as_ref().and_then(|maxvalue| parse_bound(maxvalue, f64::floor));
+
+        let mut high = max.unwrap_or(10i128.pow(max_digits) - 1);
+        if bounds == Bounds::Summed {
+            high = high.min(10i128.pow(SUMMED_INTEGER_DIGITS + decimals) - 1);
+        }
+        let high = high.max(min.unwrap_or(i128::MIN));
+        let low = min.unwrap_or(0).min(high);
+        match bounds {
+            Bounds::Valid | Bounds::Summed => Some(self.random.between(low, high)),
+            Bounds::BelowMin => min.map(|min| min - 1),
+            Bounds::AboveMax => max.map(|max| max + 1),
+        }
+    }
+
+    /// Generate a date, time or datetime between 2000 and 2030, or within the "minvalue" and "maxvalue" of the cell
+    fn generate_date(&mut self, cell: &Cell, format: &Format, bounds: Bounds) -> Option<String> {
+        let base = NaiveDate::from_ymd_opt(2000, 1, 1)?.and_hms_opt(0, 0, 0)?;
+        let end = NaiveDate::from_ymd_opt(2030, 12, 31)?.and_hms_opt(23, 59, 59)?;
+        let timezone = format.timezone.unwrap_or(chrono::FixedOffset::east_opt(0)?);
+
+        // Dates are generated by days, times and datetimes by seconds
+        let (unit, high) = match format.ctype.as_str() {
+            "date" => (86_400, (end - base).num_days()),
+            "time" => (1, 86_399),
+            _ => (1, (end - base).num_seconds()),
+        };
+        let parse_bound = |bound: &String| {
+            let datetime: NaiveDateTime = match format.parse_typed(bound)? {
+                CellValue::Date(date) => date.and_hms_opt(0, 0, 0)?,
+                CellValue::Time(time) => base.date().and_time(time),
+                CellValue::DateTime(datetime) => datetime,
+                CellValue::ZonedDateTime(datetime) => datetime.with_timezone(&timezone).naive_local(),
+                _ => return None,
+            };
+            Some((datetime - base).num_seconds() / unit)
+        };
+        let min = cell.minvalue.as_ref().and_then(parse_bound);
+        let max = cell.maxvalue.as_ref().and_then(parse_bound);
+
+        let high = max.unwrap_or(high).max(min.unwrap_or(i64::MIN));
+        let low = min.unwrap_or(0).min(high);
+        let offset = match bounds {
+            Bounds::Valid | Bounds::Summed => self.random.between(low.into(), high.into()) as i64,
+            Bounds::BelowMin => min? - 1,
+            Bounds::AboveMax => max? + 1,
+        };
+        let datetime = base.checked_add_signed(TimeDelta::try_seconds(offset.checked_mul(unit)?)?)?;
+
+        // The formatting fails on patterns not matching the values, e.g. an offset for a date
+        let mut text = String::new();
+        match format.ctype.as_str() {
+            "date" => write!(text, "{}", datetime.date().format(&format.pattern)).ok()?,
+            "time" if datetime.date() == base.date() => {
+                write!(text, "{}", datetime.time().format(&format.pattern)).ok()?
+            }
+            "time" => return None,
+            _ if Format::has_offset(&format.pattern) => {
+                let datetime = timezone.from_local_datetime(&datetime).single()?;
+                write!(text, "{}", datetime.format(&format.pattern)).ok()?
+            }
+            _ => write!(text, "{}", datetime.format(&format.pattern)).ok()?,
+        }
+        Some(text)
+    }
+
+    /// Generate a string matching a regex, of a length in the range if possible
+    fn generate_matching(&mut self, pattern: &str, min_length: usize, max_length: usize) -> Option<String> {
+        let hir = self
+            .regexes
+            .entry(pattern.to_owned())
+            .or_insert_with(|| regex_syntax::Parser::new().parse(pattern).ok())
+            .as_ref()?;
+
+        let mut text = String::new();
+        for _ in 0..STRING_ATTEMPTS {
+            text.clear();
+            Self::generate_hir(&mut self.random, hir, max_length, &mut text);
+            if (min_length..=max_length).contains(&text.len()) {
+                break;
+            }
+        }
+        Some(text)
+    }
+
+    /// Append a string matching the regex, with the repetitions up to the maximum length when possible
+    /// and the printable ASCII characters of the classes
+    fn generate_hir(random: &mut Random, hir: &Hir, max_length: usize, text: &mut String) {
+        match hir.kind() {
+            HirKind::Empty | HirKind::Look(_) => (),
+            HirKind::Literal(literal) => text.push_str(&String::from_utf8_lossy(&literal.0)),
+            HirKind::Class(class) => {
+                let ranges: Vec<(u32, u32)> = match class {
+                    Class::Unicode(class) => {
+                        class.ranges().iter().map(|range| (range.start() as u32, range.end() as u32)).collect()
+                    }
+                    Class::Bytes(class) => {
+                        class.ranges().iter().map(|range| (range.start() as u32, range.end() as u32)).collect()
+                    }
+                };
+                let printable: Vec<(u32, u32)> = ranges
+                    .iter()
+                    .map(|&(start, end)| (start.max(' ' as u32), end.min('~' as u32)))
+                    .filter(|(start, end)| start <= end)
+                    .collect();
+                let ranges = if printable.is_empty() { ranges } else { printable };
+                if let Some(&(start, end)) = random.pick(&ranges) {
+                    let c = random.between(start.into(), end.into()) as u32;
+                    text.extend(char::from_u32(c).or(char::from_u32(start)));
+                }
+            }
+            HirKind::Repetition(repetition) => {
+                let min = repetition.min as usize;
+                let max = repetition.max.map_or(usize::MAX, |max| max as usize);
+                let available = max_length.saturating_sub(text.len());
+                let count = random.between(min as i128, max.min(available.max(min)) as i128);
+                for _ in 0..count {
+                    Self::generate_hir(random, &repetition.sub, max_length, text);
+                }
+            }
+            HirKind::Capture(capture) => Self::generate_hir(random, &capture.sub, max_length, text),
+            HirKind::Concat(hirs) => {
+                for hir in hirs {
+                    Self::generate_hir(random, hir, max_length, text);
+                }
+            }
+            HirKind::Alternation(hirs) => {
+                if let Some(hir) = random.pick(hirs) {
+                    Self::generate_hir(random, hir, max_length, text);
+                }
+            }
+        }
+    }
+
+    /// Set the cells of the control totals of a group or file from its lines
+    fn set_control_totals(
+        &mut self, plan: &[&'s Line], lines: &mut [GeneratedLine], range: Range<usize>, control_totals: &[ControlTotal],
+    ) -> Result<(), Error> {
+        for control_total in control_totals {
+            // The total is in the first line of its line type, if any
+            let Some(total_index) = range.clone().find(|&index| plan[index].linetype == control_total.linetype) else {
+                continue;
+            };
+
+            let total = match &control_total.aggregate {
+                Aggregate::Count { linetype } => {
+                    let count = range.clone().filter(|&index| &plan[index].linetype == linetype).count();
+                    Decimal::from_integer(count as i128)
+                }
+                Aggregate::Sum { linetype, cell } => {
+                    let mut sum = Decimal::default();
+                    for index in range.clone().filter(|&index| &plan[index].linetype == linetype) {
+                        let processed_line = self
+                            .schema
+                            .validate_line_ref(0, &lines[index].text)
+                            .map_err(|error| anyhow!("Invalid line {}: {}", linetype, error.message))?;
+                        let value = processed_line.cell_values.get(cell.as_str()).copied().unwrap_or_default();
+                        sum = Decimal::parse(value)
+                            .and_then(|value| sum.checked_add(value))
+                            .ok_or_else(|| anyhow!("Cannot sum the value {} of {}.{}", value, linetype, cell))?;
+                    }
+                    sum
+                }
+            };
+
+            let line = plan[total_index];
+            let cell_index = line.cell.iter().position(|cell| cell.name == control_total.cell);
+            let generated_line = cell_index.and_then(|cell_index| {
+                let cell = &line.cell[cell_index];
+                let mut cells = lines[total_index].cells.clone();
+                cells[cell_index] = render(cell, &Self::format_total(cell, total)?)?;
+                let generated_line = GeneratedLine::new(line, cells);
+                self.schema.validate_line_ref(0, &generated_line.text).is_ok().then_some(generated_line)
+            });
+            lines[total_index] = generated_line.ok_or_else(|| {
+                anyhow!("The total {} of the control total {} does not fit in its cell", total, control_total.rule)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write a total in the format of its cell, the cells without format are padded with zeros
+    fn format_total(cell: &Cell, total: Decimal) -> Option<String> {
+        let Some(format) = &cell.format else {
+            let total = total.to_string();
+            return match total.starts_with('-') {
+                true => Some(total),
+                false => Some(format!("{:0>width$}", total, width = cell.length)),
+            };
+        };
+        match format.ctype.as_str() {
+            "number" => {
+                let pattern = NumberPattern::parse(&format.pattern);
+                pattern.format(total.rescale(pattern.decimals())?, format.locale.as_ref())
+            }
+            "integer" => total.rescale(0).map(|total| total.to_string()),
+            "implieddecimal" => total.rescale(format.pattern.parse().ok()?).map(|total| total.to_string()),
+            "float" => {
+                let decimal_separator = format.locale.as_ref().map_or('.', |locale| locale.decimal_separator);
+                Some(total.to_string().replace('.', &decimal_separator.to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Inject the faults of the options, each in a different line chosen at random
+    fn inject_faults(&mut self, plan: &[&'s Line], lines: &mut [GeneratedLine]) -> Result<Vec<InjectedFault>, Error> {
+        let options = self.options;
+        let mut faults = Vec::new();
+        let mut line_indexes: Vec<usize> = (0..lines.len()).collect();
+        self.random.shuffle(&mut line_indexes);
+
+        for &(kind, count) in &options.faults {
+            // Line types where the fault could not be injected
+            let mut failed_linetypes = HashSet::new();
+            for injected_count in 0..count {
+                let mut injected = None;
+                for (position, &index) in line_indexes.iter().enumerate() {
+                    let line = plan[index];
+                    if failed_linetypes.contains(line.linetype.as_str()) {
+                        continue;
+                    }
+                    match self.inject_fault(kind, line, &lines[index]) {
+                        Some((text, message)) => {
+                            injected = Some((position, text, message));
+                            break;
+                        }
+                        None => {
+                            failed_linetypes.insert(line.linetype.as_str());
+                        }
+                    }
+                }
+
+                let (position, text, message) = injected.ok_or_else(|| {
+                    anyhow!("Cannot inject {} {:?} faults, no more lines can have them", count - injected_count, kind)
+                })?;
+                let index = line_indexes.swap_remove(position);
+                lines[index].text = text;
+                faults.push(InjectedFault { line_number: index + 1, kind, message });
+            }
+        }
+
+        faults.sort_by_key(|fault| fault.line_number);
+        Ok(faults)
+    }
+
+    /// Change a valid line until the parser reports the error of the fault
+    /// Returns the text of the line and the error message
+    fn inject_fault(
+        &mut self, kind: FaultKind, line: &Line, generated_line: &GeneratedLine,
+    ) -> Option<(String, String)> {
+        for _ in 0..MAX_ATTEMPTS {
+            let Some(text) = self.faulty_line(kind, line, generated_line) else {
+                continue;
+            };
+            if let Err(error) = self.schema.validate_line_ref(0, &text) {
+                if kind.error_codes().iter().any(|code| error.message.starts_with(&format!("[err:{}]", code))) {
+                    return Some((text, error.message));
+                }
+            }
+        }
+        None
+    }
+
+    /// Change a cell of the line, or its length, according to the fault
+    fn faulty_line(&mut self, kind: FaultKind, line: &Line, generated_line: &GeneratedLine) -> Option<String> {
+        if kind == FaultKind::LineLength {
+            if line.maxlength == 0 {
+                return None;
+            }
+            let mut text = generated_line.text.clone();
+            match self.random.between(0, 1) {
+                0 => text.push(line.padcharacter.chars().next().unwrap_or(' ')),
+                _ => {
+                    text.pop();
+                }
+            }
+            return Some(text);
+        }
+
+        let cell_indexes: Vec<usize> = (0..line.cell.len())
+            .filter(|&index| {
+                let cell = &line.cell[index];
+                match kind {
+                    FaultKind::UnknownLineType => cell.linecondition_pattern.is_some(),
+                    FaultKind::Format => cell.format.is_some() && cell.linecondition_pattern.is_none(),
+                    FaultKind::Mandatory => cell.mandatory,
+                    FaultKind::ValueRange => cell.minvalue.is_some() || cell.maxvalue.is_some(),
+                    FaultKind::AllowedValues => !cell.values.is_empty(),
+                    FaultKind::ValueLength => {
+                        cell.minlength > 1 || (cell.maxlength > 0 && cell.maxlength < cell.length)
+                    }
+                    FaultKind::LineLength => false,
+                }
+            })
+            .collect();
+        let &cell_index = self.random.pick(&cell_indexes)?;
+        let cell = &line.cell[cell_index];
+
+        let value = match kind {
+            FaultKind::UnknownLineType => self.alphanumeric(cell.length, cell.length),
+            FaultKind::Format => {
+                let length = self.random.between(1, cell.length as i128) as usize;
+                (0..length).filter_map(|_| self.random.pick(FAULT_CHARACTERS).map(|&c| c as char)).collect()
+            }
+            FaultKind::Mandatory => String::new(),
+            FaultKind::ValueRange => {
+                let bounds = match (&cell.minvalue, &cell.maxvalue, self.random.between(0, 1)) {
+                    (Some(_), Some(_), 0) | (Some(_), None, _) => Bounds::BelowMin,
+                    _ => Bounds::AboveMax,
+                };
+                self.generate_value(cell, bounds)?
+            }
+            FaultKind::AllowedValues => {
+                let unconstrained_cell = Cell { values: Vec::new(), ..cell.clone() };
+                self.generate_value(&unconstrained_cell, Bounds::Valid).filter(|value| !cell.values.contains(value))?
+            }
+            FaultKind::ValueLength => {
+                let length = match (cell.minlength > 1, self.random.between(0, 1)) {
+                    (true, 0) => cell.minlength - 1,
+                    _ if cell.maxlength > 0 && cell.maxlength < cell.length => cell.maxlength + 1,
+                    _ => cell.minlength - 1,
+                };
+                let resized_cell = Cell { minlength: length, maxlength: length, values: Vec::new(), ..cell.clone() };
+                self.generate_value(&resized_cell, Bounds::Valid)?
+            }
+            FaultKind::LineLength => return None,
+        };
+
+        let mut cells = generated_line.cells.clone();
+        cells[cell_index] = render(cell, &value)?;
+        Some(GeneratedLine::new(line, cells).text)
+    }
+}
+
+/// Path of the sample data of `example/fixedwidth_schema.xml` used by the tests, generated on first use.
+/// The file is not committed: a header, 10000 body lines and a footer, always the same for the seed.
+#[cfg(test)]
+pub(crate) fn sample_data() -> &'static str {
+    static GENERATED: std::sync::OnceLock<()> = std::sync::OnceLock::new();
+    const FILE_PATH: &str = "./example/fixedwidth_data.txt";
+    GENERATED.get_or_init(|| {
+        if std::path::Path::new(FILE_PATH).exists() {
+            return;
+        }
+        let schema = Schema::new("./example/fixedwidth_schema.xml").unwrap();
+        let occurs = [("Header", 1), ("Body", 10000), ("Footer", 1)];
+        let options = GenerateOptions {
+            seed: 1,
+            occurs: occurs.iter().map(|&(name, occurs)| (name.to_string(), occurs)).collect(),
+            ..Default::default()
+        };
+        // Written aside and renamed, so that no test reads a partial file
+        let temp_path = format!("{}.{}.tmp", FILE_PATH, std::process::id());
+        schema.generate(std::io::BufWriter::new(std::fs::File::create(&temp_path).unwrap()), &options).unwrap();
+        std::fs::rename(&temp_path, FILE_PATH).unwrap();
+    });
+    FILE_PATH
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::TestSchemaFile;
+    use crate::{Parser, ParserConfig, ProcessedRecord};
+
+    /// Write the generated file and parse it with the schema
+    fn parse_lines(name: &str, file_schema: &str, data: &[u8]) -> Vec<Result<String, (usize, String)>> {
+        let file_path = std::env::temp_dir().join(format!("rsapar_generate_{}_{}.txt", name, std::process::id()));
+        std::fs::write(&file_path, data).unwrap();
+        let config = ParserConfig {
+            file_path: file_path.to_str().unwrap().to_string(),
+            file_schema: file_schema.to_string(),
+            ..Default::default()
+        };
+        let mut parser = Parser::new(config).unwrap();
+        let lines = parser
+            .iter_mut()
+            .map(|line_result| line_result.map(|line| line.linetype).map_err(|err| (err.line_number, err.message)))
+            .collect();
+        std::fs::remove_file(&file_path).unwrap();
+        lines
+    }
+
+    #[test]
+    fn test_number_pattern() {
+        let pattern = NumberPattern::parse("#######0.00");
+        assert_eq!(pattern.decimals(), 2);
+        assert_eq!(pattern.format(1250, None).as_deref(), Some("12.50"));
+        assert_eq!(pattern.format(5, None).as_deref(), Some("0.05"));
+        assert_eq!(pattern.format(-1250, None).as_deref(), Some("-12.50"));
+        assert_eq!(pattern.format(10i128.pow(10), None), None);
+        assert_eq!(NumberPattern::parse("#,##0.00").format(123456, None).as_deref(), Some("1,234.56"));
+        let locale = Locale::new("de", "DE").unwrap();
+        assert_eq!(NumberPattern::parse("#,##0.0").format(12345, Some(&locale)).as_deref(), Some("1.234,5"));
+        assert_eq!(NumberPattern::parse("0'%'").format(7, None).as_deref(), Some("7%"));
+    }
+
+    #[test]
+    fn test_generate() {
+        let schema = Schema::new("./example/fixedwidth_schema.xml").unwrap();
+        let occurs = [("Header", 1), ("Body", 200), ("Footer", 1)];
+        let options = GenerateOptions {
+            seed: 7,
+            occurs: occurs.iter().map(|&(name, occurs)| (name.to_string(), occurs)).collect(),
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        let report = schema.generate(&mut data, &options).unwrap();
+        assert_eq!((report.lines, report.faults.len()), (202, 0));
+
+        let lines = parse_lines("valid", "./example/fixedwidth_schema.xml", &data);
+        assert!(lines.iter().all(Result::is_ok), "{:?}", lines.iter().find(|line| line.is_err()));
+        assert_eq!(lines.first(), Some(&Ok("Header".to_string())));
+        assert_eq!(lines.last(), Some(&Ok("Footer".to_string())));
+        assert_eq!(lines.iter().filter(|line| line == &&Ok("Body".to_string())).count(), 200);
+
+        // The same seed generates the same file
+        let mut same_data = Vec::new();
+        schema.generate(&mut same_data, &options).unwrap();
+        assert_eq!(data, same_data);
+        let mut other_data = Vec::new();
+        schema.generate(&mut other_data, &GenerateOptions { seed: 8, ..options }).unwrap();
+        assert_ne!(data, other_data);
+
+        // Booleans, integers within minvalue and maxvalue, floats, times, datetimes with a timezone and enums
+        let mut data = Vec::new();
+        let schema = Schema::new("./example/fixedwidth_schema_types.xml").unwrap();
+        schema.generate(&mut data, &GenerateOptions { repeat: 100, ..Default::default() }).unwrap();
+        let lines = parse_lines("types", "./example/fixedwidth_schema_types.xml", &data);
+        assert_eq!(lines.len(), 100);
+        assert!(lines.iter().all(Result::is_ok), "{:?}", lines.iter().find(|line| line.is_err()));
+    }
+
+    #[test]
+    fn test_generate_groups() {
+        let file_schema = "./example/fixedwidth_schema_groups.xml";
+        let schema = Schema::new(file_schema).unwrap();
+        let mut data = Vec::new();
+        let options = GenerateOptions { seed: 3, repeat: 3, ..Default::default() };
+        schema.generate(&mut data, &options).unwrap();
+
+        let file_path = std::env::temp_dir().join(format!("rsapar_generate_groups_{}.txt", std::process::id()));
+        std::fs::write(&file_path, &data).unwrap();
+        let config = ParserConfig {
+            file_path: file_path.to_str().unwrap().to_string(),
+            file_schema: file_schema.to_string(),
+            ..Default::default()
+        };
+        let mut parser = Parser::new(config).unwrap();
+        let records: Vec<_> = parser.groups().collect();
+        std::fs::remove_file(&file_path).unwrap();
+
+        // Header, 3 batches with their control totals and Footer with the count of batches
+        assert!(records.iter().all(Result::is_ok), "{:?}", records.iter().find(|record| record.is_err()));
+        let batches = records.iter().filter(|record| matches!(record, Ok(ProcessedRecord::Group(_)))).count();
+        assert_eq!((records.len(), batches), (5, 3));
+        assert!(String::from_utf8(data).unwrap().ends_with("\nF003"));
+
+        // All the lines have line conditions
+        let options = GenerateOptions { faults: vec![(FaultKind::UnknownLineType, 2)], ..options };
+        let report = schema.generate(std::io::sink(), &options).unwrap();
+        let messages: Vec<&str> = report.faults.iter().map(|fault| fault.message.as_str()).collect();
+        assert_eq!(messages, ["[err:001]|line|no match found for schema line type"; 2]);
+    }
+
+    #[test]
+    fn test_generate_faults() {
+        let xml = std::fs::read_to_string("./example/fixedwidth_schema_types.xml").unwrap();
+        let xml = xml
+            .replace(r#"<cell name="Active" length="1">"#, r#"<cell name="Active" length="1" values="Y|N">"#)
+            .replace(
+                r#"<cell name="Status" length="4">"#,
+                r#"<cell name="Status" length="4" mandatory="true" minlength="4">"#,
+            );
+        let schema_file = TestSchemaFile::new("generate_faults", &xml);
+        let schema = Schema::new(schema_file.path()).unwrap();
+
+        let faults = vec![
+            (FaultKind::Format, 3),
+            (FaultKind::Mandatory, 2),
+            (FaultKind::ValueRange, 2),
+            (FaultKind::AllowedValues, 2),
+            (FaultKind::ValueLength, 2),
+            (FaultKind::LineLength, 1),
+        ];
+        let options = GenerateOptions { seed: 11, repeat: 50, faults, ..Default::default() };
+        let mut data = Vec::new();
+        let report = schema.generate(&mut data, &options).unwrap();
+        assert_eq!((report.lines, report.faults.len()), (50, 12));
+        for fault in &report.faults {
+            assert!(fault.kind.error_codes().iter().any(|code| fault.message.starts_with(&format!("[err:{}]", code))));
+        }
+
+        // The parser reports the injected faults, and only them
+        let expected_errors: Vec<_> =
+            report.faults.iter().map(|fault| Err((fault.line_number, fault.message.to_owned()))).collect();
+        let errors: Vec<_> =
+            parse_lines("faults", schema_file.path(), &data).into_iter().filter(Result::is_err).collect();
+        assert_eq!(errors, expected_errors);
+
+        // Faults that no line can have
+        let options = GenerateOptions { faults: vec![(FaultKind::UnknownLineType, 1)], ..Default::default() };
+        let error = schema.generate(std::io::sink(), &options).unwrap_err();
+        assert_eq!(error.to_string(), "Cannot inject 1 UnknownLineType faults, no more lines can have them");
+    }
+}
@@ -0,0 +1,272 @@
+//! Schema includes and reusable cell groups.
+//!
+//! `<include href="common.xml"/>` inserts the content of the `<fixedwidthschema>` of another schema file
+//! (lines, groups, control totals and cell groups), resolved relative to the including file.
+//! `<cellgroup name="Address">` declares cells once, and `<cellgroupref name="Address"/>` inserts them in a line,
+//! with an optional `prefix` added to the cell names, e.g. `prefix="Billing"`.
+//!
+//! Both are expanded before the schema is loaded, so the errors of the included and referenced elements
+//! are reported at the position of the `<include>` or `<cellgroupref>`. Cyclic includes and cell groups are errors.
+
+use anyhow::{anyhow, Context, Error, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use xml::attribute::OwnedAttribute;
+use xml::common::{Position, TextPosition};
+use xml::name::OwnedName;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::jsapar;
+use crate::schema::error_at;
+
+/// An XML event with its position in the schema file
+type Event = (XmlEvent, Option<TextPosition>);
+
+/// Read the events of a schema file with its includes and cell groups expanded
+pub(crate) fn read_schema_events(path: &Path) -> Result<Vec<Event>, Error> {
+    let file = File::open(path)?;
+    let mut including = vec![path.canonicalize()?];
+    let events = read_events(file, path, &mut including)?;
+    expand_cell_groups(events)
+}
+
+/// Check if an element is a schema element with the given name
+fn is_element(name: &OwnedName, local_name: &str) -> bool {
+    name.local_name == local_name && jsapar::is_schema_name(name)
+}
+
+fn attribute(attributes: &[OwnedAttribute], name: &str) -> Option<String> {
+    attributes.iter().find(|attr| attr.name.local_name == name).map(|attr| attr.value.to_owned())
+}
+
+/// Read the events of a schema file, replacing the `<include>` elements by the content of the included files.
+/// `including` holds the files being read, the last one is `path`.
+fn read_events(file: File, path: &Path, including: &mut Vec<PathBuf>) -> Result<Vec<Event>, Error> {
+    let mut parser = EventReader::new(BufReader::new(file));
+    let mut events = Vec::new();
+    let mut skip_depth = 0; // Depth inside an <include>, its content is skipped
+
+    loop {
+        let event = parser.next().map_err(|e| Error::new(e).context("Error parsing XML"))?;
+        let position = Some(parser.position());
+        match event {
+            XmlEvent::EndDocument => return Ok(events),
+            XmlEvent::StartElement { .. } if skip_depth > 0 => skip_depth += 1,
+            XmlEvent::EndElement { .. } if skip_depth > 0 => skip_depth -= 1,
+            XmlEvent::StartElement { name, attributes, .. } if is_element(&name, "include") => {
+                skip_depth = 1;
+                let href = attribute(&attributes, "href")
+                    .ok_or_else(|| error_at(position, anyhow!("The include has no href")))?;
+                let included = include(path, &href, including).map_err(|e| error_at(position, e))?;
+                // Errors in the included elements are reported at the <include>
+                events.extend(included.into_iter().map(|(event, _)| (event, position)));
+            }
+            _ if skip_depth > 0 => (),
+            event => events.push((event, position)),
+        }
+    }
+}
+
+/// Read the content of the `<fixedwidthschema>` of an included file
+fn include(path: &Path, href: &str, including: &mut Vec<PathBuf>) -> Result<Vec<Event>, Error> {
+    let included_path = path.parent().unwrap_or(Path::new("")).join(href);
+    let file = File::open(&included_path).with_context(|| format!("Cannot read included schema {}", href))?;
+    let canonical_path = included_path.canonicalize()?;
+    if including.contains(&canonical_path) {
+        let chain: Vec<String> = including
+            .iter()
+            .chain([&canonical_path])
+            .filter_map(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+            .collect();
+        return Err(anyhow!("Cyclic include: {}", chain.join(" -> ")));
+    }
+
+    including.push(canonical_path);
+    let events = read_events(file, &included_path, including).with_context(|| format!("In {}", href))?;
+    including.pop();
+
+    let mut content = Vec::new();
+    let mut depth = 0;
+    let mut in_fixed_width_schema = false;
+    let mut found = false;
+    for (event, position) in events {
+        match &event {
+            // Children of the root element
+            XmlEvent::StartElement { name, .. } if depth == 1 => {
+                depth += 1;
+                in_fixed_width_schema = is_element(name, "fixedwidthschema");
+                found |= in_fixed_width_schema;
+                continue;
+            }
+            XmlEvent::StartElement { .. } => depth += 1,
+            XmlEvent::EndElement { .. } => {
+                depth -= 1;
+                if depth < 2 {
+                    continue;
+                }
+            }
+            _ => (),
+        }
+        if depth >= 2 && in_fixed_width_schema {
+            content.push((event, position));
+        }
+    }
+    match found {
+        true => Ok(content),
+        false => Err(anyhow!("The included schema {} has no <fixedwidthschema>", href)),
+    }
+}
+
+/// Take the events until the end of the current element, which is consumed
+fn take_element_content(events: &mut impl Iterator<Item = Event>) -> Vec<Event> {
+    let mut depth = 0;
+    let mut content = Vec::new();
+    for (event, position) in events {
+        match &event {
+            XmlEvent::StartElement { .. } => depth += 1,
+            XmlEvent::EndElement { .. } if depth == 0 => break,
+            XmlEvent::EndElement { .. } => depth -= 1,
+            _ => (),
+        }
+        content.push((event, position));
+    }
+    content
+}
+
+/// Remove the `<cellgroup>` elements and replace the `<cellgroupref>` elements by their cells
+fn expand_cell_groups(events: Vec<Event>) -> Result<Vec<Event>, Error> {
+    let mut cell_groups: HashMap<String, Vec<Event>> = HashMap::new();
+    let mut other_events = Vec::with_capacity(events.len());
+    let mut events = events.into_iter();
+    while let Some((event, position)) = events.next() {
+        match &event {
+            XmlEvent::StartElement { name, attributes, .. } if is_element(name, "cellgroup") => {
+                let group_name = attribute(attributes, "name")
+                    .ok_or_else(|| error_at(position, anyhow!("The cell group has no name")))?;
+                let cells = take_element_content(&mut events);
+                if cell_groups.insert(group_name.to_owned(), cells).is_some() {
+                    return Err(error_at(position, anyhow!("Duplicate cell group: {}", group_name)));
+                }
+            }
+            _ => other_events.push((event, position)),
+        }
+    }
+    expand_cell_group_refs(other_events, &cell_groups, "", &mut Vec::new())
+}
+
+/// Replace the `<cellgroupref>` elements by the cells of the groups, adding `prefix` to the names of the cells.
+/// `expanding` holds the cell groups being expanded.
+fn expand_cell_group_refs(
+    events: Vec<Event>, cell_groups: &HashMap<String, Vec<Event>>, prefix: &str, expanding: &mut Vec<String>,
+) -> Result<Vec<Event>, Error> {
+    let mut expanded = Vec::with_capacity(events.len());
+    let mut events = events.into_iter();
+    while let Some((mut event, position)) = events.next() {
+        match &mut event {
+            XmlEvent::StartElement { name, attributes, .. } if is_element(name, "cellgroupref") => {
+                take_element_content(&mut events);
+                let group_name = attribute(attributes, "name").unwrap_or_default();
+                let Some(cells) = cell_groups.get(&group_name) else {
+                    return Err(error_at(position, anyhow!("Unknown cell group: {}", group_name)));
+                };
+                if expanding.contains(&group_name) {
+                    let chain = format!("{} -> {}", expanding.join(" -> "), group_name);
+                    return Err(error_at(position, anyhow!("Cyclic cell group: {}", chain)));
+                }
+
+                let group_prefix = format!("{}{}", prefix, attribute(attributes, "prefix").unwrap_or_default());
+                expanding.push(group_name);
+                let cells = expand_cell_group_refs(cells.to_owned(), cell_groups, &group_prefix, expanding)?;
+                expanding.pop();
+                // Errors in the cells are reported at the <cellgroupref>
+                expanded.extend(cells.into_iter().map(|(event, _)| (event, position)));
+                continue;
+            }
+            // Fillers stay unnamed
+            XmlEvent::StartElement { name, attributes, .. } if is_element(name, "cell") && !prefix.is_empty() => {
+                for attr in
+                    attributes.iter_mut().filter(|attr| attr.name.local_name == "name" && !attr.value.is_empty())
+                {
+                    attr.value.insert_str(0, prefix);
+                }
+            }
+            _ => (),
+        }
+        expanded.push((event, position));
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Parser, ParserConfig, Schema};
+
+    #[test]
+    fn test_include() {
+        let config = ParserConfig {
+            file_path: "./example/fixedwidth_data_include.txt".to_string(),
+            file_schema: "./example/fixedwidth_schema_include.xml".to_string(),
+            ..Default::default()
+        };
+        let mut parser = Parser::new(config).unwrap();
+        let lines: Vec<_> = parser.iter_mut().map(|line_result| line_result.unwrap()).collect();
+        let linetypes: Vec<&str> = lines.iter().map(|line| line.linetype.as_str()).collect();
+        assert_eq!(linetypes, ["Header", "Customer", "Customer", "Delivery", "Footer"]);
+
+        // The cells of the group are inserted with the prefix of the reference
+        let cell_names: Vec<&str> = lines[3].cell_values.keys().map(String::as_str).collect();
+        assert_eq!(cell_names[..5], ["RecordType", "CustomerID", "BillingStreet", "BillingZip", "BillingCity"]);
+        assert_eq!(cell_names[5..], ["ShippingStreet", "ShippingZip", "ShippingCity"]);
+        assert_eq!(lines[3].cell_values["ShippingZip"], "54399");
+    }
+
+    #[test]
+    fn test_include_errors() {
+        let dir = std::env::temp_dir().join(format!("rsapar_include_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("common")).unwrap();
+        let load = |files: &[(&str, &str)]| {
+            for (name, xml) in files {
+                std::fs::write(dir.join(name), xml).unwrap();
+            }
+            Schema::new(dir.join(files[0].0).to_str().unwrap()).map_err(|e| format!("{:#}", e))
+        };
+        let schema = |content: &str| format!("<schema>\n  <fixedwidthschema>{}</fixedwidthschema>\n</schema>", content);
+        let line = |cells: &str| format!("<line linetype=\"A\"><cell name=\"Code\" length=\"1\"/>{}</line>", cells);
+
+        // Includes are resolved relative to the including file
+        let result = load(&[
+            ("main.xml", &schema(r#"<include href="common/a.xml"/>"#)),
+            ("common/a.xml", &schema(r#"<include href="b.xml"/>"#)),
+            ("common/b.xml", &schema(&line(""))),
+        ]);
+        assert_eq!(result.unwrap().get_line_by_linetype("A").unwrap().cell.len(), 1);
+
+        let result = load(&[
+            ("main.xml", &schema(r#"<include href="common/a.xml"/>"#)),
+            ("common/a.xml", &schema(r#"<include href="../main.xml"/>"#)),
+        ]);
+        assert_eq!(
+            result.unwrap_err(),
+            "line 2, column 21: In common/a.xml: line 2, column 21: Cyclic include: main.xml -> a.xml -> main.xml"
+        );
+
+        let result = load(&[("main.xml", &schema(r#"<include href="missing.xml"/>"#))]);
+        assert!(result.unwrap_err().starts_with("line 2, column 21: Cannot read included schema missing.xml"));
+
+        let result = load(&[("main.xml", &schema(&line(r#"<cellgroupref name="Address"/>"#)))]);
+        assert_eq!(result.unwrap_err(), "line 2, column 70: Unknown cell group: Address");
+
+        let cell_groups = r#"<cellgroup name="X"><cellgroupref name="Y"/></cellgroup>
+            <cellgroup name="Y"><cellgroupref name="X"/></cellgroup>"#;
+        let result = load(&[("main.xml", &schema(&format!("{}{}", cell_groups, line(r#"<cellgroupref name="X"/>"#))))]);
+        assert!(result.unwrap_err().ends_with("Cyclic cell group: X -> Y -> X"));
+
+        let cell_groups = r#"<cellgroup name="X"/><cellgroup name="X"/>"#;
+        let result = load(&[("main.xml", &schema(cell_groups))]);
+        assert_eq!(result.unwrap_err(), "line 2, column 42: Duplicate cell group: X");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,403 @@
+//! Schema inference from sample files, see `Schema::infer`.
+
+use anyhow::{anyhow, Error, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use indexmap::IndexMap;
+use std::collections::HashSet;
+use std::io::BufRead;
+
+use crate::{Cell, FixedWidthSchema, Format, Line, Schema};
+
+/// Kind of schema proposed by `Schema::infer`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InferFormat {
+    #[default]
+    Auto, // Fails on delimited samples: the lines have the same number of cells for a separator and different lengths
+    FixedWidth,
+}
+
+/// Options of `Schema::infer`
+#[derive(Debug, Clone)]
+pub struct InferOptions {
+    pub format: InferFormat,
+    pub max_lines: usize,     // Lines read from the sample (default 1000)
+    pub max_linetypes: usize, // Line types proposed from the leading characters (default 10)
+}
+
+impl Default for InferOptions {
+    fn default() -> Self {
+        Self { format: InferFormat::Auto, max_lines: 1000, max_linetypes: 10 }
+    }
+}
+
+/// Cell separators tried to recognize delimited samples
+const CSV_SEPARATORS: [char; 4] = [',', ';', '\t', '|'];
+
+/// Date and datetime patterns tried for the format guesses: (chrono pattern, length of the values)
+const DATE_PATTERNS: [(&str, usize); 4] = [("%Y%m%d", 8), ("%Y-%m-%d", 10), ("%d/%m/%Y", 10), ("%d.%m.%Y", 10)];
+const DATETIME_PATTERNS: [(&str, usize); 3] =
+    [("%Y%m%d%H%M%S", 14), ("%Y-%m-%d %H:%M:%S", 19), ("%Y-%m-%dT%H:%M:%S", 19)];
+
+/// Class of the characters of a column: letters and digits, the other characters are ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum CharClass {
+    Digit,
+    Alpha,
+}
+
+impl Schema {
+    /// Propose a schema from a sample of the data, to be reviewed and completed by hand.
+    ///
+    /// Fixed width: the line types are resolved from a stable leading character (e.g. "H" for the header), which
+    /// becomes a "string" line condition, and the lines without it share a line type without condition. The cells
+    /// are split where a column of spaces ends or the characters change between letters and digits in all the lines.
+    ///
+    /// Delimited samples, with the same number of cells for a separator in all the lines, are not supported until the
+    /// parser supports CSV schemas: `InferFormat::Auto` fails on them and `InferFormat::FixedWidth` forces a fixed
+    /// width schema.
+    ///
+    /// The format of the cells is guessed from their values: date, datetime, integer or number.
+    pub fn infer<R: BufRead>(mut sample_reader: R, options: InferOptions) -> Result<Self, Error> {
+        let mut lines = Vec::new();
+        let mut lineseparator = "\\n";
+        let mut has_empty_lines = false;
+        let mut buf = String::new();
+        while lines.len() < options.max_lines && sample_reader.read_line(&mut buf)? > 0 {
+            if buf.ends_with("\r\n") {
+                lineseparator = "\\r\\n";
+            }
+            let line = buf.trim_end_matches(['\r', '\n']);
+            match line.is_empty() {
+                true => has_empty_lines = true,
+                false => lines.push(line.to_owned()),
+            }
+            buf.clear();
+        }
+        if lines.is_empty() {
+            return Err(anyhow!("The sample has no lines"));
+        }
+
+        let same_length = lines.iter().all(|line| line.len() == lines[0].len());
+        if let (InferFormat::Auto, Some(cellseparator), false) =
+            (options.format, Self::infer_csv_separator(&lines), same_length)
+        {
+            return Err(anyhow!(
+                "The sample looks delimited by '{}', but only fixed width schemas are supported: \
+                 use InferFormat::FixedWidth to propose one",
+                cellseparator
+            ));
+        }
+
+        let mut fixed_width_schema = Self::infer_fixed_width_schema(&lines, options.max_linetypes);
+        fixed_width_schema.lineseparator = lineseparator.to_string();
+        fixed_width_schema.ignoreemptylines = has_empty_lines;
+        Ok(Schema { fixedwidthschema: Some(fixed_width_schema), csvschema: None, lint_issues: Vec::new() })
+    }
+
+    fn infer_fixed_width_schema(lines: &[String], max_linetypes: usize) -> FixedWidthSchema {
+        // Lines starting with a letter or a symbol are grouped by it, the others have no line condition
+        let mut groups: IndexMap<Option<char>, Vec<&str>> = IndexMap::new();
+        for line in lines {
+            let first_char = line.chars().next().filter(|c| c.is_ascii() && !c.is_ascii_digit() && *c != ' ');
+            groups.entry(first_char).or_default().push(line);
+        }
+        // Too many leading characters are values, not line types, e.g. names
+        if groups.len() < 2 || groups.len() > max_linetypes || groups.len() * 2 > lines.len() {
+            groups = IndexMap::from([(None, lines.iter().map(String::as_str).collect())]);
+        }
+
+        // The line condition is the prefix shared by the lines of each group, up to 4 characters
+        let condition_length = groups
+            .iter()
+            .filter(|(first_char, group_lines)| first_char.is_some() && group_lines.len() > 1)
+            .map(|(_, group_lines)| Self::common_prefix_length(group_lines))
+            .min()
+            .unwrap_or(1)
+            .clamp(1, 4);
+
+        let first_line = lines.first().map(String::as_str);
+        let last_line = lines.last().map(String::as_str);
+        // A single line with a condition at the start or the end of the sample is a header or a footer
+        let linetypes: Vec<&str> = groups
+            .iter()
+            .map(|(first_char, group_lines)| match (first_char, group_lines.as_slice()) {
+                (Some(_), [line]) if Some(*line) == first_line => "Header",
+                (Some(_), [line]) if Some(*line) == last_line => "Footer",
+                _ => "Detail",
+            })
+            .collect();
+        let group_count = groups.len();
+        let detail_count = linetypes.iter().filter(|linetype| **linetype == "Detail").count();
+        let mut detail_number = 0;
+
+        let schema_lines = groups
+            .into_iter()
+            .zip(linetypes)
+            .map(|((first_char, group_lines), linetype)| {
+                let condition = first_char
+                    .map(|_| group_lines[0].get(..condition_length).unwrap_or(group_lines[0]).trim_end().to_owned());
+                let (linetype, occurs) = match linetype {
+                    "Detail" if group_count == 1 => ("Record".to_string(), "*"),
+                    "Detail" if detail_count == 1 => ("Detail".to_string(), "*"),
+                    "Detail" => {
+                        detail_number += 1;
+                        (format!("Detail{}", detail_number), "*")
+                    }
+                    linetype => (linetype.to_string(), "1"),
+                };
+                Self::infer_line(&group_lines, linetype, occurs, condition)
+            })
+            .collect();
+
+        FixedWidthSchema { lines: schema_lines, ..Default::default() }
+    }
+
+    /// Propose the cells of the lines of a line type
+    fn infer_line(lines: &[&str], linetype: String, occurs: &str, condition: Option<String>) -> Line {
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+        let condition_length = condition.as_ref().map_or(0, String::len);
+        let boundaries = Self::column_boundaries(lines, width, condition_length);
+
+        let mut cells = Vec::new();
+        if let Some(condition) = &condition {
+            cells.push(Cell {
+                name: "RecordType".to_string(),
+                length: condition_length,
+                end: condition_length,
+                linecondition_type: Some("string".to_string()),
+                linecondition_pattern: Some(condition.to_owned()),
+                padcharacter: " ".to_string(),
+                ..Default::default()
+            });
+        }
+        for (index, bounds) in boundaries.windows(2).skip(usize::from(condition.is_some())).enumerate() {
+            let (start, end) = (bounds[0], bounds[1]);
+            let values: Vec<&str> = lines.iter().map(|line| line.get(start..end).unwrap_or("").trim()).collect();
+            cells.push(Cell {
+                name: format!("Field{}", index + 1),
+                length: end - start,
+                start,
+                end,
+                format: Self::guess_format(&values),
+                padcharacter: " ".to_string(),
+                ..Default::default()
+            });
+        }
+
+        let same_length = lines.iter().all(|line| line.len() == width);
+        Line {
+            linetype,
+            maxlength: if same_length { width } else { 0 },
+            occurs: occurs.to_string(),
+            cell: cells,
+            padcharacter: " ".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Length of the ASCII prefix shared by the lines
+    fn common_prefix_length(lines: &[&str]) -> usize {
+        let first = lines[0].as_bytes();
+        lines
+            .iter()
+            .map(|line| first.iter().zip(line.as_bytes()).take_while(|(a, b)| a == b && a.is_ascii()).count())
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Positions where the cells start, from 0 to the width of the lines:
+    /// after a column of spaces, or where all the lines change between letters and digits
+    fn column_boundaries(lines: &[&str], width: usize, condition_length: usize) -> Vec<usize> {
+        let column_classes: Vec<HashSet<CharClass>> = (0..width)
+            .map(|position| {
+                lines
+                    .iter()
+                    .filter_map(|line| match line.as_bytes().get(position) {
+                        Some(c) if c.is_ascii_digit() => Some(CharClass::Digit),
+                        Some(c) if c.is_ascii_alphabetic() || !c.is_ascii() => Some(CharClass::Alpha),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect();
+        let is_space_column =
+            |position: usize| lines.iter().all(|line| line.as_bytes().get(position).map_or(true, |c| *c == b' '));
+
+        let mut boundaries = vec![0];
+        for position in 1..width {
+            let (previous, current) = (&column_classes[position - 1], &column_classes[position]);
+            let field_start = is_space_column(position - 1) && !is_space_column(position);
+            let class_change = !previous.is_empty()
+                && !current.is_empty()
+                && previous != current
+                && (previous.len() == 1 || current.len() == 1);
+            let char_boundary = lines.iter().all(|line| line.is_char_boundary(position.min(line.len())));
+            if (field_start || class_change || position == condition_length) && char_boundary {
+                boundaries.push(position);
+            }
+        }
+        boundaries.push(width);
+        boundaries
+    }
+
+    /// Guess the format of the trimmed values of a cell, None for text
+    fn guess_format(values: &[&str]) -> Option<Format> {
+        let values: Vec<&str> = values.iter().copied().filter(|value| !value.is_empty()).collect();
+        if values.is_empty() {
+            return None;
+        }
+        let format = |ctype: &str, pattern: &str| {
+            Some(Format { ctype: ctype.to_string(), pattern: pattern.to_string(), ..Default::default() })
+        };
+        let all_match = |length: usize, parse: &dyn Fn(&str) -> bool| {
+            values.iter().all(|value| value.len() == length && parse(value))
+        };
+
+        for (pattern, length) in DATE_PATTERNS {
+            if all_match(length, &|value| NaiveDate::parse_from_str(value, pattern).is_ok()) {
+                return format("date", pattern);
+            }
+        }
+        for (pattern, length) in DATETIME_PATTERNS {
+            if all_match(length, &|value| NaiveDateTime::parse_from_str(value, pattern).is_ok()) {
+                return format("datetime", pattern);
+            }
+        }
+        if values.iter().all(|value| value.parse::<i64>().is_ok()) {
+            return format("integer", "");
+        }
+
+        // Numbers with the same number of decimals have a DecimalFormat pattern, the others are floats
+        let decimals: Option<Vec<(usize, usize)>> = values
+            .iter()
+            .map(|value| {
+                let (integer, fraction) = value.trim_start_matches('-').split_once('.')?;
+                let digits = |part: &str| !part.is_empty() && part.bytes().all(|c| c.is_ascii_digit());
+                (digits(integer) && digits(fraction)).then_some((integer.len(), fraction.len()))
+            })
+            .collect();
+        match decimals {
+            Some(decimals) if decimals.iter().all(|(_, fraction)| *fraction == decimals[0].1) => {
+                let integer_digits = decimals.iter().map(|(integer, _)| *integer).max().unwrap_or(1);
+                let pattern = format!("{}0.{}", "#".repeat(integer_digits - 1), "0".repeat(decimals[0].1));
+                format("number", &pattern)
+            }
+            Some(_) => format("float", ""),
+            None => None,
+        }
+    }
+
+    /// The separator giving the same number of cells, more than one, in all the lines
+    fn infer_csv_separator(lines: &[String]) -> Option<char> {
+        CSV_SEPARATORS
+            .into_iter()
+            .filter_map(|separator| {
+                let cell_count = Self::split_csv_line(&lines[0], separator).len();
+                let same_count = lines.iter().all(|line| Self::split_csv_line(line, separator).len() == cell_count);
+                (cell_count > 1 && same_count).then_some((separator, cell_count))
+            })
+            .max_by_key(|(_, cell_count)| *cell_count)
+            .map(|(separator, _)| separator)
+    }
+
+    /// Split a CSV line, the separators between double quotes are part of the values
+    fn split_csv_line(line: &str, separator: char) -> Vec<String> {
+        let mut cells = vec![String::new()];
+        let mut in_quotes = false;
+        for c in line.chars() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                c if c == separator && !in_quotes => cells.push(String::new()),
+                c => cells.last_mut().unwrap().push(c),
+            }
+        }
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Describe the lines of a fixed width schema: "Linetype[condition]: Cell(start-end format pattern), ..."
+    fn describe(schema: &Schema) -> Vec<String> {
+        let fixed_width_schema = schema.fixedwidthschema.as_ref().unwrap();
+        fixed_width_schema
+            .lines
+            .iter()
+            .map(|line| {
+                let cells: Vec<String> = line
+                    .cell
+                    .iter()
+                    .map(|cell| match (&cell.format, &cell.linecondition_pattern) {
+                        (_, Some(condition)) => format!("{}={}", cell.name, condition),
+                        (Some(format), _) => {
+                            format!("{}({}-{} {} {})", cell.name, cell.start, cell.end, format.ctype, format.pattern)
+                        }
+                        (None, None) => format!("{}({}-{})", cell.name, cell.start, cell.end),
+                    })
+                    .collect();
+                format!("{} {} {}: {}", line.linetype, line.occurs, line.maxlength, cells.join(", "))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_infer_fixed_width() {
+        let data = [
+            "H20240524TTTTTTTTTTT",
+            "000000000002.00jhl01@6u24.com",
+            "000100000002.00475pw@cj14.com",
+            "000200000002.00sjzuv@qzcv.com",
+            "000300000002.007xy6z@eqia.com",
+            "000400000002.005cxbv@t5zr.com",
+            "F11WWW110000000000.00",
+        ];
+        let schema = Schema::infer(data.join("\n").as_bytes(), InferOptions::default()).unwrap();
+        assert_eq!(
+            describe(&schema),
+            [
+                "Header 1 20: RecordType=H, Field1(1-9 date %Y%m%d), Field2(9-20)",
+                "Detail * 29: Field1(0-15 number ###########0.00), Field2(15-29)",
+                "Footer 1 21: RecordType=F, Field1(1-3 integer ), Field2(3-6), Field3(6-21 number ###########0.00)",
+            ]
+        );
+
+        // The proposed schema validates its sample
+        for (line_number, line) in data.iter().enumerate() {
+            assert!(schema.validate_line(line_number + 1, line.to_string()).is_ok(), "{}", line);
+        }
+
+        // Names are values, not line types; right aligned numbers and text are split at the columns of spaces
+        let sample = [
+            "Anna      Berg         1234.50Y19800101",
+            "Bo        Ek              0.00N20011231",
+            "Carl      Lind           99.90Y19751224",
+            "Dora      Nilsson      5000.00N19900101",
+        ];
+        let schema = Schema::infer(sample.join("\r\n").as_bytes(), InferOptions::default()).unwrap();
+        assert_eq!(
+            describe(&schema),
+            ["Record * 39: Field1(0-10), Field2(10-23), Field3(23-30 number ###0.00), Field4(30-31), \
+              Field5(31-39 date %Y%m%d)"]
+        );
+        assert_eq!(schema.get_newline_characters(), "\\r\\n");
+        assert!(sample.iter().all(|line| schema.validate_line(1, line.to_string()).is_ok()));
+    }
+
+    #[test]
+    fn test_infer_delimited() {
+        // Delimited samples are not proposed a schema the parser cannot use
+        let sample = "id;name;amount;created\n1;Anna;12.50;2024-05-24\n2;\"Berg; Bo\";7.00;2024-05-25\n";
+        let error = Schema::infer(sample.as_bytes(), InferOptions::default()).unwrap_err();
+        assert!(error.to_string().starts_with("The sample looks delimited by ';'"), "{}", error);
+
+        // Fixed width lines can be forced
+        let sample = "a,1\nbb,22\n";
+        assert!(Schema::infer(sample.as_bytes(), InferOptions::default()).is_err());
+        let options = InferOptions { format: InferFormat::FixedWidth, ..Default::default() };
+        assert!(Schema::infer(sample.as_bytes(), options).unwrap().fixedwidthschema.is_some());
+
+        assert!(Schema::infer("".as_bytes(), InferOptions::default()).is_err());
+    }
+}
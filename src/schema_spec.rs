@@ -0,0 +1,362 @@
+//! Layout documentation of a schema as Markdown or HTML tables, see `Schema::to_markdown` and `Schema::to_html`.
+
+use crate::{Cell, Line, Schema};
+
+/// Text of a table cell, rendered as code for the patterns and values of the schema
+enum Text {
+    Plain(String),
+    Code(String),
+}
+
+/// A section of the documentation: a line type with its cells
+struct Section {
+    title: String,
+    summary: Vec<Text>,
+    rows: Vec<Vec<Text>>,
+}
+
+/// Documentation of a schema, rendered by `render_markdown` or `render_html`
+struct Spec {
+    title: String,
+    summary: Vec<Text>,
+    header: &'static [&'static str],
+    sections: Vec<Section>,
+    controltotals: Vec<String>,
+}
+
+const FIXED_WIDTH_HEADER: &[&str] =
+    &["Cell", "Start", "End", "Length", "Alignment", "Pad", "Format", "Condition", "Constraints"];
+const CSV_HEADER: &[&str] = &["Cell", "Column", "Format", "Condition", "Constraints"];
+
+/// Describe the pad character, e.g. "space" or "0"
+fn describe_padcharacter(padcharacter: &str) -> String {
+    match padcharacter {
+        " " => "space".to_string(),
+        "" => "none".to_string(),
+        padcharacter => padcharacter.to_owned(),
+    }
+}
+
+/// Format type and pattern of a cell, e.g. `number #0.00`
+fn describe_format(cell: &Cell) -> Text {
+    match &cell.format {
+        Some(format) if format.pattern.is_empty() => Text::Code(format.ctype.to_owned()),
+        Some(format) => Text::Code(format!("{} {}", format.ctype, format.pattern)),
+        None => Text::Plain(String::new()),
+    }
+}
+
+/// Line condition of a cell: the literal value, or "regex" with its pattern
+fn describe_condition(cell: &Cell) -> Text {
+    match (cell.linecondition_type.as_deref(), cell.linecondition_pattern.as_deref()) {
+        (Some("string"), Some(pattern)) => Text::Code(pattern.to_owned()),
+        (_, Some(pattern)) => Text::Code(format!("regex {}", pattern)),
+        (_, None) => Text::Plain(String::new()),
+    }
+}
+
+/// Constraints of a cell, e.g. "mandatory, values A|B, default 0"
+fn describe_constraints(cell: &Cell) -> Text {
+    let mut constraints = Vec::new();
+    if cell.name.is_empty() {
+        constraints.push("filler".to_string());
+    }
+    if cell.mandatory {
+        constraints.push("mandatory".to_string());
+    }
+    if cell.ignoreread && !cell.name.is_empty() {
+        constraints.push("ignored".to_string());
+    }
+    if !cell.values.is_empty() {
+        constraints.push(format!("values {}", cell.values.join("|")));
+    }
+    if let Some(minvalue) = &cell.minvalue {
+        constraints.push(format!("min {}", minvalue));
+    }
+    if let Some(maxvalue) = &cell.maxvalue {
+        constraints.push(format!("max {}", maxvalue));
+    }
+    if cell.minlength > 0 {
+        constraints.push(format!("minlength {}", cell.minlength));
+    }
+    if cell.maxlength > 0 {
+        constraints.push(format!("maxlength {}", cell.maxlength));
+    }
+    if let Some(emptypattern) = &cell.emptypattern {
+        constraints.push(format!("empty {}", emptypattern));
+    }
+    if let Some(default) = &cell.default {
+        constraints.push(format!("default {}", default));
+    }
+    Text::Plain(constraints.join(", "))
+}
+
+/// Summary of a line, e.g. "Occurs: *, length: 20", or "Occurs: any" without an occurs
+fn describe_line(line: &Line) -> Vec<Text> {
+    let occurs = match line.occurs.as_str() {
+        "" => Text::Plain("any".to_string()),
+        occurs => Text::Code(occurs.to_string()),
+    };
+    let mut summary = vec![Text::Plain("Occurs: ".to_string()), occurs];
+    if line.maxlength > 0 {
+        summary.push(Text::Plain(format!(", length: {}", line.maxlength)));
+    }
+    if line.allowoverlaps {
+        summary.push(Text::Plain(", overlapping cells".to_string()));
+    }
+    summary
+}
+
+impl Schema {
+    /// Documentation of the layout as Markdown: a table per line type with the name, the 1-based start and end
+    /// positions, the length, the alignment, the pad character, the format, the line condition and the constraints
+    /// of each cell.
+    pub fn to_markdown(&self) -> String {
+        render_markdown(&self.spec())
+    }
+
+    /// Documentation of the layout as an HTML document, with the same tables as `Schema::to_markdown`
+    pub fn to_html(&self) -> String {
+        render_html(&self.spec())
+    }
+
+    fn spec(&self) -> Spec {
+        match (&self.fixedwidthschema, &self.csvschema) {
+            (Some(fixed_width_schema), _) => {
+                let mut summary = vec![
+                    Text::Plain("Fixed width, line separator ".to_string()),
+                    Text::Code(fixed_width_schema.lineseparator.to_owned()),
+                ];
+                if fixed_width_schema.recordlength > 0 {
+                    summary.push(Text::Plain(format!(", record length {}", fixed_width_schema.recordlength)));
+                }
+                if fixed_width_schema.ignoreemptylines {
+                    summary.push(Text::Plain(", empty lines ignored".to_string()));
+                }
+                if let Some(locale) = &fixed_width_schema.locale {
+                    summary.push(Text::Plain(", locale ".to_string()));
+                    summary.push(Text::Code(format!("{}_{}", locale.language, locale.country)));
+                }
+
+                let sections = fixed_width_schema
+                    .lines
+                    .iter()
+                    .map(|line| Section {
+                        title: line.linetype.to_owned(),
+                        summary: describe_line(line),
+                        rows: line
+                            .cell
+                            .iter()
+                            .map(|cell| {
+                                vec![
+                                    Text::Plain(cell.name.to_owned()),
+                                    Text::Plain((cell.start + 1).to_string()),
+                                    Text::Plain(cell.end.to_string()),
+                                    Text::Plain(cell.length.to_string()),
+                                    Text::Plain(cell.get_alignment().to_owned()),
+                                    Text::Plain(describe_padcharacter(&cell.padcharacter)),
+                                    describe_format(cell),
+                                    describe_condition(cell),
+                                    describe_constraints(cell),
+                                ]
+                            })
+                            .collect(),
+                    })
+                    .collect();
+
+                let mut controltotals: Vec<String> =
+                    fixed_width_schema.controltotals.iter().map(|total| total.rule.to_owned()).collect();
+                let mut pending_groups: Vec<_> = fixed_width_schema.groups.iter().collect();
+                while let Some(group) = pending_groups.pop() {
+                    let rules =
+                        group.controltotals.iter().map(|total| format!("{} (group {})", total.rule, group.name));
+                    controltotals.extend(rules);
+                    pending_groups.extend(group.items.iter().filter_map(|item| match item {
+                        crate::GroupItem::Group(nested_group) => Some(nested_group),
+                        crate::GroupItem::Line(_) => None,
+                    }));
+                }
+
+                Spec {
+                    title: "Fixed width schema".to_string(),
+                    summary,
+                    header: FIXED_WIDTH_HEADER,
+                    sections,
+                    controltotals,
+                }
+            }
+            (None, Some(csv_schema)) => {
+                let mut summary = vec![
+                    Text::Plain("CSV, line separator ".to_string()),
+                    Text::Code(csv_schema.lineseparator.to_owned()),
+                    Text::Plain(", cell separator ".to_string()),
+                    Text::Code(csv_schema.cellseparator.to_owned()),
+                ];
+                if csv_schema.firstlineasschema {
+                    summary.push(Text::Plain(", the first line has the names of the cells".to_string()));
+                }
+
+                let sections = csv_schema
+                    .lines
+                    .iter()
+                    .map(|line| Section {
+                        title: line.linetype.to_owned(),
+                        summary: describe_line(line),
+                        rows: line
+                            .cell
+                            .iter()
+                            .enumerate()
+                            .map(|(index, cell)| {
+                                vec![
+                                    Text::Plain(cell.name.to_owned()),
+                                    Text::Plain((index + 1).to_string()),
+                                    describe_format(cell),
+                                    describe_condition(cell),
+                                    describe_constraints(cell),
+                                ]
+                            })
+                            .collect(),
+                    })
+                    .collect();
+
+                Spec {
+                    title: "CSV schema".to_string(),
+                    summary,
+                    header: CSV_HEADER,
+                    sections,
+                    controltotals: Vec::new(),
+                }
+            }
+            (None, None) => Spec {
+                title: "Empty schema".to_string(),
+                summary: Vec::new(),
+                header: FIXED_WIDTH_HEADER,
+                sections: Vec::new(),
+                controltotals: Vec::new(),
+            },
+        }
+    }
+}
+
+/// Render a text in a Markdown table, escaping the "|" separators
+fn markdown_text(text: &Text) -> String {
+    match text {
+        Text::Plain(text) => text.replace('|', "\\|"),
+        Text::Code(text) if text.contains('`') => format!("`` {} ``", text.replace('|', "\\|")),
+        Text::Code(text) => format!("`{}`", text.replace('|', "\\|")),
+    }
+}
+
+fn render_markdown(spec: &Spec) -> String {
+    let join = |texts: &[Text]| texts.iter().map(markdown_text).collect::<String>();
+    let mut markdown = format!("# {}\n\n{}\n", spec.title, join(&spec.summary));
+    for section in &spec.sections {
+        markdown.push_str(&format!("\n## {}\n\n{}\n\n", section.title, join(&section.summary)));
+        markdown.push_str(&format!("| {} |\n", spec.header.join(" | ")));
+        markdown.push_str(&format!("|{}\n", "---|".repeat(spec.header.len())));
+        for row in &section.rows {
+            let row: Vec<String> = row.iter().map(markdown_text).collect();
+            markdown.push_str(&format!("| {} |\n", row.join(" | ")));
+        }
+    }
+    if !spec.controltotals.is_empty() {
+        markdown.push_str("\n## Control totals\n\n");
+        for rule in &spec.controltotals {
+            markdown.push_str(&format!("- {}\n", markdown_text(&Text::Code(rule.to_owned()))));
+        }
+    }
+    markdown
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Render a text in HTML, escaping the special characters
+fn html_text(text: &Text) -> String {
+    match text {
+        Text::Plain(text) => escape_html(text),
+        Text::Code(text) => format!("<code>{}</code>", escape_html(text)),
+    }
+}
+
+fn render_html(spec: &Spec) -> String {
+    let join = |texts: &[Text]| texts.iter().map(html_text).collect::<String>();
+    let title = escape_html(&spec.title);
+    let mut html = format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n", title);
+    html.push_str(&format!("</head>\n<body>\n<h1>{}</h1>\n<p>{}</p>\n", title, join(&spec.summary)));
+    for section in &spec.sections {
+        html.push_str(&format!(
+            "<h2>{}</h2>\n<p>{}</p>\n<table>\n",
+            escape_html(&section.title),
+            join(&section.summary)
+        ));
+        let header: String = spec.header.iter().map(|name| format!("<th>{}</th>", name)).collect();
+        html.push_str(&format!("<tr>{}</tr>\n", header));
+        for row in &section.rows {
+            let row: String = row.iter().map(|text| format!("<td>{}</td>", html_text(text))).collect();
+            html.push_str(&format!("<tr>{}</tr>\n", row));
+        }
+        html.push_str("</table>\n");
+    }
+    if !spec.controltotals.is_empty() {
+        html.push_str("<h2>Control totals</h2>\n<ul>\n");
+        for rule in &spec.controltotals {
+            html.push_str(&format!("<li><code>{}</code></li>\n", escape_html(rule)));
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_spec() {
+        let schema = Schema::new("./example/fixedwidth_schema.xml").unwrap();
+        let markdown = schema.to_markdown();
+        let expected = r#"# Fixed width schema
+
+Fixed width, line separator `\n`
+
+## Header
+
+Occurs: `*`, length: 20
+
+| Cell | Start | End | Length | Alignment | Pad | Format | Condition | Constraints |
+|---|---|---|---|---|---|---|---|---|
+| RecordType | 1 | 1 | 1 | left | space |  | `H` |  |
+| RecordDate | 2 | 9 | 8 | left | space | `date %Y%m%d` |  |  |
+| FileName | 10 | 20 | 11 | left | space | `string .*` |  |  |
+"#;
+        assert!(markdown.starts_with(expected), "{}", markdown);
+        assert!(markdown.contains("| Amount | 5 | 15 | 11 | right | space | `number #######0.00` |  |  |\n"));
+        assert!(markdown.contains("| FileName | 2 | 8 | 7 | center | 11 | `string .*` |  |  |\n"));
+
+        let html = schema.to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h2>Body</h2>\n<p>Occurs: <code>*</code>, length: 29</p>\n<table>\n"));
+        assert!(html.contains("<td>email</td><td>16</td><td>29</td><td>14</td><td>right</td>"));
+        assert!(html.ends_with("</table>\n</body>\n</html>\n"));
+
+        // Pipes are escaped in the Markdown tables, fillers and control totals are described
+        let schema = Schema::new("./example/fixedwidth_schema_types.xml").unwrap();
+        assert!(schema.to_markdown().contains(" | `enum NEW\\|OPEN\\|DONE` | "));
+        let schema = Schema::new("./example/fixedwidth_schema_groups.xml").unwrap();
+        let markdown = schema.to_markdown();
+        assert!(markdown.contains("\n## Control totals\n\n- `"), "{}", markdown);
+        let schema = Schema::new("./example/fixedwidth_schema_filler.xml").unwrap();
+        let markdown = schema.to_markdown();
+        assert!(markdown.contains("|  | 5 | 8 | 4 | left | space |  |  | filler |\n"), "{}", markdown);
+        assert!(markdown.contains("| Note | 15 | 20 | 6 | left | space |  |  | ignored |\n"), "{}", markdown);
+
+        // Lines without an occurs have no empty code span
+        let mut schema = Schema::new("./example/fixedwidth_schema.xml").unwrap();
+        schema.fixedwidthschema.as_mut().unwrap().lines[0].occurs = String::new();
+        assert!(schema.to_markdown().contains("\n## Header\n\nOccurs: any, length: 20\n"));
+        assert!(schema.to_html().contains("<h2>Header</h2>\n<p>Occurs: any, length: 20</p>\n"));
+    }
+}